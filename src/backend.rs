@@ -1,14 +1,24 @@
-use crate::{Config, Content, ProxyColletion};
-use dashmap::DashMap;
+use crate::{tempdir::TempDir, Config, Content, ProxyColletion};
+use dashmap::{DashMap, DashSet};
 use smol::{fs, io, lock::OnceCell};
-use std::{collections::HashMap, env, path::PathBuf};
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tower_lsp::{jsonrpc, lsp_types as lsp, Client, LanguageServer};
 
 pub struct Backend {
-    pub tempdir: OnceCell<PathBuf>,
+    pub tempdir: OnceCell<TempDir>,
     pub client: Client,
+    pub client_capabilities: OnceCell<lsp::ClientCapabilities>,
     pub files: DashMap<lsp::Url, Content>,
+    pub workspaces: DashMap<PathBuf, PathBuf>, // Map<workspace-folder, mirror-dir relative to tempdir>
     pub proxies: HashMap<&'static str, ProxyColletion>, // Map<language-id, Proxy>
+    pub registered: DashSet<&'static str>,     // Set<language-id>
+    pub diagnosing: DashMap<lsp::Url, smol::Task<()>>, // Map<document, linter run>, cancelled when dropped
+    pub published: Arc<DashMap<lsp::Url, usize>>, // Map<document, number of diagnostics last published>
     pub config: Config,
 }
 
@@ -33,6 +43,484 @@ impl Backend {
             None => Err(Error::FileNotOpen.msg(text_document.uri.path())),
         }
     }
+
+    /// Mirror a workspace folder under `{tempdir}/workspace/{name}`.
+    async fn add_workspace(&self, folder: &lsp::WorkspaceFolder) {
+        let Ok(path) = folder.uri.to_file_path() else {
+            return;
+        };
+        if self.workspaces.contains_key(&path) {
+            return;
+        }
+        let name: String = folder
+            .name
+            .chars()
+            .map(|char| {
+                if std::path::is_separator(char) {
+                    '_'
+                } else {
+                    char
+                }
+            })
+            .collect();
+        let mirror = (0..)
+            .map(|n| match n {
+                0 => PathBuf::from("workspace").join(&name),
+                n => PathBuf::from("workspace").join(format!("{name}-{n}")),
+            })
+            .find(|mirror| !self.workspaces.iter().any(|dir| dir.value() == mirror))
+            .expect("never run out of numbers");
+        self.workspaces.insert(path.clone(), mirror.clone());
+
+        if let Some(tempdir) = self.tempdir.get() {
+            if let Err(err) = crate::shadow::link(&path, &tempdir.join(mirror)).await {
+                self.client.log_message(lsp::MessageType::ERROR, err).await;
+            }
+        }
+    }
+
+    /// The innermost workspace folder which contain `path`, paired with its mirror directory.
+    fn workspace_of(&self, path: &Path) -> Option<(PathBuf, PathBuf)> {
+        let tempdir = self.tempdir.get()?;
+        self.workspaces
+            .iter()
+            .filter(|dir| path.starts_with(dir.key()))
+            .max_by_key(|dir| dir.key().components().count())
+            .map(|dir| (dir.key().clone(), tempdir.join(dir.value())))
+    }
+
+    /// Path of a document inside the mirror.
+    /// Document outside any workspace folder is mirrored under `{tempdir}/root` with its absolute path preserved.
+    fn mirror_path(&self, uri: &lsp::Url) -> Option<PathBuf> {
+        use crate::mirror;
+
+        let tempdir = self.tempdir.get()?;
+        let path = uri.to_file_path().ok()?;
+        Some(match self.workspace_of(&path) {
+            Some((workspace, mirror)) => mirror.join(path.strip_prefix(workspace).ok()?),
+            None => mirror::preserve(&tempdir.join("root"), &path),
+        })
+    }
+
+    /// Pairs of mirror and workspace directories, swapped when translating `into_mirror`.
+    fn mapping(&self, into_mirror: bool) -> crate::mirror::Mapping {
+        use crate::mirror::Mapping;
+        use std::path::MAIN_SEPARATOR_STR;
+
+        let Some(tempdir) = self.tempdir.get() else {
+            return Mapping::new([]);
+        };
+        let dirs: Vec<_> = self
+            .workspaces
+            .iter()
+            .map(|dir| (tempdir.join(dir.value()), dir.key().clone()))
+            .chain([(tempdir.join("root"), PathBuf::from(MAIN_SEPARATOR_STR))])
+            .map(|(mirror, workspace)| match into_mirror {
+                true => (workspace, mirror),
+                false => (mirror, workspace),
+            })
+            .collect();
+        Mapping::new(dirs)
+    }
+
+    /// Mirror of the workspace folder of a document, or the directory of its mirror `path` outside of them.
+    fn root(&self, uri: &lsp::Url, path: &Path) -> PathBuf {
+        (uri.to_file_path().ok())
+            .and_then(|path| self.workspace_of(&path))
+            .map(|(_, mirror)| mirror)
+            .or_else(|| path.parent().map(Into::into))
+            .unwrap_or_default()
+    }
+
+    fn open(&self, doc: lsp::TextDocumentItem, path: PathBuf, file: Option<fs::File>) {
+        let root = self.root(&doc.uri, &path);
+        self.files.insert(
+            doc.uri,
+            Content {
+                language_id: doc.language_id.into(),
+                path,
+                root,
+                file,
+                text: doc.text,
+                version: doc.version,
+                busy: false,
+            },
+        );
+    }
+
+    /// Write the text of an opened document into its mirror `file`, again when it changed meanwhile,
+    /// then hand the file over to [`change`](Self::change) for the next edits.
+    async fn write_mirror(&self, uri: &lsp::Url, mut file: fs::File) -> io::Result<()> {
+        use io::{AsyncSeekExt as _, AsyncWriteExt as _, SeekFrom};
+
+        loop {
+            let Some((version, text)) =
+                (self.files.get(uri)).map(|it| (it.version, it.text.clone()))
+            else {
+                return Ok(()); // INFO: closed meanwhile
+            };
+            file.seek(SeekFrom::Start(0)).await?;
+            file.write_all(text.as_bytes()).await?;
+            file.set_len(text.len() as u64).await?;
+            file.sync_data().await?;
+            match self.files.get_mut(uri) {
+                Some(mut content) if content.version == version => {
+                    content.file = Some(file);
+                    return Ok(());
+                }
+                Some(_) => continue,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// The document of a hierarchy item (e.g. a caller in another file) for its follow-up commands,
+    /// read from the mirror with the language of the `origin` document when it isn't open.
+    async fn item_content(&self, uri: &lsp::Url, origin: &Content) -> Content {
+        if let Some(content) = self.files.get(uri) {
+            return content.snapshot();
+        }
+        let Some(path) = self.mirror_path(uri) else {
+            return origin.snapshot();
+        };
+        Content {
+            language_id: origin.language_id.clone(),
+            root: self.root(uri, &path),
+            text: fs::read_to_string(&path).await.unwrap_or_default(),
+            path,
+            file: None,
+            version: 0,
+            busy: false,
+        }
+    }
+
+    async fn change(&self, params: lsp::DidChangeTextDocumentParams) {
+        use crate::edit::FileExt as _;
+        use io::{AsyncSeekExt as _, AsyncWriteExt as _, SeekFrom};
+
+        let Some(mut tmp) = self.files.get_mut(&params.text_document.uri) else {
+            return;
+        };
+        let tmp = &mut *tmp;
+        crate::edit::apply_text_changes(&mut tmp.text, &params.content_changes);
+        tmp.version = params.text_document.version;
+        if let Some(proxy) = self.proxies.get(tmp.language_id.as_ref()) {
+            proxy.invalidate(&tmp.path);
+        }
+        let (false, Some(file)) = (tmp.busy, &mut tmp.file) else {
+            return;
+        };
+        tmp.busy = true;
+        if let Err(err) = file.seek(SeekFrom::Start(0)).await {
+            return self.client.log_message(lsp::MessageType::ERROR, err).await;
+        }
+
+        if self.config.incremental_changes {
+            // WARNING: this implementation have more I/O operations
+            // for diff in params.content_changes {
+            //     let Some(range) = diff.range else {
+            //         continue;
+            //     };
+            //     if let Err(err) = tmp.file.apply_change(range, diff.text).await {
+            //         self.client.log_message(MessageType::ERROR, err).await
+            //     }
+            // }
+            // INFO: Less I/O operations
+            if let Err(err) = file.apply_all_changes(params.content_changes).await {
+                self.client.log_message(lsp::MessageType::ERROR, err).await;
+            }
+        } else if let Some(content) = params.content_changes.first() {
+            if let Err(err) = file.write_all(content.text.as_bytes()).await {
+                self.client.log_message(lsp::MessageType::ERROR, err).await;
+            }
+        }
+
+        if let Err(err) = file.sync_data().await {
+            self.client.log_message(lsp::MessageType::ERROR, err).await;
+        }
+        tmp.busy = false;
+    }
+
+    /// Lint a document in the background then publish its diagnostics, which replace the previous ones.
+    /// INFO: tower-lsp only handle a few messages at once, so a slow linter mustn't hold one of them;
+    /// the run of an older version is cancelled when a newer one replace its task
+    fn diagnose(&self, uri: lsp::Url) {
+        let (diagnostics, content) = {
+            let Some(content) = self.files.get(&uri) else {
+                return;
+            };
+            let Some(diagnostics) = (self.proxies.get(content.language_id.as_ref()))
+                .and_then(|proxy| proxy.diagnostics.clone())
+            else {
+                return;
+            };
+            // INFO: don't hold the `files` map while the linter runs, `did_change` would wait for it
+            (diagnostics, content.snapshot())
+        };
+        let (client, published, mapping) = (
+            self.client.clone(),
+            self.published.clone(),
+            self.mapping(false),
+        );
+        let task = smol::spawn({
+            let uri = uri.clone();
+            async move {
+                let (found, printed) = smol::channel::unbounded();
+                let previous = published.get(&uri).map_or(0, |published| *published);
+                let publish_found = async {
+                    let mut items: Vec<lsp::Diagnostic> = vec![];
+                    while let Ok(item) = printed.recv().await {
+                        let batch = std::iter::once(item)
+                            .chain(std::iter::from_fn(|| printed.try_recv().ok()));
+                        items.extend(batch.filter_map(|item| serde_json::from_value(item).ok()));
+                        // INFO: what the linter found so far, until its whole output replace it,
+                        // held back while it would hide diagnostics which are still shown (e.g. on every keystroke)
+                        if items.len() < previous {
+                            continue;
+                        }
+                        published.insert(uri.clone(), items.len());
+                        let found = mapping.rewrite(items.clone());
+                        (client.publish_diagnostics(uri.clone(), found, Some(content.version)))
+                            .await
+                    }
+                };
+                let (diagnosed, ()) =
+                    smol::future::zip(diagnostics.diagnose(&content, found), publish_found).await;
+                match diagnosed {
+                    Ok(items) => {
+                        published.insert(uri.clone(), items.len());
+                        let items = mapping.rewrite(items);
+                        (client.publish_diagnostics(uri, items, Some(content.version))).await
+                    }
+                    Err(err) if err.code == jsonrpc::ErrorCode::RequestCancelled => {} // superseded by a newer change
+                    Err(err) => client.log_message(lsp::MessageType::ERROR, err).await,
+                }
+            }
+        });
+        self.diagnosing.insert(uri, task);
+    }
+
+    /// Translate mirror paths in the output of a tool back to the workspace.
+    fn to_workspace<T>(&self, value: T) -> T
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        self.mapping(false).rewrite(value)
+    }
+
+    /// Translate workspace paths into the mirror for anything sent back to a tool,
+    /// except the origin document that lspcat attached to `data`.
+    fn to_mirror<T>(&self, value: T) -> T
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        use serde_json::Value;
+
+        let Ok(mut json) = serde_json::to_value(&value) else {
+            return value;
+        };
+        let origin = json.pointer_mut("/data/uri").map(Value::take);
+        json = self.mapping(true).rewrite(json);
+        if let (Some(origin), Some(uri)) = (origin, json.pointer_mut("/data/uri")) {
+            *uri = origin;
+        }
+        serde_json::from_value(json).unwrap_or(value)
+    }
+
+    /// The ctags of a document's language, after indexing the workspaces (or the document outside of them).
+    /// Failing to index is only logged, so what is already indexed is still served.
+    async fn ctags(
+        &self,
+        text_document: &lsp::TextDocumentIdentifier,
+    ) -> jsonrpc::Result<&crate::proxy::Ctags> {
+        use crate::Error;
+
+        let (proxy, _) = self.get_proxy(text_document)?;
+        let Some(ctags) = &proxy.ctags else {
+            return Err(Error::UnsupportedMethod.msg("Missing proxy for ctags"));
+        };
+        let roots: Vec<_> = self
+            .workspaces
+            .iter()
+            .map(|dir| dir.key().clone())
+            .collect();
+        let outside = match text_document.uri.to_file_path() {
+            Ok(path) if self.workspace_of(&path).is_none() => ctags.ensure_file(&path).await,
+            _ => Ok(()),
+        };
+        if let Err(err) = ctags.index_workspaces(roots, None).await.and(outside) {
+            self.client.log_message(lsp::MessageType::ERROR, err).await;
+        }
+        Ok(ctags)
+    }
+
+    /// The precomputed index of a document's workspace, unless the document changed since it was indexed.
+    async fn code_index(
+        &self,
+        text_document: &lsp::TextDocumentIdentifier,
+    ) -> Option<std::sync::Arc<crate::proxy::Snapshot>> {
+        let (proxy, content) = self.get_proxy(text_document).ok()?;
+        let code_index = proxy.code_index.as_ref()?;
+        let text = content.text.clone();
+        drop(content);
+        let path = text_document.uri.to_file_path().ok()?;
+        let (root, _) = self.workspace_of(&path)?;
+        let (snapshot, err) = code_index.snapshot(&root).await;
+        if let Some(err) = err {
+            self.client.log_message(lsp::MessageType::ERROR, err).await;
+        }
+        // INFO: the positions of a document edited since then are left to other proxies
+        snapshot.filter(|snapshot| snapshot.is_current(&path, &text))
+    }
+
+    /// Whether the client can register a capability via `client/registerCapability`.
+    fn dynamic_registration(
+        &self,
+        capability: impl FnOnce(&lsp::TextDocumentClientCapabilities) -> Option<bool>,
+    ) -> bool {
+        self.client_capabilities
+            .get()
+            .and_then(|client| client.text_document.as_ref())
+            .and_then(capability)
+            .unwrap_or_default()
+    }
+
+    /// Register the capabilities of a language when the first document of that language is opened,
+    /// so each language get its own trigger characters and providers.
+    async fn register(&self, language_id: &str) {
+        use crate::proxy::{Capabilities as _, SignatureHelp};
+        use serde_json::{json, to_value, Value};
+
+        let Some((&language_id, proxy)) = self.proxies.get_key_value(language_id) else {
+            return;
+        };
+        if !self.registered.insert(language_id) {
+            return;
+        }
+        let text_document_registration_options = lsp::TextDocumentRegistrationOptions {
+            document_selector: Some(vec![lsp::DocumentFilter {
+                language: Some(language_id.to_string()),
+                scheme: None,
+                pattern: None,
+            }]),
+        };
+        let text_document = self
+            .client_capabilities
+            .get()
+            .and_then(|client| client.text_document.as_ref());
+        let registration = |method: &str, register_options| lsp::Registration {
+            id: format!("{method}:{language_id}"),
+            method: method.into(),
+            register_options,
+        };
+
+        let mut registrations = vec![];
+        if self.dynamic_registration(|to| to.completion.as_ref()?.dynamic_registration) {
+            let completion_options = std::iter::once(&proxy.completion)
+                .flatten()
+                .resolve_provider(text_document.and_then(|to| to.completion.clone()))
+                .or_else(|| {
+                    proxy
+                        .ctags
+                        .as_ref()
+                        .map(|_| lsp::CompletionOptions::default())
+                });
+            if let Some(completion_options) = completion_options {
+                let options = lsp::CompletionRegistrationOptions {
+                    text_document_registration_options: text_document_registration_options.clone(),
+                    completion_options,
+                };
+                registrations.push(registration(
+                    "textDocument/completion",
+                    to_value(options).ok(),
+                ));
+            }
+        }
+        if proxy.call_hierarchy.is_some()
+            && self.dynamic_registration(|to| to.call_hierarchy.as_ref()?.dynamic_registration)
+        {
+            let options = to_value(&text_document_registration_options).ok();
+            registrations.push(registration("textDocument/prepareCallHierarchy", options));
+        }
+        // INFO: `lsp::ServerCapabilities` doesn't have `typeHierarchyProvider` yet
+        if proxy.type_hierarchy.is_some()
+            && self.dynamic_registration(|to| to.type_hierarchy.as_ref()?.dynamic_registration)
+        {
+            let options = to_value(&text_document_registration_options).ok();
+            registrations.push(registration("textDocument/prepareTypeHierarchy", options));
+        }
+        let navigation = proxy.ctags.is_some() || proxy.code_index.is_some();
+        if navigation
+            && self.dynamic_registration(|to| to.definition.as_ref()?.dynamic_registration)
+        {
+            let options = to_value(&text_document_registration_options).ok();
+            registrations.push(registration("textDocument/definition", options));
+        }
+        if navigation
+            && self.dynamic_registration(|to| to.document_symbol.as_ref()?.dynamic_registration)
+        {
+            let options = to_value(&text_document_registration_options).ok();
+            registrations.push(registration("textDocument/documentSymbol", options));
+        }
+        if proxy.code_index.is_some()
+            && self.dynamic_registration(|to| to.references.as_ref()?.dynamic_registration)
+        {
+            let options = to_value(&text_document_registration_options).ok();
+            registrations.push(registration("textDocument/references", options));
+        }
+        if (proxy.code_index.is_some() || proxy.hover.is_some())
+            && self.dynamic_registration(|to| to.hover.as_ref()?.dynamic_registration)
+        {
+            let options = to_value(&text_document_registration_options).ok();
+            registrations.push(registration("textDocument/hover", options));
+        }
+        if let Some(signature_help) = (proxy.signature_help.as_ref()).filter(|_| {
+            self.dynamic_registration(|to| to.signature_help.as_ref()?.dynamic_registration)
+        }) {
+            // INFO: `lsp::SignatureHelpRegistrationOptions` doesn't have the trigger characters yet
+            let mut options = to_value(&text_document_registration_options).ok();
+            let characters = SignatureHelp::options([signature_help])
+                .and_then(|characters| to_value(characters).ok());
+            if let (Some(Value::Object(options)), Some(Value::Object(characters))) =
+                (options.as_mut(), characters)
+            {
+                options.extend(characters);
+            }
+            registrations.push(registration("textDocument/signatureHelp", options));
+        }
+        if proxy.formatting.is_some()
+            && self.dynamic_registration(|to| to.formatting.as_ref()?.dynamic_registration)
+        {
+            let options = to_value(&text_document_registration_options).ok();
+            registrations.push(registration("textDocument/formatting", options));
+        }
+        if (proxy.diagnostics.is_some() || proxy.fix_all.is_some())
+            && self.dynamic_registration(|to| to.code_action.as_ref()?.dynamic_registration)
+        {
+            // INFO: `lsp::CodeActionRegistrationOptions` doesn't exist yet
+            let mut options = to_value(&text_document_registration_options).ok();
+            let kinds: Vec<_> = [
+                (proxy.diagnostics.as_ref()).map(|_| lsp::CodeActionKind::QUICKFIX),
+                (proxy.fix_all.as_ref()).map(|_| lsp::CodeActionKind::SOURCE_FIX_ALL),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            if let Some(options) = options.as_mut() {
+                options["codeActionKinds"] = json!(kinds);
+            }
+            registrations.push(registration("textDocument/codeAction", options));
+        }
+
+        if registrations.is_empty() {
+            return;
+        }
+        if let Err(err) = self.client.register_capability(registrations).await {
+            // INFO: the language is only marked up front so concurrent `didOpen`s don't register twice
+            self.registered.remove(language_id);
+            self.client.log_message(lsp::MessageType::ERROR, err).await;
+        }
+    }
 }
 
 #[tower_lsp::async_trait]
@@ -41,26 +529,78 @@ impl LanguageServer for Backend {
         &self,
         params: lsp::InitializeParams,
     ) -> jsonrpc::Result<lsp::InitializeResult> {
-        use crate::proxy::Capabilities as _;
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash as _, Hasher as _};
+        use crate::proxy::{Capabilities as _, SignatureHelp};
+        self.client_capabilities
+            .set_blocking(params.capabilities.clone())
+            .expect("must set once");
         let text_document = params.capabilities.text_document;
+        let folders = match (params.workspace_folders, params.root_uri) {
+            (Some(folders), _) => folders,
+            (None, Some(uri)) => vec![lsp::WorkspaceFolder {
+                name: uri
+                    .to_file_path()
+                    .ok()
+                    .and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+                    .unwrap_or_default(),
+                uri,
+            }],
+            (None, None) => vec![],
+        };
 
-        if let Some(pid) = params.process_id {
-            let tempdir = {
-                let mut hasher = DefaultHasher::new();
-                let cwd = env::current_dir().expect("need permission");
-                format!("{} {}", pid, cwd.display()).hash(&mut hasher);
-                env::temp_dir().join(format!("lspcat-{}", hasher.finish()))
-            };
-            let _ = fs::create_dir(&tempdir).await;
-            self.tempdir.set_blocking(tempdir).expect("must set once"); // WARNING: using async version didn't works
+        let base = (self.config.tempdir.clone()).unwrap_or_else(env::temp_dir);
+        let roots: Vec<_> = folders.iter().map(|folder| folder.uri.as_str()).collect();
+        match TempDir::acquire(&base, &roots).await {
+            Ok(tempdir) => {
+                self.tempdir.set_blocking(tempdir).expect("must set once"); // WARNING: using async version didn't works
+
+                // INFO: the dir in use is locked so it is never collected
+                let ttl = self.config.tempdir_ttl;
+                smol::spawn(async move { crate::tempdir::gc(&base, ttl).await }).detach();
+            }
+            Err(err) => self.client.log_message(lsp::MessageType::ERROR, err).await,
+        }
+        for folder in &folders {
+            self.add_workspace(folder).await;
         }
 
+        // INFO: fallback to a merged registration when the client can't register per language
         let completions = self
             .proxies
             .values()
-            .map_while(|proxy| proxy.completion.as_ref());
+            .filter_map(|proxy| proxy.completion.as_ref())
+            .filter(|_| {
+                !self.dynamic_registration(|to| to.completion.as_ref()?.dynamic_registration)
+            });
+        let call_hierarchy = self
+            .proxies
+            .values()
+            .any(|proxy| proxy.call_hierarchy.is_some())
+            && !self.dynamic_registration(|to| to.call_hierarchy.as_ref()?.dynamic_registration);
+        let code_action = self
+            .proxies
+            .values()
+            .any(|proxy| proxy.diagnostics.is_some() || proxy.fix_all.is_some())
+            && !self.dynamic_registration(|to| to.code_action.as_ref()?.dynamic_registration);
+        let formatting = (self.proxies.values()).any(|proxy| proxy.formatting.is_some())
+            && !self.dynamic_registration(|to| to.formatting.as_ref()?.dynamic_registration);
+
+        let any_ctags = self.proxies.values().any(|proxy| proxy.ctags.is_some());
+        let any_code_index = (self.proxies.values()).any(|proxy| proxy.code_index.is_some());
+        let any_hover = (self.proxies.values()).any(|proxy| proxy.hover.is_some());
+        let signature_helps = (self.proxies.values())
+            .filter_map(|proxy| proxy.signature_help.as_ref())
+            .filter(|_| {
+                !self.dynamic_registration(|to| to.signature_help.as_ref()?.dynamic_registration)
+            });
+        let navigation =
+            |any: bool, capability: fn(&lsp::TextDocumentClientCapabilities) -> Option<bool>| {
+                any && !self.dynamic_registration(capability)
+            };
+        let ctags_completion = self
+            .proxies
+            .values()
+            .any(|proxy| proxy.completion.is_none() && proxy.ctags.is_some())
+            && !self.dynamic_registration(|to| to.completion.as_ref()?.dynamic_registration);
 
         Ok(lsp::InitializeResult {
             capabilities: lsp::ServerCapabilities {
@@ -71,120 +611,561 @@ impl LanguageServer for Backend {
                         } else {
                             lsp::TextDocumentSyncKind::FULL
                         }),
+                        // INFO: ctags index a file again when it is saved
+                        save: any_ctags
+                            .then_some(lsp::TextDocumentSyncSaveOptions::Supported(true)),
                         ..Default::default()
                     },
                 )),
                 completion_provider: completions
-                    .resolve_provider(text_document.map(|to| to.completion).flatten()),
+                    .resolve_provider(text_document.map(|to| to.completion).flatten())
+                    .or_else(|| ctags_completion.then(lsp::CompletionOptions::default)),
+                definition_provider: navigation(any_ctags || any_code_index, |to| {
+                    to.definition.as_ref()?.dynamic_registration
+                })
+                .then_some(lsp::OneOf::Left(true)),
+                document_symbol_provider: navigation(any_ctags || any_code_index, |to| {
+                    to.document_symbol.as_ref()?.dynamic_registration
+                })
+                .then_some(lsp::OneOf::Left(true)),
+                references_provider: navigation(any_code_index, |to| {
+                    to.references.as_ref()?.dynamic_registration
+                })
+                .then_some(lsp::OneOf::Left(true)),
+                hover_provider: navigation(any_code_index || any_hover, |to| {
+                    to.hover.as_ref()?.dynamic_registration
+                })
+                .then_some(lsp::HoverProviderCapability::Simple(true)),
+                signature_help_provider: SignatureHelp::options(signature_helps),
+                workspace_symbol_provider: any_ctags.then_some(lsp::OneOf::Left(true)),
+                call_hierarchy_provider: call_hierarchy
+                    .then_some(lsp::CallHierarchyServerCapability::Simple(true)),
+                code_action_provider: code_action
+                    .then_some(lsp::CodeActionProviderCapability::Simple(true)),
+                document_formatting_provider: formatting.then_some(lsp::OneOf::Left(true)),
+                workspace: Some(lsp::WorkspaceServerCapabilities {
+                    workspace_folders: Some(lsp::WorkspaceFoldersServerCapabilities {
+                        supported: Some(true),
+                        change_notifications: Some(lsp::OneOf::Left(true)),
+                    }),
+                    file_operations: None,
+                }),
                 ..Default::default()
             },
             ..Default::default()
         })
     }
 
-    async fn did_open(&self, params: lsp::DidOpenTextDocumentParams) {
-        use io::AsyncWriteExt as _;
-        use std::path::Path;
+    async fn did_change_workspace_folders(&self, params: lsp::DidChangeWorkspaceFoldersParams) {
+        for folder in params.event.removed {
+            if let Ok(path) = folder.uri.to_file_path() {
+                self.workspaces.remove(&path);
+            }
+        }
+        for folder in &params.event.added {
+            self.add_workspace(folder).await;
+        }
+    }
 
+    async fn did_open(&self, params: lsp::DidOpenTextDocumentParams) {
         let doc = params.text_document;
-        let Some(tempdir) = self.tempdir.get() else {
-            return;
-        };
-        let cwd = env::current_dir().expect("need permission");
-        let Ok(path) = Path::new(doc.uri.path())
-            .strip_prefix(cwd)
-            .map(|file| tempdir.join(file))
-        else {
+        let uri = doc.uri.clone();
+        let language_id = doc.language_id.clone();
+        let Some(path) = self.mirror_path(&doc.uri) else {
             return;
         };
+        // INFO: notifications are handled concurrently, so the document is tracked before any await
+        // or a `didChange` handled meanwhile would be lost
+        self.open(doc, path.clone(), None);
+        self.register(&language_id).await;
+
+        let mirrored =
+            (self.proxies.get(language_id.as_str())).is_some_and(ProxyColletion::needs_mirror);
+        if !mirrored {
+            // INFO: every proxy read the content from memory, so skip the disk writes
+            return self.diagnose(uri);
+        }
+
+        if let Some((workspace, mirror)) = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| self.workspace_of(&path))
+        {
+            let relative = path.strip_prefix(&mirror).unwrap_or(&path);
+            if let Err(err) = crate::shadow::materialize(&workspace, &mirror, relative).await {
+                return self.client.log_message(lsp::MessageType::ERROR, err).await;
+            }
+        }
         if let Some(dir) = path.parent() {
             if let Err(err) = fs::create_dir_all(dir).await {
                 return self.client.log_message(lsp::MessageType::ERROR, err).await;
             }
         }
 
-        let Ok(mut file) = fs::OpenOptions::new()
+        let Ok(file) = fs::OpenOptions::new()
             .create(true)
             .read(true)
             .write(true)
+            .truncate(true)
             .open(&path)
             .await
         else {
             return;
         };
-        let write_result = file.write_all(doc.text.as_bytes()).await;
-        let sync_result = file.sync_data().await;
-        if let Err(err) = write_result.and(sync_result) {
+        if let Err(err) = self.write_mirror(&uri, file).await {
             self.client.log_message(lsp::MessageType::ERROR, err).await;
         }
 
         self.client
             .log_message(
                 lsp::MessageType::LOG,
-                format!("edit {} as {}", doc.uri.path(), path.display()),
+                format!("edit {} as {}", uri.path(), path.display()),
             )
             .await;
+        self.diagnose(uri);
     }
 
-    async fn did_change(&self, params: lsp::DidChangeTextDocumentParams) {
-        use crate::edit::FileExt as _;
-        use io::{AsyncSeekExt as _, AsyncWriteExt as _, SeekFrom};
-
-        let Some(mut tmp) = self.files.get_mut(&params.text_document.uri) else {
+    async fn did_close(&self, params: lsp::DidCloseTextDocumentParams) {
+        let Some((uri, content)) = self.files.remove(&params.text_document.uri) else {
             return;
         };
-        if tmp.busy {
+        if let Some(proxy) = self.proxies.get(content.language_id.as_ref()) {
+            proxy.invalidate(&content.path);
+            if proxy.diagnostics.is_some() {
+                self.diagnosing.remove(&uri);
+                self.published.remove(&uri);
+                self.client
+                    .publish_diagnostics(uri.clone(), vec![], None)
+                    .await;
+            }
+        }
+        if content.file.is_none() {
             return;
         }
-        tmp.busy = true;
-        if let Err(err) = tmp.file.seek(SeekFrom::Start(0)).await {
-            return self.client.log_message(lsp::MessageType::ERROR, err).await;
+        drop(content.file);
+
+        let Some((workspace, mirror)) = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| self.workspace_of(&path))
+        else {
+            return;
+        };
+        let relative = content.path.strip_prefix(&mirror).unwrap_or(&content.path);
+        if let Err(err) = crate::shadow::restore(&workspace, &mirror, relative).await {
+            self.client.log_message(lsp::MessageType::ERROR, err).await;
         }
+    }
 
-        if self.config.incremental_changes {
-            // WARNING: this implementation have more I/O operations
-            // for diff in params.content_changes {
-            //     let Some(range) = diff.range else {
-            //         continue;
-            //     };
-            //     if let Err(err) = tmp.file.apply_change(range, diff.text).await {
-            //         self.client.log_message(MessageType::ERROR, err).await
-            //     }
-            // }
-            // INFO: Less I/O operations
-            if let Err(err) = tmp.file.apply_all_changes(params.content_changes).await {
-                self.client.log_message(lsp::MessageType::ERROR, err).await;
+    async fn did_change(&self, params: lsp::DidChangeTextDocumentParams) {
+        let uri = params.text_document.uri.clone();
+        self.change(params).await;
+        self.diagnose(uri);
+    }
+
+    async fn completion(
+        &self,
+        params: lsp::CompletionParams,
+    ) -> jsonrpc::Result<Option<lsp::CompletionResponse>> {
+        use crate::{proxy::Proxy as _, Error};
+
+        let text_document = &params.text_document_position.text_document;
+        let (proxy, content) = self.get_proxy(text_document)?;
+        match (&proxy.completion, &proxy.ctags) {
+            (Some(completion), _) => completion
+                .proxy_response(params, &content, self.client_capabilities.get())
+                .await
+                .map(|response| self.to_workspace(response)),
+            // INFO: identifiers from ctags when the language has no real completion
+            (None, Some(_)) => {
+                drop(content);
+                let ctags = self.ctags(text_document).await?;
+                let (_, content) = self.get_proxy(text_document)?;
+                let position = params.text_document_position.position;
+                Ok(Some(ctags.completion(&content.text, position)))
             }
-        } else if let Some(content) = params.content_changes.first() {
-            if let Err(err) = tmp.file.write_all(content.text.as_bytes()).await {
-                self.client.log_message(lsp::MessageType::ERROR, err).await;
+            (None, None) => Err(Error::UnsupportedMethod.msg("Missing proxy for code completion")),
+        }
+    }
+
+    async fn completion_resolve(
+        &self,
+        params: lsp::CompletionItem,
+    ) -> jsonrpc::Result<lsp::CompletionItem> {
+        use crate::{proxy::unwrap_data, Error};
+
+        let Some(uri) = unwrap_data(params.data.clone()).0 else {
+            return Ok(params);
+        };
+        let (proxy, content) = self.get_proxy(&lsp::TextDocumentIdentifier { uri })?;
+        match &proxy.completion {
+            Some(completion) => completion
+                .resolve(
+                    self.to_mirror(params),
+                    &content,
+                    self.client_capabilities.get(),
+                )
+                .await
+                .map(|item| self.to_workspace(item)),
+            None => Err(Error::UnsupportedMethod.msg("Missing proxy for code completion")),
+        }
+    }
+
+    async fn prepare_call_hierarchy(
+        &self,
+        params: lsp::CallHierarchyPrepareParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::CallHierarchyItem>>> {
+        use crate::{proxy::Proxy as _, Error};
+
+        let (proxy, content) =
+            self.get_proxy(&params.text_document_position_params.text_document)?;
+        match &proxy.call_hierarchy {
+            Some(call_hierarchy) => call_hierarchy
+                .proxy_response(params, &content, self.client_capabilities.get())
+                .await
+                .map(|response| self.to_workspace(response)),
+            None => Err(Error::UnsupportedMethod.msg("Missing proxy for call hierarchy")),
+        }
+    }
+
+    async fn incoming_calls(
+        &self,
+        params: lsp::CallHierarchyIncomingCallsParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::CallHierarchyIncomingCall>>> {
+        use crate::Error;
+
+        let (proxy, origin) = (self.get_proxy(&origin(&params.item.uri, &params.item.data)))
+            .map(|(proxy, content)| (proxy, content.snapshot()))?;
+        let content = self.item_content(&params.item.uri, &origin).await;
+        match &proxy.call_hierarchy {
+            Some(call_hierarchy) => call_hierarchy
+                .incoming_calls(self.to_mirror(params.item), &content)
+                .await
+                .map(|response| self.to_workspace(response)),
+            None => Err(Error::UnsupportedMethod.msg("Missing proxy for call hierarchy")),
+        }
+    }
+
+    async fn outgoing_calls(
+        &self,
+        params: lsp::CallHierarchyOutgoingCallsParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::CallHierarchyOutgoingCall>>> {
+        use crate::Error;
+
+        let (proxy, origin) = (self.get_proxy(&origin(&params.item.uri, &params.item.data)))
+            .map(|(proxy, content)| (proxy, content.snapshot()))?;
+        let content = self.item_content(&params.item.uri, &origin).await;
+        match &proxy.call_hierarchy {
+            Some(call_hierarchy) => call_hierarchy
+                .outgoing_calls(self.to_mirror(params.item), &content)
+                .await
+                .map(|response| self.to_workspace(response)),
+            None => Err(Error::UnsupportedMethod.msg("Missing proxy for call hierarchy")),
+        }
+    }
+
+    async fn prepare_type_hierarchy(
+        &self,
+        params: lsp::TypeHierarchyPrepareParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::TypeHierarchyItem>>> {
+        use crate::{proxy::Proxy as _, Error};
+
+        let (proxy, content) =
+            self.get_proxy(&params.text_document_position_params.text_document)?;
+        match &proxy.type_hierarchy {
+            Some(type_hierarchy) => type_hierarchy
+                .proxy_response(params, &content, self.client_capabilities.get())
+                .await
+                .map(|response| self.to_workspace(response)),
+            None => Err(Error::UnsupportedMethod.msg("Missing proxy for type hierarchy")),
+        }
+    }
+
+    async fn supertypes(
+        &self,
+        params: lsp::TypeHierarchySupertypesParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::TypeHierarchyItem>>> {
+        use crate::Error;
+
+        let (proxy, origin) = (self.get_proxy(&origin(&params.item.uri, &params.item.data)))
+            .map(|(proxy, content)| (proxy, content.snapshot()))?;
+        let content = self.item_content(&params.item.uri, &origin).await;
+        match &proxy.type_hierarchy {
+            Some(type_hierarchy) => type_hierarchy
+                .supertypes(self.to_mirror(params.item), &content)
+                .await
+                .map(|response| self.to_workspace(response)),
+            None => Err(Error::UnsupportedMethod.msg("Missing proxy for type hierarchy")),
+        }
+    }
+
+    async fn subtypes(
+        &self,
+        params: lsp::TypeHierarchySubtypesParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::TypeHierarchyItem>>> {
+        use crate::Error;
+
+        let (proxy, origin) = (self.get_proxy(&origin(&params.item.uri, &params.item.data)))
+            .map(|(proxy, content)| (proxy, content.snapshot()))?;
+        let content = self.item_content(&params.item.uri, &origin).await;
+        match &proxy.type_hierarchy {
+            Some(type_hierarchy) => type_hierarchy
+                .subtypes(self.to_mirror(params.item), &content)
+                .await
+                .map(|response| self.to_workspace(response)),
+            None => Err(Error::UnsupportedMethod.msg("Missing proxy for type hierarchy")),
+        }
+    }
+
+    async fn code_action(
+        &self,
+        params: lsp::CodeActionParams,
+    ) -> jsonrpc::Result<Option<lsp::CodeActionResponse>> {
+        use crate::Error;
+
+        let (proxy, content) = (self.get_proxy(&params.text_document))
+            .map(|(proxy, content)| (proxy, content.snapshot()))?;
+        if proxy.diagnostics.is_none() && proxy.fix_all.is_none() {
+            return Err(Error::UnsupportedMethod.msg("Missing proxy for code action"));
+        }
+        let mut actions = (proxy.diagnostics.as_ref())
+            .map(|diagnostics| diagnostics.code_actions(&params))
+            .unwrap_or_default();
+        if let Some(fix_all) = (proxy.fix_all.as_ref()).filter(|it| it.is_requested(&params)) {
+            let fixes = fix_all.code_actions(&content).await?;
+            actions.extend(self.to_workspace(fixes));
+        }
+        Ok(Some(actions))
+    }
+
+    async fn formatting(
+        &self,
+        params: lsp::DocumentFormattingParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::TextEdit>>> {
+        use crate::{proxy::Proxy as _, Error};
+
+        // INFO: a formatter may take a while, `did_change` mustn't wait for the `files` map meanwhile
+        let (proxy, content) = (self.get_proxy(&params.text_document))
+            .map(|(proxy, content)| (proxy, content.snapshot()))?;
+        match &proxy.formatting {
+            Some(formatting) => {
+                (formatting.proxy_response(params, &content, self.client_capabilities.get())).await
             }
+            None => Err(Error::UnsupportedMethod.msg("Missing proxy for formatting")),
         }
+    }
 
-        if let Err(err) = tmp.file.sync_data().await {
+    async fn did_save(&self, params: lsp::DidSaveTextDocumentParams) {
+        let uri = &params.text_document.uri;
+        let Some(language_id) = (self.files.get(uri)).map(|content| content.language_id.clone())
+        else {
+            return;
+        };
+        let ctags = (self.proxies.get(language_id.as_ref())).and_then(|proxy| proxy.ctags.as_ref());
+        let (Some(ctags), Ok(path)) = (ctags, uri.to_file_path()) else {
+            return;
+        };
+        if let Err(err) = ctags.index_file(&path).await {
             self.client.log_message(lsp::MessageType::ERROR, err).await;
         }
-        tmp.busy = false;
     }
 
-    async fn completion(
+    async fn goto_definition(
         &self,
-        params: lsp::CompletionParams,
-    ) -> jsonrpc::Result<Option<lsp::CompletionResponse>> {
+        params: lsp::GotoDefinitionParams,
+    ) -> jsonrpc::Result<Option<lsp::GotoDefinitionResponse>> {
+        let lsp::TextDocumentPositionParams {
+            text_document,
+            position,
+        } = &params.text_document_position_params;
+        let path = text_document.uri.to_file_path().unwrap_or_default();
+        let link_support = (self.client_capabilities.get())
+            .and_then(|client| client.text_document.as_ref()?.definition?.link_support)
+            .unwrap_or_default();
+        if let Some(response) = (self.code_index(text_document).await)
+            .and_then(|index| index.definition(&path, *position, link_support))
+        {
+            return Ok(Some(response));
+        }
+        let (proxy, _) = self.get_proxy(text_document)?;
+        if proxy.ctags.is_none() && proxy.code_index.is_some() {
+            return Ok(None);
+        }
+        let ctags = self.ctags(text_document).await?;
+        let (_, content) = self.get_proxy(text_document)?;
+        Ok(ctags.definition(&path, &content.text, *position, link_support))
+    }
+
+    async fn references(
+        &self,
+        params: lsp::ReferenceParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::Location>>> {
+        use crate::{partial::Partial, proxy::Snapshot, Error};
+
+        let lsp::TextDocumentPositionParams {
+            text_document,
+            position,
+        } = &params.text_document_position;
+        let (proxy, _) = self.get_proxy(text_document)?;
+        let Some(code_index) = &proxy.code_index else {
+            return Err(Error::UnsupportedMethod.msg("Missing proxy for references"));
+        };
+        let path = text_document.uri.to_file_path().unwrap_or_default();
+        let Some(index) = self.code_index(text_document).await else {
+            return Ok(None);
+        };
+        let Some(symbol) = index.symbol_at(&path, *position) else {
+            return Ok(None);
+        };
+        let include_declaration = params.context.include_declaration;
+        let mut partial = Partial::new(
+            &self.client,
+            params.partial_result_params.partial_result_token,
+        );
+        let mut locations = index.references(symbol, include_declaration);
+        if Snapshot::is_shared(symbol) {
+            // INFO: the indexes of the other workspaces are loaded (maybe slowly) after reporting this one
+            partial.send(&locations).await;
+            let root = self.workspace_of(&path).map(|(root, _)| root);
+            let others: Vec<_> = (self.workspaces.iter())
+                .map(|dir| dir.key().clone())
+                .filter(|other| Some(other) != root.as_ref())
+                .collect();
+            for other in others {
+                let (snapshot, err) = code_index.snapshot(&other).await;
+                if let Some(err) = err {
+                    self.client.log_message(lsp::MessageType::ERROR, err).await;
+                }
+                if let Some(snapshot) = snapshot {
+                    let found = snapshot.references(symbol, include_declaration);
+                    partial.send(&found).await;
+                    locations.extend(found);
+                }
+            }
+        }
+        Ok(Some(partial.finish(locations).await))
+    }
+
+    async fn hover(&self, params: lsp::HoverParams) -> jsonrpc::Result<Option<lsp::Hover>> {
         use crate::{proxy::Proxy as _, Error};
 
-        let (proxy, content) = self.get_proxy(&params.text_document_position.text_document)?;
-        match &proxy.completion {
-            Some(completion) => completion.proxy_response(params, &content).await,
-            None => Err(Error::Forbidden.msg("Missing proxy for code completion")),
+        let lsp::TextDocumentPositionParams {
+            text_document,
+            position,
+        } = &params.text_document_position_params;
+        let path = text_document.uri.to_file_path().unwrap_or_default();
+        if let Some(hover) =
+            (self.code_index(text_document).await).and_then(|index| index.hover(&path, *position))
+        {
+            return Ok(Some(hover));
+        }
+        let (proxy, content) = self.get_proxy(text_document)?;
+        match (&proxy.hover, &proxy.code_index) {
+            (Some(hover), _) => hover
+                .proxy_response(params, &content, self.client_capabilities.get())
+                .await
+                .map(|response| self.to_workspace(response)),
+            (None, Some(_)) => Ok(None),
+            (None, None) => Err(Error::UnsupportedMethod.msg("Missing proxy for hover")),
+        }
+    }
+
+    async fn signature_help(
+        &self,
+        params: lsp::SignatureHelpParams,
+    ) -> jsonrpc::Result<Option<lsp::SignatureHelp>> {
+        use crate::{proxy::Proxy as _, Error};
+
+        let text_document = &params.text_document_position_params.text_document;
+        let (proxy, content) = self.get_proxy(text_document)?;
+        match &proxy.signature_help {
+            Some(signature_help) => signature_help
+                .proxy_response(params, &content, self.client_capabilities.get())
+                .await
+                .map(|response| self.to_workspace(response)),
+            None => Err(Error::UnsupportedMethod.msg("Missing proxy for signature help")),
         }
     }
 
+    async fn document_symbol(
+        &self,
+        params: lsp::DocumentSymbolParams,
+    ) -> jsonrpc::Result<Option<lsp::DocumentSymbolResponse>> {
+        let path = params.text_document.uri.to_file_path().unwrap_or_default();
+        if let Some(symbols) = (self.code_index(&params.text_document).await)
+            .and_then(|index| index.document_symbols(&path))
+        {
+            return Ok(Some(lsp::DocumentSymbolResponse::Flat(symbols)));
+        }
+        let (proxy, _) = self.get_proxy(&params.text_document)?;
+        if proxy.ctags.is_none() && proxy.code_index.is_some() {
+            return Ok(None);
+        }
+        let ctags = self.ctags(&params.text_document).await?;
+        let symbols = ctags.document_symbols(&path);
+        Ok(Some(lsp::DocumentSymbolResponse::Flat(symbols)))
+    }
+
+    async fn symbol(
+        &self,
+        params: lsp::WorkspaceSymbolParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::SymbolInformation>>> {
+        use crate::{partial::Partial, proxy::Ctags};
+        use smol::{channel, future};
+
+        let roots: Vec<_> = self
+            .workspaces
+            .iter()
+            .map(|dir| dir.key().clone())
+            .collect();
+        let query = &params.query;
+        let mut partial = Partial::new(
+            &self.client,
+            params.partial_result_params.partial_result_token,
+        );
+        let mut symbols = vec![];
+        for ctags in self
+            .proxies
+            .values()
+            .filter_map(|proxy| proxy.ctags.as_ref())
+        {
+            // INFO: with a partial result token, report the symbols while ctags index the workspaces
+            let (found, indexed) = channel::unbounded::<lsp::SymbolInformation>();
+            let found = partial.is_requested().then_some(found);
+            let report = async {
+                while let Ok(symbol) = indexed.recv().await {
+                    let batch: Vec<_> = (std::iter::once(symbol))
+                        .chain(std::iter::from_fn(|| indexed.try_recv().ok()))
+                        .filter(|symbol| Ctags::matches(query, &symbol.name))
+                        .collect();
+                    partial.send(&batch).await;
+                }
+            };
+            let (indexed, ()) =
+                future::zip(ctags.index_workspaces(roots.clone(), found), report).await;
+            if let Err(err) = indexed {
+                self.client.log_message(lsp::MessageType::ERROR, err).await;
+            }
+            symbols.extend(ctags.workspace_symbols(query));
+        }
+        Ok(Some(partial.finish(symbols).await))
+    }
+
     async fn shutdown(&self) -> jsonrpc::Result<()> {
         Ok(if let Some(tempdir) = self.tempdir.get() {
-            if let Err(err) = fs::remove_dir_all(tempdir).await {
+            if let Err(err) = tempdir.clean().await {
                 self.client.log_message(lsp::MessageType::ERROR, err).await;
             }
         })
     }
 }
+
+/// The document which a hierarchy item was prepared from,
+/// falling back to the item's own `uri` when `data` was not wrapped by lspcat.
+fn origin(uri: &lsp::Url, data: &Option<serde_json::Value>) -> lsp::TextDocumentIdentifier {
+    use crate::proxy::unwrap_data;
+
+    lsp::TextDocumentIdentifier {
+        uri: unwrap_data(data.clone()).0.unwrap_or_else(|| uri.clone()),
+    }
+}
@@ -2,7 +2,7 @@ use smol::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, SeekF
 use smol::stream::StreamExt;
 use smol::{fs::File, io};
 use std::fmt::Debug;
-use tower_lsp::lsp_types::{Range, TextDocumentContentChangeEvent};
+use tower_lsp::lsp_types::{Position, Range, TextDocumentContentChangeEvent};
 
 /// Represent the edit state of a content
 #[derive(Debug)]
@@ -277,3 +277,96 @@ async fn write_final(buf: &mut Vec<u8>, file: &mut File) -> io::Result<()> {
     file.write_all(buf).await?;
     file.set_len(buf.len() as u64).await
 }
+
+/// Apply the changes from [`lsp_types::DidChangeTextDocumentParams.content_changes`] to an in-memory `text`.
+/// Unlike [`FileExt::apply_all_changes`], each change is applied on the result of the previous one.
+pub fn apply_text_changes<'a>(
+    text: &mut String,
+    changes: impl IntoIterator<Item = &'a TextDocumentContentChangeEvent>,
+) {
+    for change in changes {
+        match change.range {
+            Some(range) => {
+                let start = offset(text, range.start);
+                let end = offset(text, range.end).max(start);
+                text.replace_range(start..end, &change.text);
+            }
+            None => change.text.clone_into(text),
+        }
+    }
+}
+
+/// Get the byte offset of a `position` whose `character` is counted in UTF-16 code units.
+fn offset(text: &str, position: Position) -> usize {
+    let line_start: usize = text
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum();
+    let line = text[line_start..].split('\n').next().unwrap_or_default();
+    let mut character = 0;
+    for (offset, char) in line.char_indices() {
+        if character >= position.character as usize {
+            return line_start + offset;
+        }
+        character += char.len_utf16();
+    }
+    line_start + line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(
+        range: Option<((u32, u32), (u32, u32))>,
+        text: &str,
+    ) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: range.map(|((line, character), (end_line, end_character))| {
+                Range::new(
+                    Position::new(line, character),
+                    Position::new(end_line, end_character),
+                )
+            }),
+            range_length: None,
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn offset_in_utf16() {
+        let text = "a𝔸b\néc\n";
+        let offset = |line, character| offset(text, Position::new(line, character));
+        assert_eq!(offset(0, 0), 0);
+        assert_eq!(offset(0, 1), 1);
+        assert_eq!(offset(0, 3), 5); // INFO: `𝔸` is 2 code units and 4 bytes
+        assert_eq!(offset(0, 2), 5); // INFO: within a surrogate pair, after it
+        assert_eq!(offset(0, 9), 6); // INFO: past the end of the line, at its end
+        assert_eq!(offset(1, 1), 9);
+        assert_eq!(offset(2, 0), text.len());
+        assert_eq!(offset(7, 3), text.len());
+    }
+
+    #[test]
+    fn apply_changes_in_order() {
+        let mut text = "let 𝔸 = 1;\nlet b = 2;\n".to_string();
+        apply_text_changes(
+            &mut text,
+            &[
+                change(Some(((0, 4), (0, 6))), "é"),
+                change(Some(((1, 8), (1, 9))), "𝔸"),
+                // INFO: on the text edited by the previous changes
+                change(Some(((1, 10), (1, 10))), " + é"),
+                change(Some(((0, 0), (1, 0))), ""),
+            ],
+        );
+        assert_eq!(text, "let b = 𝔸 + é;\n");
+
+        // INFO: a reversed range is empty, and a change without range replace everything
+        apply_text_changes(&mut text, &[change(Some(((0, 4), (0, 2))), "x")]);
+        assert_eq!(text, "let xb = 𝔸 + é;\n");
+        apply_text_changes(&mut text, &[change(None, "new")]);
+        assert_eq!(text, "new");
+    }
+}
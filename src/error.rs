@@ -6,6 +6,13 @@ pub enum Error {
     FileNotOpen,
     ParseError,
     NoResponse,
+    Timeout,
+    Cancelled,
+    SpawnFailed,
+    NonZeroExit,
+    OutputTooLarge,
+    WrongEncoding,
+    UnsupportedMethod,
 }
 
 impl From<Error> for jsonrpc::Error {
@@ -37,6 +44,41 @@ impl Error {
                 message: "No response".into(),
                 data,
             },
+            Error::Timeout => jsonrpc::Error {
+                code: ErrorCode::ServerError(-32945),
+                message: "Timeout".into(),
+                data,
+            },
+            Error::Cancelled => jsonrpc::Error {
+                code: ErrorCode::RequestCancelled,
+                message: ErrorCode::RequestCancelled.description().into(),
+                data,
+            },
+            Error::SpawnFailed => jsonrpc::Error {
+                code: ErrorCode::ServerError(-32946),
+                message: "Failed to spawn".into(),
+                data,
+            },
+            Error::NonZeroExit => jsonrpc::Error {
+                code: ErrorCode::ServerError(-32947),
+                message: "Exited with an error".into(),
+                data,
+            },
+            Error::OutputTooLarge => jsonrpc::Error {
+                code: ErrorCode::ServerError(-32948),
+                message: "Output too large".into(),
+                data,
+            },
+            Error::WrongEncoding => jsonrpc::Error {
+                code: ErrorCode::ServerError(-32949),
+                message: "Output is not UTF-8".into(),
+                data,
+            },
+            Error::UnsupportedMethod => jsonrpc::Error {
+                code: ErrorCode::MethodNotFound,
+                message: "Unsupported method".into(),
+                data,
+            },
         }
     }
     pub fn data(self, data: Value) -> jsonrpc::Error {
@@ -0,0 +1,199 @@
+use serde_json::{json, Map, Value};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, OnceLock},
+    task::{Context, Poll},
+};
+use tower_lsp::jsonrpc::{Request, Response};
+use tower_service::Service;
+
+/// `CompletionList.itemDefaults`, which `lsp::CompletionList` can't express yet,
+/// so the completion responses are rewritten as JSON on their way to the client.
+pub struct ItemDefaults<S> {
+    inner: S,
+    supported: Arc<OnceLock<Vec<String>>>, // properties the client accept as defaults, from `initialize`
+}
+
+impl<S> ItemDefaults<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            supported: Arc::default(),
+        }
+    }
+}
+
+impl<S> Service<Request> for ItemDefaults<S>
+where
+    S: Service<Request, Response = Option<Response>>,
+    S::Future: Send + 'static,
+{
+    type Response = Option<Response>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        if request.method() == "initialize" {
+            let supported = (request.params())
+                .and_then(|params| {
+                    params.pointer(
+                        "/capabilities/textDocument/completion/completionList/itemDefaults",
+                    )
+                })
+                .and_then(|defaults| serde_json::from_value(defaults.clone()).ok());
+            let _ = self.supported.set(supported.unwrap_or_default());
+        }
+        let completion = request.method() == "textDocument/completion";
+        let supported = self.supported.clone();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await?;
+            let supported = supported.get().filter(|supported| !supported.is_empty());
+            Ok(match (response, supported) {
+                (Some(response), Some(supported)) if completion => {
+                    let (id, result) = response.into_parts();
+                    let result = result.map(|mut list| {
+                        hoist(&mut list, supported);
+                        list
+                    });
+                    Some(Response::from_parts(id, result))
+                }
+                (response, _) => response,
+            })
+        })
+    }
+}
+
+/// Move what every item of a completion `list` share into its `itemDefaults`, for the `supported` properties.
+/// The items without their own `textEdit` then insert their `textEditText`, or their label.
+pub fn hoist(list: &mut Value, supported: &[String]) {
+    let Some(items) = list.get_mut("items").and_then(Value::as_array_mut) else {
+        return;
+    };
+    let supports = |property: &str| supported.iter().any(|it| it == property);
+    let mut defaults = Map::new();
+    for property in ["commitCharacters", "insertTextFormat"] {
+        let Some(value) = shared(items, |item| item.get(property).cloned()) else {
+            continue;
+        };
+        if supports(property) {
+            for item in items.iter_mut().filter_map(Value::as_object_mut) {
+                item.remove(property);
+            }
+            defaults.insert(property.into(), value);
+        }
+    }
+
+    // INFO: the `range` of a `TextEdit`, or the `insert` and `replace` ranges of an `InsertReplaceEdit`
+    let edit_range = |item: &Value| {
+        let edit = item.get("textEdit")?;
+        Some(match edit.get("range") {
+            Some(range) => range.clone(),
+            None => json!({"insert": edit.get("insert")?, "replace": edit.get("replace")?}),
+        })
+    };
+    if let Some(range) = shared(items, edit_range).filter(|_| supports("editRange")) {
+        for item in items.iter_mut().filter_map(Value::as_object_mut) {
+            let Some(mut edit) = item.remove("textEdit") else {
+                continue;
+            };
+            let text = edit["newText"].take();
+            if item.get("label") != Some(&text) {
+                item.insert("textEditText".into(), text);
+            }
+        }
+        defaults.insert("editRange".into(), range);
+    }
+
+    if !defaults.is_empty() {
+        list["itemDefaults"] = Value::Object(defaults);
+    }
+}
+
+/// The value of every item when it is the same, `None` when any differ or miss it.
+fn shared(items: &[Value], value: impl Fn(&Value) -> Option<Value>) -> Option<Value> {
+    let (first, rest) = items.split_first()?;
+    let first = value(first)?;
+    rest.iter()
+        .all(|item| value(item).as_ref() == Some(&first))
+        .then_some(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(new_text: &str, character: u32) -> Value {
+        let position = json!({"line": 2, "character": character});
+        json!({"range": {"start": {"line": 2, "character": 4}, "end": position}, "newText": new_text})
+    }
+
+    fn supported(properties: &[&str]) -> Vec<String> {
+        properties.iter().map(|it| it.to_string()).collect()
+    }
+
+    #[test]
+    fn hoist_shared_properties() {
+        let mut list = json!({"isIncomplete": false, "items": [
+            {"label": "foo", "insertTextFormat": 2, "commitCharacters": ["."], "textEdit": edit("foo", 6)},
+            {"label": "bar", "insertTextFormat": 2, "commitCharacters": [","], "textEdit": edit("bar($1)", 6)},
+        ]});
+        hoist(
+            &mut list,
+            &supported(&["editRange", "insertTextFormat", "commitCharacters"]),
+        );
+        assert_eq!(
+            list,
+            json!({"isIncomplete": false, "items": [
+                {"label": "foo", "commitCharacters": ["."]},
+                {"label": "bar", "commitCharacters": [","], "textEditText": "bar($1)"},
+            ], "itemDefaults": {
+                "insertTextFormat": 2,
+                "editRange": {"start": {"line": 2, "character": 4}, "end": {"line": 2, "character": 6}},
+            }})
+        );
+    }
+
+    #[test]
+    fn hoist_insert_and_replace_ranges() {
+        let range = |character| json!({"start": {"line": 0, "character": 0}, "end": {"line": 0, "character": character}});
+        let edit = |new_text| json!({"newText": new_text, "insert": range(2), "replace": range(5)});
+        let mut list = json!({"isIncomplete": true, "items": [
+            {"label": "a", "textEdit": edit("a")},
+            {"label": "b", "textEdit": edit("b")},
+        ]});
+        hoist(&mut list, &supported(&["editRange"]));
+        assert_eq!(
+            list["itemDefaults"],
+            json!({"editRange": {"insert": range(2), "replace": range(5)}})
+        );
+        assert_eq!(list["items"], json!([{"label": "a"}, {"label": "b"}]));
+    }
+
+    #[test]
+    fn keep_what_is_not_shared_or_supported() {
+        let items = json!([
+            {"label": "foo", "insertTextFormat": 1, "textEdit": edit("foo", 6)},
+            {"label": "bar", "insertTextFormat": 2, "textEdit": edit("bar", 7)},
+            {"label": "baz", "insertTextFormat": 2},
+        ]);
+        let mut list = json!({"isIncomplete": false, "items": items});
+        hoist(&mut list, &supported(&["editRange", "insertTextFormat"]));
+        assert_eq!(list, json!({"isIncomplete": false, "items": items}));
+
+        let items =
+            json!([{"label": "a", "insertTextFormat": 2}, {"label": "b", "insertTextFormat": 2}]);
+        let mut list = json!({"isIncomplete": false, "items": items});
+        hoist(&mut list, &supported(&["editRange"]));
+        assert_eq!(list, json!({"isIncomplete": false, "items": items}));
+
+        let mut array = json!([{"label": "a"}]);
+        hoist(&mut array, &supported(&["editRange"]));
+        assert_eq!(array, json!([{"label": "a"}]));
+    }
+}
@@ -1,29 +1,80 @@
 mod backend;
 mod edit;
 mod error;
+mod item_defaults;
+mod mirror;
 mod mock;
+mod partial;
 mod proxy;
+mod shadow;
+mod tempdir;
 
 use backend::Backend;
 use error::Error;
 
 use smol::fs::File;
-use std::{borrow::Cow, path::PathBuf};
+use std::{borrow::Cow, path::PathBuf, sync::Arc, time::Duration};
 
+#[derive(Default)]
 struct ProxyColletion {
     completion: Option<proxy::Completion>,
+    call_hierarchy: Option<proxy::CallHierarchy>,
+    type_hierarchy: Option<proxy::TypeHierarchy>,
+    diagnostics: Option<Arc<proxy::Diagnostics>>, // shared with the runs in the background
+    ctags: Option<proxy::Ctags>,
+    code_index: Option<proxy::CodeIndex>,
+    hover: Option<proxy::Hover>,
+    signature_help: Option<proxy::SignatureHelp>,
+    formatting: Option<proxy::Formatting>,
+    fix_all: Option<proxy::FixAll>,
     // ...reserved for other proxies...
 }
 
 struct Content {
     language_id: Cow<'static, str>,
     path: PathBuf,
-    file: File,
+    root: PathBuf, // mirror of the workspace folder of the document, or its directory outside of them
+    file: Option<File>, // `None` when no proxy read from the mirror
+    text: String,
+    version: i32,
     busy: bool,
 }
 
+impl Content {
+    /// Copy without the mirror file, for a long-running command which shouldn't hold the `files` map.
+    fn snapshot(&self) -> Self {
+        Self {
+            language_id: self.language_id.clone(),
+            path: self.path.clone(),
+            root: self.root.clone(),
+            file: None,
+            text: self.text.clone(),
+            version: self.version,
+            busy: false,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Content {
+    /// A document of the `/mirror` workspace, whose mirror file isn't open.
+    fn test(language_id: &'static str, path: &str, text: &str) -> Self {
+        Self {
+            language_id: language_id.into(),
+            path: PathBuf::from(path),
+            root: PathBuf::from("/mirror"),
+            file: None,
+            text: text.into(),
+            version: 1,
+            busy: false,
+        }
+    }
+}
+
 struct Config {
     incremental_changes: bool,
+    tempdir: Option<PathBuf>, // base dir of `lspcat-*` dirs (e.g. `/dev/shm`), default to the system tempdir
+    tempdir_ttl: Duration,    // unused `lspcat-*` dirs older than this are removed on startup
 }
 
 fn main() {
@@ -31,7 +82,8 @@ fn main() {
     use std::io::{stdin, stdout};
     use tower_lsp::{LspService, Server};
 
-    let (service, socket) = LspService::new(mock::rescript::backend);
+    let (service, socket) = LspService::new(mock::backend);
+    let service = item_defaults::ItemDefaults::new(service);
 
     let stdin = Unblock::new(stdin());
     let stdout = Unblock::new(stdout());
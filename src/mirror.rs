@@ -0,0 +1,192 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::path::{Component, Path, PathBuf};
+use tower_lsp::lsp_types::Url;
+
+/// Translate every path (and `file://` URI) under a directory into the same path under another directory.
+///
+/// # Example
+/// With a pair of `/tmp/lspcat-123/workspace/project` into `/home/user/project`
+/// - `file:///tmp/lspcat-123/workspace/project/src/main.res` become `file:///home/user/project/src/main.res`
+/// - `/tmp/lspcat-123/workspace/project/src/main.res:1:2` become `/home/user/project/src/main.res:1:2`
+pub struct Mapping {
+    pairs: Vec<(String, String)>, // sorted by the longest `from`
+}
+
+impl Mapping {
+    pub fn new(dirs: impl IntoIterator<Item = (PathBuf, PathBuf)>) -> Self {
+        let mut pairs = vec![];
+        for (from, to) in dirs {
+            if let (Ok(from), Ok(to)) = (
+                Url::from_directory_path(&from),
+                Url::from_directory_path(&to),
+            ) {
+                pairs.push((from.into(), to.into()));
+            }
+            // INFO: a bare `/` would match every separator in a plain text
+            if from.parent().is_some() {
+                pairs.push((directory(&from), directory(&to)));
+            }
+        }
+        pairs.sort_by_key(|(from, _)| std::cmp::Reverse(from.len()));
+        Self { pairs }
+    }
+
+    /// Rewrite any string in `value`, including the keys of a map (e.g. `WorkspaceEdit.changes`).
+    pub fn rewrite<T>(&self, value: T) -> T
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        if self.pairs.is_empty() {
+            return value;
+        }
+        let Ok(mut json) = serde_json::to_value(&value) else {
+            return value;
+        };
+        self.replace(&mut json);
+        serde_json::from_value(json).unwrap_or(value)
+    }
+
+    fn replace(&self, json: &mut Value) {
+        match json {
+            Value::String(text) => {
+                if let Some(replaced) = self.replace_str(text) {
+                    *text = replaced;
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|value| self.replace(value)),
+            Value::Object(map) => {
+                let renamed: Vec<_> = map
+                    .keys()
+                    .filter_map(|key| self.replace_str(key).map(|renamed| (key.clone(), renamed)))
+                    .collect();
+                for (key, renamed) in renamed {
+                    if let Some(value) = map.remove(&key) {
+                        map.insert(renamed, value);
+                    }
+                }
+                map.values_mut().for_each(|value| self.replace(value));
+            }
+            _ => {}
+        }
+    }
+
+    /// Replace in a single pass so a rewritten path is never rewritten twice.
+    fn replace_str(&self, text: &str) -> Option<String> {
+        if !self
+            .pairs
+            .iter()
+            .any(|(from, _)| text.contains(from.as_str()))
+        {
+            return None;
+        }
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        'scan: while !rest.is_empty() {
+            for (from, to) in &self.pairs {
+                if let Some(after) = rest.strip_prefix(from.as_str()) {
+                    result.push_str(to);
+                    rest = after;
+                    continue 'scan;
+                }
+            }
+            let mut chars = rest.chars();
+            result.extend(chars.next());
+            rest = chars.as_str();
+        }
+        Some(result)
+    }
+}
+
+/// Path of `path` inside `dir` with the absolute path preserved, e.g. `/home/user/file` into `{dir}/home/user/file`.
+pub fn preserve(dir: &Path, path: &Path) -> PathBuf {
+    let relative: PathBuf = path
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect();
+    dir.join(relative)
+}
+
+fn directory(path: &Path) -> String {
+    let path = path.display().to_string();
+    if path.ends_with(std::path::MAIN_SEPARATOR) {
+        path
+    } else {
+        format!("{path}{}", std::path::MAIN_SEPARATOR)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tower_lsp::lsp_types as lsp;
+
+    fn mapping() -> Mapping {
+        Mapping::new([
+            (PathBuf::from("/tmp/lspcat/root"), PathBuf::from("/")),
+            (
+                PathBuf::from("/tmp/lspcat/workspace/my project"),
+                PathBuf::from("/home/user/my project"),
+            ),
+        ])
+    }
+
+    #[test]
+    fn rewrite_nested_json() {
+        let value = json!({
+            "changes": {
+                "file:///tmp/lspcat/workspace/my%20project/a.res": [{"newText": "/tmp/lspcat/root/usr/lib/x"}],
+            },
+            "data": [[{"uri": "file:///tmp/lspcat/root/etc/hosts"}], 1, null],
+            "message": "/tmp/lspcat/workspace/my project/a.res:1:2 and /tmp/lspcat/workspace/my project/b.res",
+            "other": "/tmp/lspcat/workspace/my project2/a.res",
+        });
+        assert_eq!(
+            mapping().rewrite(value),
+            json!({
+                "changes": {
+                    "file:///home/user/my%20project/a.res": [{"newText": "/usr/lib/x"}],
+                },
+                "data": [[{"uri": "file:///etc/hosts"}], 1, null],
+                "message": "/home/user/my project/a.res:1:2 and /home/user/my project/b.res",
+                "other": "/tmp/lspcat/workspace/my project2/a.res",
+            })
+        );
+    }
+
+    #[test]
+    fn rewrite_once() {
+        // INFO: the rewritten path is itself under the `from` of the other pair
+        let mapping = Mapping::new([
+            (PathBuf::from("/a"), PathBuf::from("/b/a")),
+            (PathBuf::from("/b"), PathBuf::from("/c")),
+        ]);
+        assert_eq!(mapping.rewrite(json!("/a/x /b/y")), json!("/b/a/x /c/y"));
+    }
+
+    #[test]
+    fn rewrite_typed_values() {
+        let location = lsp::Location {
+            uri: "file:///tmp/lspcat/root/src/main.c".parse().unwrap(),
+            range: lsp::Range::default(),
+        };
+        assert_eq!(
+            mapping().rewrite(location).uri.as_str(),
+            "file:///src/main.c"
+        );
+        assert_eq!(
+            Mapping::new([]).rewrite("/tmp/lspcat/root/a".to_owned()),
+            "/tmp/lspcat/root/a"
+        );
+    }
+
+    #[test]
+    fn preserve_the_absolute_path() {
+        let dir = Path::new("/tmp/lspcat/root");
+        assert_eq!(
+            preserve(dir, Path::new("/home/user/a.c")),
+            dir.join("home/user/a.c")
+        );
+    }
+}
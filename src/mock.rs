@@ -1 +1,97 @@
+mod c;
+mod dockerfile;
+mod javascript;
+mod lua;
+mod markdown;
+mod plaintext;
+mod python;
 pub mod rescript;
+mod rust;
+mod shellscript;
+mod starlark;
+mod terraform;
+mod yaml;
+
+use crate::{proxy, Backend, Config, ProxyColletion};
+use dashmap::{DashMap, DashSet};
+use smol::lock::{OnceCell, Semaphore};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tower_lsp::Client;
+
+pub fn backend(client: Client) -> Backend {
+    let global = Arc::new(Semaphore::new(4)); // INFO: shared by the commands of every language
+    let limits = || proxy::Limits {
+        language: Arc::new(Semaphore::new(2)),
+        global: global.clone(),
+    };
+    let proxies: HashMap<_, ProxyColletion> = HashMap::from([
+        ("rescript", rescript::proxies(limits())),
+        ("python", python::proxies(limits())),
+        ("plaintext", plaintext::proxies(limits())),
+        ("markdown", markdown::proxies(limits())),
+        ("c", c::proxies(limits(), "gcc")),
+        ("cpp", c::proxies(limits(), "g++")),
+        ("rust", rust::proxies(limits())),
+        ("yaml", yaml::proxies(limits())),
+        ("lua", lua::proxies(limits())),
+        ("dockerfile", dockerfile::proxies(limits())),
+        ("javascript", javascript::proxies(limits())),
+        ("terraform", terraform::proxies(limits())),
+        ("shellscript", shellscript::proxies(limits())),
+        ("starlark", starlark::proxies(limits())),
+    ]);
+    Backend {
+        client,
+        client_capabilities: OnceCell::new(),
+        proxies,
+        registered: DashSet::new(),
+        diagnosing: DashMap::new(),
+        published: Arc::default(),
+        tempdir: OnceCell::new(),
+        files: DashMap::new(),
+        workspaces: DashMap::new(),
+        config: Config {
+            incremental_changes: true,
+            tempdir: None,
+            tempdir_ttl: Duration::from_secs(7 * 24 * 60 * 60),
+        },
+    }
+}
+
+/// An `exec:` command with the defaults shared by every language, whose requests may run side by side.
+fn exec(
+    program: &str,
+    args: &[&str],
+    input: proxy::Input,
+    parser: proxy::Parser,
+    limits: &proxy::Limits,
+) -> proxy::Exec {
+    proxy::Exec {
+        program: program.into(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        input,
+        timeout: Some(Duration::from_secs(5)),
+        limits: limits.clone(),
+        in_flight: proxy::InFlight::new(false),
+        cache: proxy::Cache::new(32),
+        parser,
+        report_file: None,
+        max_output: Some(16 * 1024 * 1024),
+    }
+}
+
+/// Only the latest request per document matter, for those sent on every change (diagnostics, completion).
+fn latest_only(exec: proxy::Exec) -> proxy::Exec {
+    proxy::Exec {
+        in_flight: proxy::InFlight::new(true),
+        ..exec
+    }
+}
+
+pub fn mapping<const N: usize>(fields: [(&str, proxy::Field); N]) -> proxy::Mapping {
+    proxy::Mapping {
+        fields: (fields.into_iter())
+            .map(|(path, field)| (path.into(), field))
+            .collect(),
+    }
+}
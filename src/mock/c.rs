@@ -0,0 +1,102 @@
+use super::{exec, latest_only};
+use crate::{proxy, ProxyColletion};
+use proxy::Field;
+use std::sync::Arc;
+
+/// The function defined at `$2`:`$3` of `$1`, as `file function line text` lines of cscope.
+const CSCOPE_PREPARE: &str = r#"
+word=$(awk -v line="$2" -v character="$3" 'NR == line + 1 {
+    before = substr($0, 1, character); after = substr($0, character + 1)
+    match(before, /[A-Za-z0-9_]*$/); word = substr(before, RSTART)
+    match(after, /^[A-Za-z0-9_]*/); print word substr(after, 1, RLENGTH)
+}' "$1")
+[ -n "$word" ] && cscope -dL -1 "$word" | awk -v root="$PWD" '$1 !~ /^\// { $1 = root "/" $1 } { print }'
+"#;
+
+/// The callers (`-3`) or the callees (`-2`) of the function of the item `$2`, whose name is its first field.
+const CSCOPE_CALLS: &str = r#"
+name=$(printf '%s' "$2" | sed -n 's/^{"name":"\([^"]*\)".*/\1/p')
+cscope -dL "$1" "$name" | awk -v root="$PWD" '$1 !~ /^\// { $1 = root "/" $1 } { print }'
+"#;
+
+/// The whole line of a cscope result.
+const LINE: &str =
+    ".line | tonumber - 1 | {start: {line: ., character: 0}, end: {line: (. + 1), character: 0}}";
+
+/// C or C++, checked by `compiler` (e.g. `gcc` or `clang++`) without building anything.
+pub fn proxies(limits: proxy::Limits, compiler: &str) -> ProxyColletion {
+    // INFO: compilers print their diagnostics on stderr
+    let script = format!("{compiler} -fsyntax-only \"$1\" 2>&1");
+    let compiler = exec(
+        "sh",
+        &["-c", &script, "sh", "{file}"],
+        proxy::Input::Mirror,
+        proxy::Parser::ErrorFormat(proxy::ErrorFormat::preset(proxy::Preset::Gcc)),
+        &limits,
+    );
+    // INFO: cscope runs in the root, where `cscope -bR` left its `cscope.out`
+    let prepare = exec(
+        "sh",
+        &[
+            "-c",
+            CSCOPE_PREPARE,
+            "sh",
+            "{file}",
+            "{line}",
+            "{character}",
+        ],
+        proxy::Input::Mirror,
+        cscope(""),
+        &limits,
+    );
+    let calls = |option| {
+        exec(
+            "sh",
+            &["-c", CSCOPE_CALLS, "sh", option, "{item}"],
+            proxy::Input::Mirror,
+            cscope(match option {
+                "-3" => "from",
+                _ => "to", // INFO: at the call site, where cscope find a callee
+            }),
+            &limits,
+        )
+    };
+    ProxyColletion {
+        diagnostics: Some(Arc::new(proxy::Diagnostics {
+            proxy: proxy::PassThrough::ExecCommand(latest_only(compiler)),
+        })),
+        call_hierarchy: Some(proxy::CallHierarchy {
+            prepare: proxy::PassThrough::ExecCommand(prepare),
+            incoming_calls: proxy::PassThrough::ExecCommand(calls("-3")),
+            outgoing_calls: proxy::PassThrough::ExecCommand(calls("-2")),
+        }),
+        ..Default::default()
+    }
+}
+
+/// `file function line text` lines into `CallHierarchyItem`s, nested in `item` with their `fromRanges` if any.
+fn cscope(item: &str) -> proxy::Parser {
+    let query = |source| Field::Query(proxy::Query::new(source).expect("a valid query"));
+    let path = |field: &str| match item {
+        "" => field.to_owned(),
+        item => format!("{item}.{field}"),
+    };
+    let mut fields = vec![
+        (path("name"), Field::Raw("function".into())),
+        (path("kind"), Field::Const(12.into())), // INFO: `SymbolKind::FUNCTION`
+        (path("detail"), Field::Raw("text".into())),
+        (path("uri"), Field::Text("file://{file}".into())),
+        (path("range"), query(LINE)),
+        (path("selectionRange"), query(LINE)),
+    ];
+    if !item.is_empty() {
+        fields.push(("fromRanges".into(), query(&format!("[{LINE}]"))));
+    }
+    proxy::Parser::Columns {
+        separator: " ".into(),
+        columns: ["file", "function", "line", "text"]
+            .map(String::from)
+            .to_vec(),
+        mapping: proxy::Mapping { fields },
+    }
+}
@@ -0,0 +1,19 @@
+use super::{exec, latest_only};
+use crate::{proxy, ProxyColletion};
+use std::sync::Arc;
+
+pub fn proxies(limits: proxy::Limits) -> ProxyColletion {
+    let hadolint = exec(
+        "hadolint",
+        &["--no-fail", "--format", "sarif", "{file}"],
+        proxy::Input::Mirror,
+        proxy::Parser::Sarif,
+        &limits,
+    );
+    ProxyColletion {
+        diagnostics: Some(Arc::new(proxy::Diagnostics {
+            proxy: proxy::PassThrough::ExecCommand(latest_only(hadolint)),
+        })),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,25 @@
+use super::{exec, latest_only};
+use crate::{proxy, ProxyColletion};
+use std::sync::Arc;
+
+pub fn proxies(limits: proxy::Limits) -> ProxyColletion {
+    let eslint = exec(
+        "eslint",
+        &[
+            "--format",
+            "checkstyle",
+            "--stdin",
+            "--stdin-filename",
+            "{path}",
+        ],
+        proxy::Input::Stdin,
+        proxy::Parser::Checkstyle,
+        &limits,
+    );
+    ProxyColletion {
+        diagnostics: Some(Arc::new(proxy::Diagnostics {
+            proxy: proxy::PassThrough::ExecCommand(latest_only(eslint)),
+        })),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,31 @@
+use super::{exec, latest_only};
+use crate::{proxy, ProxyColletion};
+use std::{sync::Arc, time::Duration};
+
+pub fn proxies(limits: proxy::Limits) -> ProxyColletion {
+    // INFO: e.g. `init.lua:1:7: (W211) unused variable 'a'`
+    let errorformat =
+        proxy::ErrorFormat::new(["%f:%l:%c: (%t%n) %m"]).expect("a valid errorformat");
+    let luacheck = exec(
+        "luacheck",
+        &["--formatter", "plain", "--codes", "{file}"],
+        proxy::Input::Mirror,
+        proxy::Parser::ErrorFormat(errorformat),
+        &limits,
+    );
+    ProxyColletion {
+        diagnostics: Some(Arc::new(proxy::Diagnostics {
+            proxy: proxy::PassThrough::ExecCommand(latest_only(luacheck)),
+        })),
+        // INFO: no analyzer, definitions, symbols and completion come from the tags
+        ctags: Some(proxy::Ctags {
+            program: "ctags".into(),
+            args: vec!["--languages=Lua".into()],
+            tags_file: None,
+            timeout: Some(Duration::from_secs(30)),
+            max_output: Some(64 * 1024 * 1024),
+            index: proxy::Index::default(),
+        }),
+        ..Default::default()
+    }
+}
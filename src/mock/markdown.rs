@@ -0,0 +1,40 @@
+use super::{exec, latest_only, mapping};
+use crate::{proxy, ProxyColletion};
+use proxy::Field;
+use serde_json::json;
+use std::sync::Arc;
+
+pub fn proxies(limits: proxy::Limits) -> ProxyColletion {
+    // INFO: e.g. `12:5:TODO`, with 1-based line and column
+    let todo = exec(
+        "rg",
+        &[
+            "--line-number",
+            "--column",
+            "--only-matching",
+            "TODO|FIXME",
+            "-",
+        ],
+        proxy::Input::Stdin,
+        proxy::Parser::Columns {
+            separator: ":".into(),
+            columns: ["line", "column", "text"].map(String::from).to_vec(),
+            mapping: mapping([
+                ("range.start.line", Field::Number("line".into(), -1)),
+                ("range.start.character", Field::Number("column".into(), -1)),
+                ("range.end.line", Field::Number("line".into(), -1)),
+                ("range.end.character", Field::Number("column".into(), 3)), // INFO: up to the end of `TODO`
+                ("severity", Field::Const(json!(3))),
+                ("source", Field::Const(json!("rg"))),
+                ("message", Field::Raw("text".into())),
+            ]),
+        },
+        &limits,
+    );
+    ProxyColletion {
+        diagnostics: Some(Arc::new(proxy::Diagnostics {
+            proxy: proxy::PassThrough::ExecCommand(latest_only(todo)),
+        })),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,55 @@
+use super::{exec, latest_only, mapping};
+use crate::{proxy, ProxyColletion};
+use dashmap::DashMap;
+use proxy::Field;
+use regex::Regex;
+use serde_json::json;
+use std::sync::Arc;
+
+pub fn proxies(limits: proxy::Limits) -> ProxyColletion {
+    // INFO: every distinct word of the document
+    let words = exec(
+        "sh",
+        &["-c", "tr -cs '[:alpha:]' '\\n' | sort -u"],
+        proxy::Input::Stdin,
+        proxy::Parser::Words(mapping([
+            ("label", Field::Raw("word".into())),
+            ("kind", Field::Const(json!(1))), // `CompletionItemKind::TEXT`
+        ])),
+        &limits,
+    );
+    // INFO: e.g. `-:3: teh ==> the`
+    let codespell = exec(
+        "codespell",
+        &["-"],
+        proxy::Input::Stdin,
+        proxy::Parser::Regex {
+            pattern: Regex::new(r"(?m)^[^:\n]*:(?P<line>\d+): (?P<typo>.+) ==> (?P<fix>.+)$")
+                .expect("a valid pattern"),
+            mapping: mapping([
+                ("range.start.line", Field::Number("line".into(), -1)),
+                ("range.start.character", Field::Const(json!(0))),
+                ("range.end.line", Field::Number("line".into(), 0)),
+                ("range.end.character", Field::Const(json!(0))),
+                ("severity", Field::Const(json!(3))),
+                ("source", Field::Const(json!("codespell"))),
+                ("message", Field::Text("{typo} ==> {fix}".into())),
+            ]),
+        },
+        &limits,
+    );
+    ProxyColletion {
+        completion: Some(proxy::Completion {
+            proxy: proxy::PassThrough::ExecCommand(latest_only(words)),
+            trigger_characters: None,
+            resolve: None,
+            max_items: Some(100),
+            max_documentation: None,
+            cache: DashMap::new(),
+        }),
+        diagnostics: Some(Arc::new(proxy::Diagnostics {
+            proxy: proxy::PassThrough::ExecCommand(latest_only(codespell)),
+        })),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,122 @@
+use super::{exec, latest_only, mapping};
+use crate::{proxy, ProxyColletion};
+use proxy::Field;
+use serde_json::json;
+use std::{sync::Arc, time::Duration};
+
+/// Answer each JSON request line with the docstrings under the cursor as one JSON string line.
+const JEDI_HOVER: &str = r#"
+import json, sys, jedi
+for line in sys.stdin:
+    request = json.loads(line)
+    script = jedi.Script(path=request["file"])
+    names = script.help(int(request["line"]) + 1, int(request["character"]))
+    print(json.dumps("\n\n".join(name.docstring() for name in names)), flush=True)
+"#;
+
+/// Answer each JSON request line with the signatures of the call under the cursor as one `SignatureHelp` line.
+const JEDI_SIGNATURES: &str = r#"
+import json, sys, jedi
+for line in sys.stdin:
+    request = json.loads(line)
+    script = jedi.Script(path=request["file"])
+    signatures = script.get_signatures(int(request["line"]) + 1, int(request["character"]))
+    print(json.dumps({
+        "signatures": [{
+            "label": signature.to_string(),
+            "documentation": signature.docstring(raw=True),
+            "parameters": [{"label": param.to_string()} for param in signature.params],
+        } for signature in signatures],
+        "activeSignature": 0,
+        "activeParameter": signatures[0].index if signatures else None,
+    }), flush=True)
+"#;
+
+pub fn proxies(limits: proxy::Limits) -> ProxyColletion {
+    let query = |source| proxy::Query::new(source).expect("a valid query");
+    // INFO: one JSON object per line, e.g. `{"file": .., "line": 3, "column": 4, "severity": "error", "message": .., "code": ..}`
+    let mypy = exec(
+        "mypy",
+        &["--output", "json", "--no-error-summary", "{file}"],
+        proxy::Input::Mirror,
+        proxy::Parser::Ndjson(mapping([
+            ("range.start.line", Field::Number("line".into(), -1)),
+            ("range.start.character", Field::Number("column".into(), 0)),
+            ("range.end.line", Field::Number("line".into(), -1)),
+            ("range.end.character", Field::Number("column".into(), 1)),
+            ("severity", Field::Query(query(".severity | severity"))),
+            ("code", Field::Query(query(r#".code // "misc""#))),
+            ("source", Field::Const(json!("mypy"))),
+            ("message", Field::Raw("message".into())),
+        ])),
+        &limits,
+    );
+    // INFO: both print a unified diff of the document, named after `--stdin-filename`
+    let ruff_format = exec(
+        "ruff",
+        &["format", "--diff", "--stdin-filename", "{path}", "-"],
+        proxy::Input::Stdin,
+        proxy::Parser::Diff,
+        &limits,
+    );
+    let ruff_fix = exec(
+        "ruff",
+        &[
+            "check",
+            "--fix-only",
+            "--diff",
+            "--stdin-filename",
+            "{path}",
+            "-",
+        ],
+        proxy::Input::Stdin,
+        proxy::Parser::DiffWorkspace,
+        &limits,
+    );
+    // INFO: importing jedi is the slow part, so a few warm interpreters answer every hover
+    let jedi = proxy::Worker {
+        program: "python3".into(),
+        args: vec!["-c".into(), JEDI_HOVER.into()],
+        request: None,
+        timeout: Some(Duration::from_secs(5)),
+        max_memory: Some(512 * 1024 * 1024),
+        max_output: Some(1024 * 1024),
+        pool: proxy::Pool::new(2),
+        cache: proxy::Cache::new(32),
+        parser: proxy::Parser::Json,
+    };
+    let jedi_signatures = proxy::Worker {
+        program: "python3".into(),
+        args: vec!["-c".into(), JEDI_SIGNATURES.into()],
+        request: None,
+        timeout: Some(Duration::from_secs(5)),
+        max_memory: Some(512 * 1024 * 1024),
+        max_output: Some(1024 * 1024),
+        pool: proxy::Pool::new(2),
+        cache: proxy::Cache::new(32),
+        parser: proxy::Parser::Json,
+    };
+    ProxyColletion {
+        diagnostics: Some(Arc::new(proxy::Diagnostics {
+            proxy: proxy::PassThrough::ExecCommand(latest_only(mypy)),
+        })),
+        formatting: Some(proxy::Formatting {
+            proxy: proxy::PassThrough::ExecCommand(ruff_format),
+        }),
+        fix_all: Some(proxy::FixAll {
+            proxy: proxy::PassThrough::ExecCommand(ruff_fix),
+            title: "Fix all ruff issues".into(),
+        }),
+        hover: Some(proxy::Hover {
+            proxy: proxy::PassThrough::Worker(jedi),
+            max_documentation: Some(8 * 1024),
+        }),
+        signature_help: Some(proxy::SignatureHelp {
+            proxy: proxy::PassThrough::Worker(jedi_signatures),
+            trigger_characters: Some(vec!["(".into()]),
+            retrigger_characters: Some(vec![",".into()]),
+            max_documentation: Some(4 * 1024),
+        }),
+        ..Default::default()
+    }
+}
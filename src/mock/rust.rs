@@ -0,0 +1,25 @@
+use super::{exec, latest_only};
+use crate::{proxy, ProxyColletion};
+use std::sync::Arc;
+
+pub fn proxies(limits: proxy::Limits) -> ProxyColletion {
+    // INFO: a single file checked as a library, without writing anything
+    let rustc = exec(
+        "sh",
+        &[
+            "-c",
+            "rustc --edition=2021 --crate-type=lib --error-format=short --emit=metadata -o /dev/null \"$1\" 2>&1",
+            "sh",
+            "{file}",
+        ],
+        proxy::Input::Mirror,
+        proxy::Parser::ErrorFormat(proxy::ErrorFormat::preset(proxy::Preset::Rustc)),
+        &limits,
+    );
+    ProxyColletion {
+        diagnostics: Some(Arc::new(proxy::Diagnostics {
+            proxy: proxy::PassThrough::ExecCommand(latest_only(rustc)),
+        })),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,58 @@
+use super::{exec, latest_only};
+use crate::{proxy, ProxyColletion};
+use std::sync::Arc;
+
+/// `{"comments": [{"line": 3, "endLine": 3, "column": 1, "endColumn": 5, "level": "warning", "code": 2034, "message": ..}]}`
+const COMMENTS: &str = r#".comments[] | {
+    range: {
+        start: {line: (.line - 1), character: (.column - 1)},
+        end: {line: (.endLine - 1), character: (.endColumn - 1)}
+    },
+    severity: (.level | severity),
+    code: "SC\(.code)",
+    codeDescription: {href: "https://www.shellcheck.net/wiki/SC\(.code)"},
+    source: "shellcheck",
+    message
+}"#;
+
+/// The man page of the command under the cursor, with its overstrikes kept for the Markdown emphasis.
+/// Run as `sh -c MAN_HOVER sh {file} {line} {character}`.
+const MAN_HOVER: &str = r#"
+word=$(awk -v line="$2" -v character="$3" 'NR == line + 1 {
+    before = substr($0, 1, character); after = substr($0, character + 1)
+    match(before, /[A-Za-z0-9_.-]*$/); word = substr(before, RSTART)
+    match(after, /^[A-Za-z0-9_.-]*/); print word substr(after, 1, RLENGTH)
+}' "$1")
+[ -n "$word" ] && MAN_KEEP_FORMATTING=1 MANWIDTH=80 man -P cat "$word" 2>/dev/null
+exit 0
+"#;
+
+pub fn proxies(limits: proxy::Limits) -> ProxyColletion {
+    let shellcheck = exec(
+        "shellcheck",
+        &["--format", "json1", "{file}"],
+        proxy::Input::Memfd,
+        proxy::Parser::Query {
+            query: proxy::Query::new(COMMENTS).expect("a valid query"),
+            first: false,
+        },
+        &limits,
+    );
+    let man = exec(
+        "sh",
+        &["-c", MAN_HOVER, "sh", "{file}", "{line}", "{character}"],
+        proxy::Input::Mirror,
+        proxy::Parser::Text,
+        &limits,
+    );
+    ProxyColletion {
+        diagnostics: Some(Arc::new(proxy::Diagnostics {
+            proxy: proxy::PassThrough::ExecCommand(latest_only(shellcheck)),
+        })),
+        hover: Some(proxy::Hover {
+            proxy: proxy::PassThrough::ExecCommand(man),
+            max_documentation: Some(16 * 1024),
+        }),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,24 @@
+use super::{exec, latest_only};
+use crate::{proxy, ProxyColletion};
+use std::sync::Arc;
+
+/// Bazel files, whose syntax is a subset of Python so a compile is enough to find the syntax errors.
+const COMPILE: &str =
+    r#"python3 -c 'import sys; compile(open(sys.argv[1]).read(), sys.argv[1], "exec")' "$1" 2>&1"#;
+
+pub fn proxies(limits: proxy::Limits) -> ProxyColletion {
+    // INFO: the traceback is printed on stderr, without writing any `__pycache__` into the mirror
+    let compile = exec(
+        "sh",
+        &["-c", COMPILE, "sh", "{file}"],
+        proxy::Input::Mirror,
+        proxy::Parser::ErrorFormat(proxy::ErrorFormat::preset(proxy::Preset::Python)),
+        &limits,
+    );
+    ProxyColletion {
+        diagnostics: Some(Arc::new(proxy::Diagnostics {
+            proxy: proxy::PassThrough::ExecCommand(latest_only(compile)),
+        })),
+        ..Default::default()
+    }
+}
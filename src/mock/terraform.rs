@@ -0,0 +1,20 @@
+use super::{exec, latest_only};
+use crate::{proxy, ProxyColletion};
+use std::sync::Arc;
+
+pub fn proxies(limits: proxy::Limits) -> ProxyColletion {
+    // INFO: the whole module of the root, each issue is a failed test case with `main.tf:3,1-10` in its output
+    let tflint = exec(
+        "tflint",
+        &["--format", "junit", "--force"],
+        proxy::Input::Mirror,
+        proxy::Parser::Junit,
+        &limits,
+    );
+    ProxyColletion {
+        diagnostics: Some(Arc::new(proxy::Diagnostics {
+            proxy: proxy::PassThrough::ExecCommand(latest_only(tflint)),
+        })),
+        ..Default::default()
+    }
+}
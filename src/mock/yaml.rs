@@ -0,0 +1,20 @@
+use super::{exec, latest_only};
+use crate::{proxy, ProxyColletion};
+use std::sync::Arc;
+
+pub fn proxies(limits: proxy::Limits) -> ProxyColletion {
+    // INFO: e.g. `config.yaml:2:1: [warning] too many blank lines (empty-lines)`
+    let yamllint = exec(
+        "yamllint",
+        &["--format", "parsable", "{file}"],
+        proxy::Input::Mirror,
+        proxy::Parser::ErrorFormat(proxy::ErrorFormat::preset(proxy::Preset::Generic)),
+        &limits,
+    );
+    ProxyColletion {
+        diagnostics: Some(Arc::new(proxy::Diagnostics {
+            proxy: proxy::PassThrough::ExecCommand(latest_only(yamllint)),
+        })),
+        ..Default::default()
+    }
+}
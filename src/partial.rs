@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use tower_lsp::{lsp_types as lsp, Client};
+
+/// `$/progress` with partial results, which `lsp::ProgressParamsValue` can't express yet.
+enum PartialResult {}
+
+#[derive(Serialize, Deserialize)]
+struct PartialResultParams {
+    token: lsp::ProgressToken,
+    value: Value,
+}
+
+impl lsp::notification::Notification for PartialResult {
+    type Params = PartialResultParams;
+    const METHOD: &'static str = "$/progress";
+}
+
+/// Results reported while a request is still running, when the client gave a `partialResultToken`.
+/// Once some are reported, the response must be empty so every result is reported instead.
+pub struct Partial<'a> {
+    client: &'a Client,
+    token: Option<lsp::ProgressToken>,
+    sent: HashSet<String>, // results already reported, serialized
+}
+
+impl<'a> Partial<'a> {
+    pub fn new(client: &'a Client, token: Option<lsp::ProgressToken>) -> Self {
+        Self {
+            client,
+            token,
+            sent: HashSet::new(),
+        }
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// Report the `results` which weren't yet.
+    pub async fn send<T: Serialize>(&mut self, results: &[T]) {
+        let Some(token) = &self.token else {
+            return;
+        };
+        let fresh: Vec<_> = (results.iter())
+            .filter_map(|result| serde_json::to_value(result).ok())
+            .filter(|result| self.sent.insert(result.to_string()))
+            .collect();
+        if fresh.is_empty() {
+            return;
+        }
+        let params = PartialResultParams {
+            token: token.clone(),
+            value: Value::Array(fresh),
+        };
+        self.client.send_notification::<PartialResult>(params).await;
+    }
+
+    /// The response of the request, which is empty when the `results` were reported.
+    pub async fn finish<T: Serialize>(mut self, results: Vec<T>) -> Vec<T> {
+        if !self.is_requested() {
+            return results;
+        }
+        self.send(&results).await;
+        vec![]
+    }
+}
@@ -1,13 +1,437 @@
+mod cache;
+mod call_hierarchy;
+mod code_index;
 mod completion;
+mod ctags;
+mod diagnostics;
+mod fix_all;
+mod formatting;
+mod hierarchy;
+mod hover;
+mod markdown;
+mod parse;
+mod process;
+mod schedule;
+mod signature_help;
+mod type_hierarchy;
+mod worker;
+pub use cache::Cache;
+pub use call_hierarchy::CallHierarchy;
+pub use code_index::{CodeIndex, Snapshot};
 pub use completion::Completion;
+pub use ctags::{Ctags, Index};
+pub use diagnostics::Diagnostics;
+pub use fix_all::FixAll;
+pub use formatting::Formatting;
+pub use hover::Hover;
+pub use parse::{ErrorFormat, Field, Mapping, Parser, Preset, Query};
+pub use schedule::{InFlight, Limits};
+pub use signature_help::SignatureHelp;
+pub use type_hierarchy::TypeHierarchy;
+pub use worker::{Pool, Worker};
 
-use crate::Content;
-use smol::{lock::RwLock, process::Command};
-use tower_lsp::jsonrpc;
+use crate::{Content, Error, ProxyColletion};
+use process::{Capture, Failure};
+use serde_json::{json, Value};
+use smol::{channel, future, io, lock::RwLock, process::Command};
+use std::{
+    borrow::Cow,
+    path::Path,
+    process::Output,
+    time::{Duration, Instant},
+};
+use tower_lsp::{jsonrpc, lsp_types as lsp};
 
 pub enum PassThrough {
-    ExecCommand(RwLock<Command>), // lspcat exec:"cli-command <row> <col> <file>"
-    LangServer(RwLock<Command>),  // lspcat serve:"lsp-server --stdio"
+    ExecCommand(Exec),           // lspcat exec:"cli-command {line} {character} {file}"
+    Worker(Worker),              // lspcat worker:"cli-command --server"
+    LangServer(RwLock<Command>), // lspcat serve:"lsp-server --stdio"
+}
+
+/// Template of an `exec:` command where every argument may contain `{placeholder}`
+pub struct Exec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub input: Input,
+    pub timeout: Option<Duration>, // each LSP method has its own `Exec`, so it is a per-method timeout
+    pub limits: Limits,
+    pub in_flight: InFlight,
+    pub cache: Cache,
+    pub parser: Parser,
+    pub report_file: Option<String>, // read instead of stdout after the command exit, e.g. "{path}.checkstyle.xml"
+    pub max_output: Option<usize>,   // in bytes, a command printing more is killed, e.g. 16 MiB
+}
+
+/// How the content of a document is delivered to an `exec:` command
+#[derive(Clone, Copy, PartialEq)]
+pub enum Input {
+    Mirror, // `{file}` is the path of the document inside the tempdir mirror
+    Stdin, // the content is piped into stdin, `{file}` is still the mirror path but it may not exist
+    Memfd, // `{file}` is `/proc/self/fd/N` of an in-memory file (Linux only)
+}
+
+impl Exec {
+    /// The command line with every `{key}` replaced by its value.
+    pub fn argv(&self, vars: &[(&str, &str)]) -> Vec<String> {
+        let args = self.args.iter().map(|arg| substitute(arg, vars));
+        [self.program.clone()].into_iter().chain(args).collect()
+    }
+
+    /// Build a fresh `Command` with every `{key}` in the arguments replaced by its value,
+    /// the only one inheriting the `memfd` of its document if any.
+    pub fn command(&self, vars: &[(&str, &str)], memfd: Option<&std::fs::File>) -> Command {
+        let args = self.args.iter().map(|arg| substitute(arg, vars));
+        match memfd {
+            Some(memfd) => process::command_inheriting(&self.program, args, memfd),
+            None => process::command(&self.program, args),
+        }
+    }
+
+    async fn output(
+        &self,
+        content: &Content,
+        vars: &[(&str, &str)],
+        lines: Option<channel::Sender<String>>,
+    ) -> Result<Output, Failure> {
+        use std::hash::{DefaultHasher, Hash as _, Hasher as _};
+
+        let mut hasher = DefaultHasher::new();
+        (vars, &content.path, &content.text).hash(&mut hasher);
+        (self.in_flight)
+            .run(&self.limits, &content.path, hasher.finish(), || {
+                self.spawn(content, vars, lines)
+            })
+            .await
+    }
+
+    async fn spawn(
+        &self,
+        content: &Content,
+        vars: &[(&str, &str)],
+        lines: Option<channel::Sender<String>>,
+    ) -> Result<Output, Failure> {
+        let path = content.path.to_string_lossy();
+        let memfd = match self.input {
+            Input::Memfd => Some(memfd(&content.text)?), // INFO: must live until the command exit
+            _ => None,
+        };
+        let file = match &memfd {
+            Some((_, fd_path)) => fd_path,
+            None => path.as_ref(),
+        };
+        let vars = [&[("file", file), ("path", &path)], vars].concat();
+        let report_file = (self.report_file.as_ref()).map(|template| substitute(template, &vars));
+        if let Some(report_file) = &report_file {
+            // INFO: a report left by a previous run must not be mistaken for the current one
+            let _ = smol::fs::remove_file(report_file).await;
+        }
+        let input = (self.input == Input::Stdin).then_some(content.text.as_str());
+        let capture = Capture {
+            max_output: self.max_output,
+            lines,
+        };
+        let mut command = self.command(&vars, memfd.as_ref().map(|(file, _)| file));
+        if content.root.is_dir() {
+            command.current_dir(&content.root); // INFO: so relative paths printed by the tool are relative to the root
+        }
+        let mut output = process::run(command, input, self.timeout, capture).await?;
+        if let Some(report_file) = report_file {
+            match smol::fs::read(&report_file).await {
+                Ok(report) if self.max_output.is_some_and(|max| report.len() > max) => {
+                    output.stdout = report;
+                    return Err(Failure::TooLarge(output));
+                }
+                Ok(report) => output.stdout = report,
+                // INFO: a command which failed before writing its report is reported with its own output
+                Err(_) if !output.status.success() => {}
+                Err(err) => return Err(Failure::Io(err.kind(), format!("{report_file}: {err}"))),
+            }
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+impl Exec {
+    /// An `exec:` command reading the mirror, which may run side by side without any timeout.
+    pub fn test(program: &str, args: &[&str], parser: Parser) -> Self {
+        Self {
+            program: program.into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            input: Input::Mirror,
+            timeout: None,
+            limits: Limits {
+                language: std::sync::Arc::new(smol::lock::Semaphore::new(1)),
+                global: std::sync::Arc::new(smol::lock::Semaphore::new(1)),
+            },
+            in_flight: InFlight::new(false),
+            cache: Cache::new(8),
+            parser,
+            report_file: None,
+            max_output: None,
+        }
+    }
+}
+
+impl PassThrough {
+    /// Run the `exec:` command (or ask a `worker:`) then deserialize its stdout as JSON.
+    /// The `{file}` and `{path}` placeholders are filled from the `content` according to [`Input`].
+    pub async fn exec<T>(&self, content: &Content, vars: &[(&str, &str)]) -> jsonrpc::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.run(content, vars, None).await
+    }
+
+    /// Like [`exec`](Self::exec), but each item of an NDJSON output is also sent to `items` as soon as
+    /// the `exec:` command print its line, e.g. to report partial results while it is still running.
+    pub async fn stream<T>(
+        &self,
+        content: &Content,
+        vars: &[(&str, &str)],
+        items: channel::Sender<Value>,
+    ) -> jsonrpc::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let (lines, printed) = channel::unbounded::<String>();
+        let forward = async {
+            while let Ok(line) = printed.recv().await {
+                if let Some(item) = self.parser().parse_line(&line) {
+                    let _ = items.send(item).await;
+                }
+            }
+        };
+        let (response, ()) = future::zip(self.run(content, vars, Some(lines)), forward).await;
+        response
+    }
+
+    async fn run<T>(
+        &self,
+        content: &Content,
+        vars: &[(&str, &str)],
+        lines: Option<channel::Sender<String>>,
+    ) -> jsonrpc::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let key = cache::key(&content.path, content.version, vars);
+        if let Some(stdout) = self.cache().and_then(|cache| cache.get(&key)) {
+            let stdout = String::from_utf8_lossy(&stdout); // INFO: only UTF-8 output is cached
+            return self
+                .parser()
+                .parse(&stdout, content)
+                .map_err(|err| Error::ParseError.msg(&err));
+        }
+
+        let start = Instant::now();
+        let path = content.path.to_string_lossy();
+        let (argv, output) = match self {
+            PassThrough::ExecCommand(exec) => (
+                exec.argv(&[&[("file", path.as_ref()), ("path", &path)], vars].concat()),
+                exec.output(content, vars, lines).await,
+            ),
+            PassThrough::Worker(worker) => (
+                worker.argv(),
+                (worker.request(content, vars).await).map(|stdout| Output {
+                    status: Default::default(),
+                    stdout,
+                    stderr: vec![],
+                }),
+            ),
+            PassThrough::LangServer(_) => {
+                return Err(Error::UnsupportedMethod.msg("serve:lsp-server is not implemented yet"))
+            }
+        };
+        let report = |output, message| report(&argv, start, output, message);
+
+        let output = match output {
+            Ok(output) => output,
+            Err(Failure::Spawn(err)) => {
+                return Err(Error::SpawnFailed.data(report(None, Some(&err))))
+            }
+            Err(Failure::TimedOut) => return Err(Error::Timeout.data(report(None, None))),
+            Err(Failure::Superseded) => {
+                return Err(Error::Cancelled.msg("superseded by a newer request"))
+            }
+            Err(Failure::Io(_, err)) => {
+                return Err(Error::NoResponse.data(report(None, Some(&err))))
+            }
+            Err(Failure::TooLarge(output)) => {
+                return Err(Error::OutputTooLarge.data(report(Some(&output), None)))
+            }
+        };
+        let stdout = match std::str::from_utf8(&output.stdout) {
+            Ok(stdout) => stdout,
+            Err(err) => {
+                let err = err.to_string();
+                return Err(Error::WrongEncoding.data(report(Some(&output), Some(&err))));
+            }
+        };
+        match self.parser().parse(stdout, content) {
+            Ok(response) => {
+                if let Some(cache) = self.cache() {
+                    cache.put(key, output.stdout.clone());
+                }
+                Ok(response)
+            }
+            // INFO: a tool may exit with an error code while still printing a valid response (e.g. linters)
+            Err(err) if !output.status.success() => {
+                Err(Error::NonZeroExit.data(report(Some(&output), Some(&err))))
+            }
+            Err(err) => Err(Error::ParseError.data(report(Some(&output), Some(&err)))),
+        }
+    }
+
+    fn parser(&self) -> &Parser {
+        match self {
+            PassThrough::ExecCommand(exec) => &exec.parser,
+            PassThrough::Worker(worker) => &worker.parser,
+            PassThrough::LangServer(_) => &Parser::Json,
+        }
+    }
+
+    fn cache(&self) -> Option<&Cache> {
+        match self {
+            PassThrough::ExecCommand(exec) => Some(&exec.cache),
+            PassThrough::Worker(worker) => Some(&worker.cache),
+            PassThrough::LangServer(_) => None,
+        }
+    }
+
+    pub fn input(&self) -> Input {
+        match self {
+            PassThrough::ExecCommand(exec) => exec.input,
+            PassThrough::Worker(_) | PassThrough::LangServer(_) => Input::Mirror,
+        }
+    }
+}
+
+/// Payload of a failed request with what is needed to debug a tool integration.
+fn report(
+    argv: &[String],
+    start: Instant,
+    output: Option<&Output>,
+    message: Option<&str>,
+) -> Value {
+    let mut data = json!({
+        "argv": argv,
+        "duration_ms": start.elapsed().as_millis() as u64,
+    });
+    if let Some(output) = output {
+        data["exit_code"] = output.status.code().into();
+        data["stdout"] = truncate(&output.stdout).into();
+        data["stderr"] = truncate(&output.stderr).into();
+    }
+    if let Some(message) = message {
+        data["message"] = message.into();
+    }
+    data
+}
+
+fn truncate(bytes: &[u8]) -> String {
+    const LIMIT: usize = 4096;
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(LIMIT)]);
+    match bytes.len() > LIMIT {
+        true => format!("{text}…"),
+        false => text.into_owned(),
+    }
+}
+
+/// Replace every `{key}` in `template` by its value.
+fn substitute(template: &str, vars: &[(&str, &str)]) -> String {
+    substitute_with(template, |key| {
+        (vars.iter())
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| Cow::Borrowed(*value))
+    })
+}
+
+/// Replace every `{key}` in `template` for which `value` has one, keeping the others as they are.
+/// INFO: a single pass, so a value (e.g. a path containing `{label}`) is never scanned for other keys
+fn substitute_with<'a>(template: &str, value: impl Fn(&str) -> Option<Cow<'a, str>>) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let key = after
+            .find(['{', '}'])
+            .filter(|end| after[*end..].starts_with('}'));
+        match key.and_then(|end| Some((end, value(&after[..end])?))) {
+            Some((end, value)) => {
+                text.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+impl ProxyColletion {
+    /// Whether any proxy read the document from the mirror, otherwise writing it to disk can be skipped.
+    pub fn needs_mirror(&self) -> bool {
+        (self.pass_throughs()).any(|proxy| proxy.input() == Input::Mirror)
+    }
+
+    /// Forget the cached responses on a `document` which changed or closed.
+    pub fn invalidate(&self, document: &Path) {
+        (self.pass_throughs().filter_map(PassThrough::cache))
+            .for_each(|cache| cache.invalidate(document));
+    }
+
+    fn pass_throughs(&self) -> impl Iterator<Item = &PassThrough> {
+        let completion = (self.completion.iter())
+            .flat_map(|it| [Some(&it.proxy), it.resolve.as_ref()])
+            .flatten();
+        let call_hierarchy = (self.call_hierarchy.iter())
+            .flat_map(|it| [&it.prepare, &it.incoming_calls, &it.outgoing_calls]);
+        let type_hierarchy =
+            (self.type_hierarchy.iter()).flat_map(|it| [&it.prepare, &it.supertypes, &it.subtypes]);
+        let diagnostics = self.diagnostics.iter().map(|it| &it.proxy);
+        let hover = self.hover.iter().map(|it| &it.proxy);
+        let signature_help = self.signature_help.iter().map(|it| &it.proxy);
+        let formatting = self.formatting.iter().map(|it| &it.proxy);
+        let fix_all = self.fix_all.iter().map(|it| &it.proxy);
+        completion
+            .chain(call_hierarchy)
+            .chain(type_hierarchy)
+            .chain(diagnostics)
+            .chain(hover)
+            .chain(signature_help)
+            .chain(formatting)
+            .chain(fix_all)
+    }
+}
+
+/// Create an in-memory file containing `text`, returning it together with the path to open it.
+/// It is closed on exec, see [`process::command_inheriting`] for the command which reads it.
+#[cfg(target_os = "linux")]
+fn memfd(text: &str) -> io::Result<(std::fs::File, String)> {
+    use std::{io::Write as _, os::fd::FromRawFd as _};
+
+    // INFO: with `MFD_CLOEXEC` so commands spawned concurrently from other threads don't inherit it
+    let fd = unsafe { libc::memfd_create(c"lspcat".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `fd` is a freshly created file descriptor which nothing else own
+    let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+    file.write_all(text.as_bytes())?;
+    Ok((file, format!("/proc/self/fd/{fd}")))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn memfd(_: &str) -> io::Result<(std::fs::File, String)> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "memfd input is only available on Linux",
+    ))
 }
 
 pub trait Proxy {
@@ -17,6 +441,7 @@ pub trait Proxy {
         &self,
         params: Self::Params,
         content: &Content,
+        client: Option<&lsp::ClientCapabilities>,
     ) -> jsonrpc::Result<Option<Self::Response>>;
 }
 
@@ -28,3 +453,56 @@ pub trait Capabilities {
         client: Option<Self::ClientCapabilities>,
     ) -> Option<Self::ServerOptions>;
 }
+
+/// Wrap the opaque `data` of an item with the `uri` of the document it was requested from,
+/// so that follow-up requests (e.g. `callHierarchy/incomingCalls`) can find the same proxy.
+pub fn wrap_data(origin: &lsp::Url, data: Option<Value>) -> Option<Value> {
+    Some(json!({ "uri": origin, "data": data }))
+}
+
+/// Reverse of [`wrap_data`], returning the origin `uri` (if any) and the original `data`.
+pub fn unwrap_data(data: Option<Value>) -> (Option<lsp::Url>, Option<Value>) {
+    match data {
+        Some(Value::Object(mut wrapper)) if wrapper.contains_key("uri") => {
+            let origin = wrapper
+                .remove("uri")
+                .and_then(|uri| serde_json::from_value(uri).ok());
+            let data = wrapper.remove("data").filter(|data| !data.is_null());
+            (origin, data)
+        }
+        data => (None, data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitute_once() {
+        let vars = [("file", "/tmp/{label}.res"), ("label", "map")];
+        assert_eq!(
+            substitute("{file}:{label}:{line}", &vars),
+            "/tmp/{label}.res:map:{line}"
+        );
+        assert_eq!(substitute("{{file}} {", &vars), "{/tmp/{label}.res} {");
+    }
+
+    #[test]
+    fn wrap_and_unwrap_data() {
+        let origin: lsp::Url = "file:///a.res".parse().unwrap();
+        let data = Some(json!({"id": 1}));
+        assert_eq!(
+            unwrap_data(wrap_data(&origin, data.clone())),
+            (Some(origin.clone()), data)
+        );
+        assert_eq!(unwrap_data(wrap_data(&origin, None)), (Some(origin), None));
+        // INFO: data which wasn't wrapped by lspcat is kept as it is
+        assert_eq!(unwrap_data(Some(json!([1]))), (None, Some(json!([1]))));
+        assert_eq!(
+            unwrap_data(Some(json!({"id": 1}))),
+            (None, Some(json!({"id": 1})))
+        );
+        assert_eq!(unwrap_data(None), (None, None));
+    }
+}
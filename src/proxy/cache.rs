@@ -0,0 +1,127 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+type Key = (PathBuf, i32, u64); // (document, version, hash of the placeholders e.g. position)
+
+/// Least recently used responses of one proxy, since editors repeat the same request
+/// (e.g. hovering the same symbol) while the document doesn't change.
+pub struct Cache {
+    entries: Mutex<HashMap<Key, (u64, Vec<u8>)>>, // Map<key, (last used, stdout)>
+    tick: AtomicU64,
+    capacity: usize,
+}
+
+impl Cache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::default(),
+            tick: AtomicU64::default(),
+            capacity,
+        }
+    }
+
+    pub fn get(&self, key: &Key) -> Option<Vec<u8>> {
+        let tick = self.tick();
+        let mut entries = self.entries.lock().ok()?;
+        let (used, stdout) = entries.get_mut(key)?;
+        *used = tick;
+        Some(stdout.clone())
+    }
+
+    pub fn put(&self, key: Key, stdout: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let tick = self.tick();
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let oldest = (entries.iter())
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, (tick, stdout));
+    }
+
+    /// Drop every response of a `document` which changed or closed.
+    pub fn invalidate(&self, document: &Path) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|(path, _, _), _| path != document);
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        self.tick.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// Cache key of a request with `vars` on the `version` of a `document`.
+pub fn key(document: &Path, version: i32, vars: &[(&str, &str)]) -> Key {
+    use std::hash::{DefaultHasher, Hash as _, Hasher as _};
+
+    let mut hasher = DefaultHasher::new();
+    vars.hash(&mut hasher);
+    (document.to_path_buf(), version, hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evict_the_least_recently_used() {
+        let cache = Cache::new(2);
+        let (a, b, c) = (Path::new("/a"), Path::new("/b"), Path::new("/c"));
+        cache.put(key(a, 1, &[]), b"a".to_vec());
+        cache.put(key(b, 1, &[]), b"b".to_vec());
+        assert_eq!(cache.get(&key(a, 1, &[])), Some(b"a".to_vec())); // INFO: `b` is now the oldest
+        cache.put(key(c, 1, &[]), b"c".to_vec());
+        assert_eq!(cache.get(&key(b, 1, &[])), None);
+        assert_eq!(cache.get(&key(a, 1, &[])), Some(b"a".to_vec()));
+
+        // INFO: replacing an entry doesn't evict another one
+        cache.put(key(c, 1, &[]), b"c2".to_vec());
+        assert_eq!(cache.get(&key(a, 1, &[])), Some(b"a".to_vec()));
+        assert_eq!(cache.get(&key(c, 1, &[])), Some(b"c2".to_vec()));
+
+        let disabled = Cache::new(0);
+        disabled.put(key(a, 1, &[]), b"a".to_vec());
+        assert_eq!(disabled.get(&key(a, 1, &[])), None);
+    }
+
+    #[test]
+    fn key_by_version_and_vars() {
+        let cache = Cache::new(8);
+        let document = Path::new("/a.py");
+        cache.put(key(document, 1, &[("line", "3")]), b"hover".to_vec());
+        assert_eq!(
+            cache.get(&key(document, 1, &[("line", "3")])),
+            Some(b"hover".to_vec())
+        );
+        assert_eq!(cache.get(&key(document, 2, &[("line", "3")])), None);
+        assert_eq!(cache.get(&key(document, 1, &[("line", "4")])), None);
+    }
+
+    #[test]
+    fn invalidate_a_document() {
+        let cache = Cache::new(8);
+        let (a, b) = (Path::new("/a.py"), Path::new("/b.py"));
+        cache.put(key(a, 1, &[("line", "1")]), b"1".to_vec());
+        cache.put(key(a, 1, &[("line", "2")]), b"2".to_vec());
+        cache.put(key(b, 1, &[]), b"b".to_vec());
+        cache.invalidate(a);
+        assert_eq!(cache.get(&key(a, 1, &[("line", "1")])), None);
+        assert_eq!(cache.get(&key(a, 1, &[("line", "2")])), None);
+        assert_eq!(cache.get(&key(b, 1, &[])), Some(b"b".to_vec()));
+    }
+}
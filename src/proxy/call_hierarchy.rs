@@ -0,0 +1,79 @@
+use super::hierarchy::{exec, origin, restore};
+use super::{PassThrough, Proxy};
+use crate::Content;
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types as lsp;
+
+pub struct CallHierarchy {
+    pub prepare: PassThrough,
+    pub incoming_calls: PassThrough,
+    pub outgoing_calls: PassThrough,
+}
+
+impl Proxy for CallHierarchy {
+    type Params = lsp::CallHierarchyPrepareParams;
+    type Response = Vec<lsp::CallHierarchyItem>;
+
+    async fn proxy_response(
+        &self,
+        params: Self::Params,
+        content: &Content,
+        _: Option<&lsp::ClientCapabilities>,
+    ) -> Result<Option<Self::Response>> {
+        let lsp::TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+        let (line, character) = (position.line.to_string(), position.character.to_string());
+        let items: Option<Self::Response> = self
+            .prepare
+            .exec(content, &[("line", &line), ("character", &character)])
+            .await?;
+        Ok(items.map(|items| {
+            items
+                .into_iter()
+                .map(|item| origin(item, &text_document.uri))
+                .collect()
+        }))
+    }
+}
+
+impl CallHierarchy {
+    pub async fn incoming_calls(
+        &self,
+        item: lsp::CallHierarchyItem,
+        content: &Content,
+    ) -> Result<Option<Vec<lsp::CallHierarchyIncomingCall>>> {
+        let (uri, item) = restore(item);
+        let calls: Option<Vec<lsp::CallHierarchyIncomingCall>> =
+            exec(&self.incoming_calls, &item, content).await?;
+        Ok(calls.map(|calls| {
+            calls
+                .into_iter()
+                .map(|call| lsp::CallHierarchyIncomingCall {
+                    from: origin(call.from, &uri),
+                    ..call
+                })
+                .collect()
+        }))
+    }
+
+    pub async fn outgoing_calls(
+        &self,
+        item: lsp::CallHierarchyItem,
+        content: &Content,
+    ) -> Result<Option<Vec<lsp::CallHierarchyOutgoingCall>>> {
+        let (uri, item) = restore(item);
+        let calls: Option<Vec<lsp::CallHierarchyOutgoingCall>> =
+            exec(&self.outgoing_calls, &item, content).await?;
+        Ok(calls.map(|calls| {
+            calls
+                .into_iter()
+                .map(|call| lsp::CallHierarchyOutgoingCall {
+                    to: origin(call.to, &uri),
+                    ..call
+                })
+                .collect()
+        }))
+    }
+}
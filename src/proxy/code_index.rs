@@ -0,0 +1,354 @@
+mod lsif;
+mod scip;
+
+use dashmap::DashMap;
+use smol::lock::Mutex;
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash as _, Hasher as _},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+use tower_lsp::lsp_types as lsp;
+
+/// Definitions, references, hovers and document symbols from a SCIP or LSIF index precomputed by CI.
+pub struct CodeIndex {
+    pub index_file: String, // `{workspace}/{index_file}`, either SCIP (protobuf) or LSIF (JSON lines), e.g. "index.scip"
+    pub loaded: Loaded,
+}
+
+/// The index of every workspace, loaded on the first request which need it
+/// and again whenever the index file changed.
+#[derive(Default)]
+pub struct Loaded {
+    workspaces: DashMap<PathBuf, Arc<Mutex<State>>>,
+}
+
+#[derive(Default)]
+struct State {
+    stamp: Option<(SystemTime, u64)>, // modified time and length of the index file last loaded (or failed to)
+    snapshot: Option<Arc<Snapshot>>,
+}
+
+/// An index where every position was converted to UTF-16.
+pub struct Snapshot {
+    documents: HashMap<PathBuf, Document>,
+    symbols: HashMap<String, Symbol>,
+}
+
+struct Document {
+    revision: Option<u64>, // hash of the indexed text, `None` when it is unknown
+    occurrences: Vec<Occurrence>,
+}
+
+struct Occurrence {
+    range: lsp::Range,
+    enclosing: Option<lsp::Range>, // e.g. the whole function of its name
+    symbol: String,
+    definition: bool,
+    documentation: Vec<String>, // Markdown which override the one of the symbol
+}
+
+#[derive(Default)]
+struct Symbol {
+    name: Option<String>,
+    kind: Option<lsp::SymbolKind>,
+    container: Option<String>,
+    documentation: Vec<String>, // Markdown
+    occurrences: Vec<(PathBuf, usize)>,
+}
+
+/// What SCIP and LSIF parsers produce before [`Snapshot::new`].
+#[derive(Default)]
+struct Raw {
+    documents: Vec<RawDocument>,
+    symbols: HashMap<String, Symbol>,
+}
+
+struct RawDocument {
+    path: PathBuf, // relative to the workspace, unless the indexer couldn't tell
+    text: Option<String>,
+    encoding: Encoding, // of the columns of `occurrences`
+    occurrences: Vec<Occurrence>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Encoding {
+    Utf8,
+    Utf16,
+    Utf32,
+}
+
+impl CodeIndex {
+    /// The index of a workspace, loaded again when its file changed. A workspace without index file has none.
+    /// A file which fails to load is reported once along with the snapshot previously loaded.
+    pub async fn snapshot(&self, root: &Path) -> (Option<Arc<Snapshot>>, Option<String>) {
+        let path = root.join(&self.index_file);
+        let state = self
+            .loaded
+            .workspaces
+            .entry(root.into())
+            .or_default()
+            .clone();
+        let mut state = state.lock().await;
+
+        let metadata = match smol::fs::metadata(&path).await {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                *state = State::default();
+                return (None, None);
+            }
+            Err(err) => {
+                let err = format!("{}: {err}", path.display());
+                return (state.snapshot.clone(), Some(err));
+            }
+        };
+        let stamp = (metadata.modified().ok()).map(|modified| (modified, metadata.len()));
+        if stamp.is_some() && stamp == state.stamp {
+            return (state.snapshot.clone(), None);
+        }
+        state.stamp = stamp;
+        match load(&path, root).await {
+            Ok(snapshot) => {
+                state.snapshot = Some(Arc::new(snapshot));
+                (state.snapshot.clone(), None)
+            }
+            // INFO: e.g. a file still being written, it is tried again once it changed
+            Err(err) => (
+                state.snapshot.clone(),
+                Some(format!("{}: {err}", path.display())),
+            ),
+        }
+    }
+}
+
+async fn load(path: &Path, root: &Path) -> Result<Snapshot, String> {
+    let bytes = smol::fs::read(path).await.map_err(|err| err.to_string())?;
+    let root = root.to_path_buf();
+    smol::unblock(move || {
+        // INFO: a SCIP index start with the tag of a protobuf field, never with `{` or `[`
+        let raw = match bytes.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            Some(b'{' | b'[') => lsif::parse(&String::from_utf8_lossy(&bytes))?,
+            _ => scip::parse(&bytes)?,
+        };
+        Ok(Snapshot::new(&root, raw))
+    })
+    .await
+}
+
+impl Snapshot {
+    /// Resolve the documents against the workspace `root` and convert their columns to UTF-16.
+    /// INFO: without the text in the index, the indexed revision is the file on disk when it is loaded.
+    fn new(root: &Path, raw: Raw) -> Self {
+        let Raw {
+            documents: raw_documents,
+            mut symbols,
+        } = raw;
+        let mut documents = HashMap::new();
+        for document in raw_documents {
+            let path = root.join(&document.path);
+            let text = (document.text).or_else(|| std::fs::read_to_string(&path).ok());
+            let mut occurrences = document.occurrences;
+            if let (Some(text), Encoding::Utf8 | Encoding::Utf32) = (&text, document.encoding) {
+                if !text.is_ascii() {
+                    let lines: Vec<_> = text.lines().collect();
+                    for occurrence in &mut occurrences {
+                        occurrence.range = to_utf16(&lines, occurrence.range, document.encoding);
+                        occurrence.enclosing = (occurrence.enclosing)
+                            .map(|range| to_utf16(&lines, range, document.encoding));
+                    }
+                }
+            }
+            for (index, occurrence) in occurrences.iter().enumerate() {
+                let symbol = symbols.entry(occurrence.symbol.clone()).or_default();
+                symbol.occurrences.push((path.clone(), index));
+            }
+            documents.insert(
+                path,
+                Document {
+                    revision: text.as_deref().map(hash),
+                    occurrences,
+                },
+            );
+        }
+        Self { documents, symbols }
+    }
+
+    /// Whether the `text` of a `document` is the one which was indexed, otherwise its positions can't be trusted.
+    pub fn is_current(&self, document: &Path, text: &str) -> bool {
+        (self.documents.get(document))
+            .and_then(|document| document.revision)
+            .is_some_and(|revision| revision == hash(text))
+    }
+
+    /// Where the symbol under the cursor is defined.
+    pub fn definition(
+        &self,
+        document: &Path,
+        position: lsp::Position,
+        link_support: bool,
+    ) -> Option<lsp::GotoDefinitionResponse> {
+        let origin = self.occurrence_at(document, position)?;
+        let definitions: Vec<_> = (self.occurrences(&origin.symbol))
+            .filter(|(_, occurrence)| occurrence.definition)
+            .collect();
+        if definitions.is_empty() {
+            return None;
+        }
+        Some(match link_support {
+            true => lsp::GotoDefinitionResponse::Link(
+                (definitions.into_iter())
+                    .filter_map(|(path, occurrence)| {
+                        Some(lsp::LocationLink {
+                            origin_selection_range: Some(origin.range),
+                            target_uri: lsp::Url::from_file_path(path).ok()?,
+                            target_range: occurrence.enclosing.unwrap_or(occurrence.range),
+                            target_selection_range: occurrence.range,
+                        })
+                    })
+                    .collect(),
+            ),
+            false => lsp::GotoDefinitionResponse::Array(
+                (definitions.into_iter())
+                    .filter_map(|(path, occurrence)| location(path, occurrence))
+                    .collect(),
+            ),
+        })
+    }
+
+    pub fn symbol_at(&self, document: &Path, position: lsp::Position) -> Option<&str> {
+        Some(&self.occurrence_at(document, position)?.symbol)
+    }
+
+    /// Whether a `symbol` has the same name in the index of another workspace, e.g. one using a library.
+    /// Only global SCIP symbols do, local ones are qualified by their document and LSIF ones by their index.
+    pub fn is_shared(symbol: &str) -> bool {
+        !symbol.starts_with("local ") && !symbol.starts_with("lsif:")
+    }
+
+    /// Every occurrence of a `symbol`, its definitions only when `include_declaration`.
+    pub fn references(&self, symbol: &str, include_declaration: bool) -> Vec<lsp::Location> {
+        (self.occurrences(symbol))
+            .filter(|(_, occurrence)| include_declaration || !occurrence.definition)
+            .filter_map(|(path, occurrence)| location(path, occurrence))
+            .collect()
+    }
+
+    pub fn hover(&self, document: &Path, position: lsp::Position) -> Option<lsp::Hover> {
+        let occurrence = self.occurrence_at(document, position)?;
+        let documentation = match occurrence.documentation.is_empty() {
+            true => &self.symbols.get(&occurrence.symbol)?.documentation,
+            false => &occurrence.documentation,
+        };
+        if documentation.is_empty() {
+            return None;
+        }
+        Some(lsp::Hover {
+            contents: lsp::HoverContents::Markup(lsp::MarkupContent {
+                kind: lsp::MarkupKind::Markdown,
+                value: documentation.join("\n\n---\n\n"),
+            }),
+            range: Some(occurrence.range),
+        })
+    }
+
+    /// The named symbols defined in a `document`, in the order of the text.
+    pub fn document_symbols(&self, document: &Path) -> Option<Vec<lsp::SymbolInformation>> {
+        let uri = lsp::Url::from_file_path(document).ok()?;
+        let mut definitions: Vec<_> = (self.documents.get(document)?.occurrences.iter())
+            .filter(|occurrence| occurrence.definition)
+            .filter_map(|occurrence| {
+                let symbol = self.symbols.get(&occurrence.symbol)?;
+                Some((occurrence, symbol, symbol.name.as_ref()?))
+            })
+            .collect();
+        definitions.sort_by_key(|(occurrence, _, _)| {
+            let start = occurrence.range.start;
+            (start.line, start.character)
+        });
+        Some(
+            (definitions.into_iter())
+                .map(|(occurrence, symbol, name)| {
+                    #[allow(deprecated)] // INFO: `deprecated` is replaced by `tags`
+                    lsp::SymbolInformation {
+                        name: name.clone(),
+                        kind: symbol.kind.unwrap_or(lsp::SymbolKind::VARIABLE),
+                        tags: None,
+                        deprecated: None,
+                        location: lsp::Location {
+                            uri: uri.clone(),
+                            range: occurrence.range,
+                        },
+                        container_name: symbol.container.clone(),
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// The innermost occurrence under the cursor, which may be right after its last character.
+    fn occurrence_at(&self, document: &Path, position: lsp::Position) -> Option<&Occurrence> {
+        let at = (position.line, position.character);
+        (self.documents.get(document)?.occurrences.iter())
+            .filter(|occurrence| {
+                let lsp::Range { start, end } = occurrence.range;
+                (start.line, start.character) <= at && at <= (end.line, end.character)
+            })
+            .min_by_key(|occurrence| {
+                let lsp::Range { start, end } = occurrence.range;
+                (
+                    end.line - start.line,
+                    end.character.wrapping_sub(start.character),
+                )
+            })
+    }
+
+    fn occurrences<'a>(
+        &'a self,
+        symbol: &str,
+    ) -> impl Iterator<Item = (&'a PathBuf, &'a Occurrence)> + 'a {
+        (self.symbols.get(symbol).into_iter())
+            .flat_map(|symbol| symbol.occurrences.iter())
+            .filter_map(|(path, index)| {
+                Some((path, self.documents.get(path)?.occurrences.get(*index)?))
+            })
+    }
+}
+
+fn location(path: &Path, occurrence: &Occurrence) -> Option<lsp::Location> {
+    Some(lsp::Location {
+        uri: lsp::Url::from_file_path(path).ok()?,
+        range: occurrence.range,
+    })
+}
+
+fn hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Convert a range whose columns are counted in UTF-8 bytes or in characters.
+fn to_utf16(lines: &[&str], range: lsp::Range, encoding: Encoding) -> lsp::Range {
+    let position = |position: lsp::Position| {
+        let Some(line) = lines.get(position.line as usize) else {
+            return position;
+        };
+        let mut count = 0;
+        let character = (line.chars())
+            .take_while(|char| {
+                count += match encoding {
+                    Encoding::Utf8 => char.len_utf8(),
+                    Encoding::Utf16 => char.len_utf16(),
+                    Encoding::Utf32 => 1,
+                };
+                count <= position.character as usize
+            })
+            .map(char::len_utf16)
+            .sum::<usize>();
+        lsp::Position::new(position.line, character as u32)
+    };
+    lsp::Range::new(position(range.start), position(range.end))
+}
@@ -0,0 +1,281 @@
+use super::{Encoding, Occurrence, Raw, RawDocument};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+use tower_lsp::lsp_types as lsp;
+
+const MAX_CHAIN: usize = 64; // INFO: `next` edges of a malformed dump may loop
+
+/// Read an LSIF dump with one vertex or edge per line, or all of them in an array,
+/// see https://microsoft.github.io/language-server-protocol/specifications/lsif/0.6.0/specification/
+pub fn parse(text: &str) -> Result<Raw, String> {
+    let elements: Vec<Value> = match text.trim_start().starts_with('[') {
+        true => serde_json::from_str(text).map_err(|err| err.to_string())?,
+        false => (text.lines())
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|err| err.to_string()))
+            .collect::<Result<_, _>>()?,
+    };
+    let mut graph = Graph::default();
+    for element in &elements {
+        graph.add(element);
+    }
+    Ok(graph.raw())
+}
+
+/// The vertices and edges needed to answer requests, by id.
+#[derive(Default)]
+struct Graph<'a> {
+    project_root: Option<PathBuf>,
+    documents: Vec<(String, &'a str)>, // id and uri
+    ranges: HashMap<String, &'a Value>,
+    hover_results: HashMap<String, &'a Value>, // their `contents`
+    contains: HashMap<String, Vec<String>>,
+    next: HashMap<String, String>,
+    definition: HashMap<String, String>, // range or result set to its definition result
+    references: HashMap<String, String>, // range or result set to its reference result
+    hover: HashMap<String, String>,      // range or result set to its hover result
+    items: HashMap<String, Vec<(String, bool)>>, // result to ranges, with whether they are definitions
+}
+
+impl<'a> Graph<'a> {
+    fn add(&mut self, element: &'a Value) {
+        let Some(id) = key(&element["id"]) else {
+            return;
+        };
+        let label = element["label"].as_str().unwrap_or_default();
+        if element["type"] == "vertex" {
+            match label {
+                "metaData" => {
+                    self.project_root = (element["projectRoot"].as_str())
+                        .and_then(|root| lsp::Url::parse(root).ok()?.to_file_path().ok())
+                }
+                "document" => self
+                    .documents
+                    .extend(element["uri"].as_str().map(|uri| (id, uri))),
+                "range" => _ = self.ranges.insert(id, element),
+                "hoverResult" => {
+                    _ = self
+                        .hover_results
+                        .insert(id, &element["result"]["contents"])
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        let Some(out) = key(&element["outV"]) else {
+            return;
+        };
+        let mut ins = (element["inVs"].as_array().into_iter().flatten())
+            .chain([&element["inV"]])
+            .filter_map(key);
+        match label {
+            "contains" => self.contains.entry(out).or_default().extend(ins),
+            "next" => self.next.extend(ins.next().map(|next| (out, next))),
+            "textDocument/definition" => self.definition.extend(ins.next().map(|it| (out, it))),
+            "textDocument/references" => self.references.extend(ins.next().map(|it| (out, it))),
+            "textDocument/hover" => self.hover.extend(ins.next().map(|it| (out, it))),
+            // INFO: `referenceResults` items are other results rather than ranges
+            "item" if element["property"] != "referenceResults" => {
+                let definitions = element["property"] == "definitions";
+                let items = self.items.entry(out).or_default();
+                items.extend(ins.map(|range| (range, definitions)));
+            }
+            _ => {}
+        }
+    }
+
+    /// A symbol for each reference result (or definition result when there are none),
+    /// which ranges reach through their chain of result sets.
+    fn raw(self) -> Raw {
+        let definition_results: HashSet<_> = self.definition.values().collect();
+        let definitions: HashSet<_> = (self.items.iter())
+            .flat_map(|(result, items)| {
+                let all = definition_results.contains(result);
+                (items.iter())
+                    .filter(move |(_, definition)| all || *definition)
+                    .map(|(range, _)| range)
+            })
+            .collect();
+
+        let mut raw = Raw::default();
+        for (id, uri) in &self.documents {
+            let Some(path) = lsp::Url::parse(uri)
+                .ok()
+                .and_then(|uri| uri.to_file_path().ok())
+            else {
+                continue;
+            };
+            // INFO: the project root is where CI checked out, not the workspace
+            let path = match &self.project_root {
+                Some(root) => path.strip_prefix(root).map(Into::into).unwrap_or(path),
+                None => path,
+            };
+            let occurrences = (self.contains.get(id).into_iter().flatten())
+                .filter_map(|range_id| {
+                    let range = self.ranges.get(range_id)?;
+                    let chain: Vec<_> =
+                        std::iter::successors(Some(range_id), |id| self.next.get(*id))
+                            .take(MAX_CHAIN)
+                            .collect();
+                    let first = |edges| find(&chain, edges);
+                    let result = (first(&self.references))
+                        .or_else(|| first(&self.definition))
+                        .or(chain.last().copied())?;
+                    let symbol = format!("lsif:{result}");
+
+                    let tag = &range["tag"];
+                    let is_definition = tag["type"] == "definition";
+                    let information = raw.symbols.entry(symbol.clone()).or_default();
+                    if information.documentation.is_empty() {
+                        let contents = first(&self.hover).and_then(|id| self.hover_results.get(id));
+                        information.documentation =
+                            contents.map(|it| markdown(it)).unwrap_or_default();
+                    }
+                    if is_definition {
+                        information.name = tag["text"].as_str().map(Into::into);
+                        information.kind = serde_json::from_value(tag["kind"].clone()).ok();
+                    }
+                    Some(Occurrence {
+                        range: self::range(range)?,
+                        enclosing: self::range(&tag["fullRange"]),
+                        symbol,
+                        definition: is_definition || definitions.contains(range_id),
+                        documentation: vec![],
+                    })
+                })
+                .collect();
+            raw.documents.push(RawDocument {
+                path,
+                text: None,
+                encoding: Encoding::Utf16,
+                occurrences,
+            });
+        }
+        raw
+    }
+}
+
+/// The result of the first element of a chain of result sets which has one.
+fn find<'a>(chain: &[&String], edges: &'a HashMap<String, String>) -> Option<&'a String> {
+    chain.iter().find_map(|id| edges.get(*id))
+}
+
+/// The key of an id, which may be a number or a string.
+fn key(id: &Value) -> Option<String> {
+    match id {
+        Value::Number(number) => Some(number.to_string()),
+        Value::String(id) => Some(id.clone()),
+        _ => None,
+    }
+}
+
+fn range(range: &Value) -> Option<lsp::Range> {
+    Some(lsp::Range::new(
+        serde_json::from_value(range["start"].clone()).ok()?,
+        serde_json::from_value(range["end"].clone()).ok()?,
+    ))
+}
+
+/// The Markdown of hover `contents`, a `MarkupContent` or one or many `MarkedString`.
+fn markdown(contents: &Value) -> Vec<String> {
+    match contents {
+        Value::Array(contents) => contents.iter().flat_map(markdown).collect(),
+        Value::String(text) => vec![text.clone()],
+        contents => match (contents["language"].as_str(), contents["value"].as_str()) {
+            (Some(language), Some(value)) => vec![format!("```{language}\n{value}\n```")],
+            (None, Some(value)) => vec![value.into()],
+            _ => vec![],
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Snapshot;
+    use super::*;
+    use std::path::Path;
+
+    // INFO: the reference reach the result set of the definition through another one
+    const DUMP: &str = r#"
+{"id":1,"type":"vertex","label":"metaData","projectRoot":"file:///ci"}
+{"id":2,"type":"vertex","label":"document","uri":"file:///ci/src/a.ts"}
+{"id":3,"type":"vertex","label":"range","start":{"line":0,"character":9},"end":{"line":0,"character":12},"tag":{"type":"definition","text":"foo","kind":12,"fullRange":{"start":{"line":0,"character":0},"end":{"line":0,"character":17}}}}
+{"id":4,"type":"vertex","label":"range","start":{"line":1,"character":0},"end":{"line":1,"character":3},"tag":{"type":"reference","text":"foo"}}
+{"id":5,"type":"vertex","label":"resultSet"}
+{"id":6,"type":"vertex","label":"resultSet"}
+{"id":7,"type":"edge","label":"next","outV":3,"inV":5}
+{"id":8,"type":"edge","label":"next","outV":4,"inV":6}
+{"id":9,"type":"edge","label":"next","outV":6,"inV":5}
+{"id":10,"type":"vertex","label":"definitionResult"}
+{"id":11,"type":"edge","label":"textDocument/definition","outV":5,"inV":10}
+{"id":12,"type":"edge","label":"item","outV":10,"inVs":[3],"document":2}
+{"id":13,"type":"vertex","label":"referenceResult"}
+{"id":14,"type":"edge","label":"textDocument/references","outV":5,"inV":13}
+{"id":15,"type":"edge","label":"item","outV":13,"inVs":[3],"document":2,"property":"definitions"}
+{"id":16,"type":"edge","label":"item","outV":13,"inVs":[4],"document":2,"property":"references"}
+{"id":17,"type":"vertex","label":"hoverResult","result":{"contents":{"language":"typescript","value":"function foo(): void"}}}
+{"id":18,"type":"edge","label":"textDocument/hover","outV":5,"inV":17}
+{"id":19,"type":"vertex","label":"range","start":{"line":2,"character":0},"end":{"line":2,"character":1},"tag":{"type":"reference","text":"x"}}
+{"id":20,"type":"vertex","label":"resultSet"}
+{"id":21,"type":"edge","label":"next","outV":19,"inV":20}
+{"id":22,"type":"edge","label":"next","outV":20,"inV":19}
+{"id":23,"type":"edge","label":"contains","outV":2,"inVs":[3,4,19]}
+"#;
+
+    fn snapshot() -> Snapshot {
+        Snapshot::new(Path::new("/ws"), parse(DUMP).unwrap())
+    }
+
+    #[test]
+    fn next_chains() {
+        let snapshot = snapshot();
+        let a = Path::new("/ws/src/a.ts");
+        let Some(lsp::GotoDefinitionResponse::Array(locations)) =
+            snapshot.definition(a, lsp::Position::new(1, 1), false)
+        else {
+            panic!("no definition");
+        };
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].uri.path(), "/ws/src/a.ts");
+        assert_eq!(locations[0].range.start, lsp::Position::new(0, 9));
+
+        let symbol = snapshot.symbol_at(a, lsp::Position::new(1, 1)).unwrap();
+        assert_eq!(symbol, "lsif:13");
+        assert!(!Snapshot::is_shared(symbol));
+        let references = snapshot.references(symbol, false);
+        assert_eq!(references.len(), 1);
+        assert_eq!(references[0].range.start, lsp::Position::new(1, 0));
+        assert_eq!(snapshot.references(symbol, true).len(), 2);
+
+        let hover = snapshot.hover(a, lsp::Position::new(1, 1)).unwrap();
+        assert!(matches!(
+            hover.contents,
+            lsp::HoverContents::Markup(markup) if markup.value == "```typescript\nfunction foo(): void\n```"
+        ));
+        let symbols = snapshot.document_symbols(a).unwrap();
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].name, "foo");
+        assert_eq!(symbols[0].kind, lsp::SymbolKind::FUNCTION);
+    }
+
+    #[test]
+    fn looping_chain() {
+        let snapshot = snapshot();
+        let a = Path::new("/ws/src/a.ts");
+        assert!(snapshot.symbol_at(a, lsp::Position::new(2, 0)).is_some());
+        assert!(snapshot.hover(a, lsp::Position::new(2, 0)).is_none());
+    }
+
+    #[test]
+    fn array_dump() {
+        let lines: Vec<_> = DUMP.lines().filter(|line| !line.is_empty()).collect();
+        let raw = parse(&format!("[{}]", lines.join(",\n"))).unwrap();
+        assert_eq!(raw.documents.len(), 1);
+        assert_eq!(raw.documents[0].path, Path::new("src/a.ts"));
+        assert_eq!(raw.documents[0].occurrences.len(), 3);
+    }
+}
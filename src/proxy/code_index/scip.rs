@@ -0,0 +1,507 @@
+use super::{Encoding, Occurrence, Raw, RawDocument, Symbol};
+use std::path::PathBuf;
+use tower_lsp::lsp_types as lsp;
+
+const DEFINITION: u64 = 0x1; // of `Occurrence.symbol_roles`
+
+/// Read a SCIP `Index` message, see https://github.com/sourcegraph/scip/blob/main/scip.proto
+pub fn parse(bytes: &[u8]) -> Result<Raw, String> {
+    let mut raw = Raw::default();
+    for field in Message(bytes) {
+        match field? {
+            (2, Wire::Bytes(document)) => {
+                let document = self::document(document, &mut raw)?;
+                raw.documents.push(document);
+            }
+            (3, Wire::Bytes(information)) => {
+                symbol_information(information, &mut raw)?;
+            }
+            _ => {} // INFO: the `project_root` of `metadata` is where CI checked out, not the workspace
+        }
+    }
+    Ok(raw)
+}
+
+fn document(bytes: &[u8], raw: &mut Raw) -> Result<RawDocument, String> {
+    let mut document = RawDocument {
+        path: PathBuf::new(),
+        text: None,
+        encoding: Encoding::Utf16, // INFO: older indexers which didn't tell were for JVM or TypeScript
+        occurrences: vec![],
+    };
+    let mut informations = vec![];
+    for field in Message(bytes) {
+        match field? {
+            (1, Wire::Bytes(path)) => document.path = PathBuf::from(string(path)),
+            (2, Wire::Bytes(occurrence)) => {
+                document.occurrences.push(self::occurrence(occurrence)?)
+            }
+            (3, Wire::Bytes(information)) => informations.push(information),
+            (5, Wire::Bytes(text)) if !text.is_empty() => document.text = Some(string(text)),
+            (6, Wire::Varint(encoding)) => {
+                document.encoding = match encoding {
+                    1 => Encoding::Utf8,
+                    3 => Encoding::Utf32,
+                    _ => Encoding::Utf16,
+                }
+            }
+            _ => {}
+        }
+    }
+    // INFO: `local` symbols are only unique within their document
+    let path = document.path.to_string_lossy().into_owned();
+    for occurrence in &mut document.occurrences {
+        if occurrence.definition && !is_local(&occurrence.symbol) {
+            describe(
+                raw.symbols.entry(occurrence.symbol.clone()).or_default(),
+                &occurrence.symbol,
+            );
+        }
+        occurrence.symbol = qualify(&path, &occurrence.symbol);
+    }
+    for information in informations {
+        let symbol = symbol_information(information, raw)?;
+        if is_local(&symbol) {
+            let information = raw.symbols.remove(&symbol).unwrap_or_default();
+            raw.symbols.insert(qualify(&path, &symbol), information);
+        }
+    }
+    Ok(document)
+}
+
+fn occurrence(bytes: &[u8]) -> Result<Occurrence, String> {
+    let (mut range, mut enclosing, mut roles) = (vec![], vec![], 0);
+    let mut occurrence = Occurrence {
+        range: lsp::Range::default(),
+        enclosing: None,
+        symbol: String::new(),
+        definition: false,
+        documentation: vec![],
+    };
+    for field in Message(bytes) {
+        match field? {
+            (1, wire) => int32s(wire, &mut range)?,
+            (2, Wire::Bytes(symbol)) => occurrence.symbol = string(symbol),
+            (3, Wire::Varint(value)) => roles = value,
+            (4, Wire::Bytes(documentation)) => occurrence.documentation.push(string(documentation)),
+            (7, wire) => int32s(wire, &mut enclosing)?,
+            _ => {}
+        }
+    }
+    occurrence.range = self::range(&range).ok_or("invalid occurrence range")?;
+    occurrence.enclosing = self::range(&enclosing);
+    occurrence.definition = roles & DEFINITION != 0;
+    Ok(occurrence)
+}
+
+/// Merge a `SymbolInformation` into the symbols, returning its name.
+fn symbol_information(bytes: &[u8], raw: &mut Raw) -> Result<String, String> {
+    let (mut name, mut documentation, mut display_name, mut signature) = (None, vec![], None, None);
+    for field in Message(bytes) {
+        match field? {
+            (1, Wire::Bytes(symbol)) => name = Some(string(symbol)),
+            (3, Wire::Bytes(text)) => documentation.push(string(text)),
+            (6, Wire::Bytes(text)) if !text.is_empty() => display_name = Some(string(text)),
+            (7, Wire::Bytes(document)) => signature = self::signature(document)?,
+            _ => {}
+        }
+    }
+    let name = name.ok_or("symbol information without symbol")?;
+    let symbol = raw.symbols.entry(name.clone()).or_default();
+    if signature.is_some() || !documentation.is_empty() {
+        symbol.documentation = signature.into_iter().chain(documentation).collect();
+    }
+    describe(symbol, &name);
+    symbol.name = display_name.or(symbol.name.take());
+    Ok(name)
+}
+
+/// The name, container and kind of a symbol from its descriptors, unless it has some.
+fn describe(symbol: &mut Symbol, name: &str) {
+    if symbol.name.is_some() {
+        return;
+    }
+    let descriptors = descriptors(name);
+    if let Some((last, rest)) = descriptors.split_last() {
+        let container = rest.last();
+        symbol.name = Some(last.0.clone());
+        symbol.container = container.map(|(name, _)| name.clone());
+        symbol.kind = Some(kind(last.1, container.map(|(_, suffix)| *suffix)));
+    }
+}
+
+/// A code block of the `signature_documentation`, which is a `Document` with only `language` and `text`.
+fn signature(bytes: &[u8]) -> Result<Option<String>, String> {
+    let (mut language, mut text) = (String::new(), None);
+    for field in Message(bytes) {
+        match field? {
+            (4, Wire::Bytes(bytes)) => language = string(bytes).to_lowercase(),
+            (5, Wire::Bytes(bytes)) if !bytes.is_empty() => text = Some(string(bytes)),
+            _ => {}
+        }
+    }
+    Ok(text.map(|text| format!("```{language}\n{text}\n```")))
+}
+
+/// `[line, character, end character]` or `[line, character, end line, end character]`
+fn range(range: &[i32]) -> Option<lsp::Range> {
+    let range: Vec<_> = range.iter().map(|&value| value.max(0) as u32).collect();
+    let (start, end) = match range[..] {
+        [line, character, end] => ((line, character), (line, end)),
+        [line, character, end_line, end] => ((line, character), (end_line, end)),
+        _ => return None,
+    };
+    Some(lsp::Range::new(
+        lsp::Position::new(start.0, start.1),
+        lsp::Position::new(end.0, end.1),
+    ))
+}
+
+fn is_local(symbol: &str) -> bool {
+    symbol.starts_with("local ")
+}
+
+/// `local N` followed by the document, so it is still a local symbol.
+fn qualify(path: &str, symbol: &str) -> String {
+    match is_local(symbol) {
+        true => format!("{symbol} {path}"),
+        false => symbol.into(),
+    }
+}
+
+/// What ends a descriptor of a symbol, which tells its kind.
+#[derive(Clone, Copy, PartialEq)]
+enum Suffix {
+    Namespace,     // `name/`
+    Type,          // `name#`
+    Term,          // `name.`
+    Method,        // `name(disambiguator).`
+    TypeParameter, // `[name]`
+    Parameter,     // `(name)`
+    Meta,          // `name:`
+    Macro,         // `name!`
+}
+
+/// The descriptors of `scheme manager package version descriptors…`, where a double space escape a space.
+/// A local symbol has none, so it isn't listed in the symbols of its document.
+fn descriptors(symbol: &str) -> Vec<(String, Suffix)> {
+    if is_local(symbol) {
+        return vec![];
+    }
+    let mut rest = symbol;
+    for _ in 0..4 {
+        let mut index = 0;
+        rest = loop {
+            let Some(offset) = rest[index..].find(' ') else {
+                return vec![];
+            };
+            index += offset;
+            match rest[index + 1..].starts_with(' ') {
+                true => index += 2,
+                false => break &rest[index + 1..],
+            }
+        };
+    }
+
+    let mut descriptors = vec![];
+    let mut chars = rest.chars().peekable();
+    while chars.peek().is_some() {
+        let mut name = String::new();
+        let suffix = match chars.peek() {
+            Some('[') => {
+                chars.next();
+                name.extend(chars.by_ref().take_while(|&char| char != ']'));
+                Suffix::TypeParameter
+            }
+            Some('(') => {
+                chars.next();
+                name.extend(chars.by_ref().take_while(|&char| char != ')'));
+                Suffix::Parameter
+            }
+            _ => {
+                // INFO: a name with other characters is quoted by backticks, which are doubled inside
+                if chars.next_if_eq(&'`').is_some() {
+                    while let Some(char) = chars.next() {
+                        match char {
+                            '`' if chars.next_if_eq(&'`').is_none() => break,
+                            char => name.push(char),
+                        }
+                    }
+                } else {
+                    while let Some(char) =
+                        chars.next_if(|&char| char.is_alphanumeric() || "_+-$".contains(char))
+                    {
+                        name.push(char);
+                    }
+                }
+                match chars.next() {
+                    Some('/') => Suffix::Namespace,
+                    Some('#') => Suffix::Type,
+                    Some('.') => Suffix::Term,
+                    Some(':') => Suffix::Meta,
+                    Some('!') => Suffix::Macro,
+                    Some('(') => {
+                        chars
+                            .by_ref()
+                            .take_while(|&char| char != ')')
+                            .for_each(drop);
+                        chars.next_if_eq(&'.');
+                        Suffix::Method
+                    }
+                    _ => return descriptors, // INFO: not a valid symbol, what was read is still useful
+                }
+            }
+        };
+        descriptors.push((name, suffix));
+    }
+    descriptors
+}
+
+/// INFO: every indexer emit descriptors, while the `kind` of `SymbolInformation` is a recent addition.
+fn kind(suffix: Suffix, container: Option<Suffix>) -> lsp::SymbolKind {
+    let in_type = container == Some(Suffix::Type);
+    match suffix {
+        Suffix::Namespace => lsp::SymbolKind::NAMESPACE,
+        Suffix::Type => lsp::SymbolKind::CLASS,
+        Suffix::Term if in_type => lsp::SymbolKind::FIELD,
+        Suffix::Term | Suffix::Parameter => lsp::SymbolKind::VARIABLE,
+        Suffix::Method if in_type => lsp::SymbolKind::METHOD,
+        Suffix::Method | Suffix::Macro => lsp::SymbolKind::FUNCTION,
+        Suffix::TypeParameter => lsp::SymbolKind::TYPE_PARAMETER,
+        Suffix::Meta => lsp::SymbolKind::PROPERTY,
+    }
+}
+
+/// A value of the protobuf wire format.
+enum Wire<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]), // a string, a nested message or packed numbers
+    Fixed,           // 32 or 64 bits, which SCIP doesn't use
+}
+
+/// The fields of an encoded protobuf message, with their number.
+struct Message<'a>(&'a [u8]);
+
+impl<'a> Message<'a> {
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        for (index, &byte) in self.0.iter().enumerate().take(10) {
+            value |= ((byte & 0x7f) as u64) << (7 * index);
+            if byte & 0x80 == 0 {
+                self.0 = &self.0[index + 1..];
+                return Ok(value);
+            }
+        }
+        Err("truncated varint".into())
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.0.len() < len {
+            return Err("truncated message".into());
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn field(&mut self) -> Result<(u32, Wire<'a>), String> {
+        let key = self.varint()?;
+        let wire = match key & 0x7 {
+            0 => Wire::Varint(self.varint()?),
+            1 => self.take(8).map(|_| Wire::Fixed)?,
+            2 => {
+                let len = self.varint()? as usize;
+                Wire::Bytes(self.take(len)?)
+            }
+            5 => self.take(4).map(|_| Wire::Fixed)?,
+            kind => return Err(format!("unsupported wire type {kind}")),
+        };
+        Ok(((key >> 3) as u32, wire))
+    }
+}
+
+impl<'a> Iterator for Message<'a> {
+    type Item = Result<(u32, Wire<'a>), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let field = self.field();
+        if field.is_err() {
+            self.0 = &[]; // INFO: the rest can't be read without knowing where this field ends
+        }
+        Some(field)
+    }
+}
+
+/// A `repeated int32`, which is either packed or one value per field.
+fn int32s(wire: Wire, values: &mut Vec<i32>) -> Result<(), String> {
+    match wire {
+        Wire::Varint(value) => values.push(value as i32),
+        Wire::Bytes(bytes) => {
+            let mut packed = Message(bytes);
+            while !packed.0.is_empty() {
+                values.push(packed.varint()? as i32);
+            }
+        }
+        Wire::Fixed => {}
+    }
+    Ok(())
+}
+
+fn string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Snapshot;
+    use super::*;
+    use std::path::Path;
+
+    const FOO: &str = "rust-analyzer cargo demo 0.1.0 foo().";
+
+    fn varint(mut value: u64, bytes: &mut Vec<u8>) {
+        while value >= 0x80 {
+            bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes.push(value as u8);
+    }
+
+    fn number(field: u64, value: u64) -> Vec<u8> {
+        let mut bytes = vec![];
+        varint(field << 3, &mut bytes);
+        varint(value, &mut bytes);
+        bytes
+    }
+
+    fn message(field: u64, value: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        varint(field << 3 | 2, &mut bytes);
+        varint(value.len() as u64, &mut bytes);
+        bytes.extend(value);
+        bytes
+    }
+
+    fn occurrence(range: &[u8], symbol: &str, definition: bool) -> Vec<u8> {
+        let mut bytes = message(1, range); // INFO: packed, as indexers write it
+        bytes.extend(message(2, symbol.as_bytes()));
+        bytes.extend(number(3, definition as u64));
+        message(2, &bytes)
+    }
+
+    fn index() -> Vec<u8> {
+        // INFO: columns are in UTF-8 bytes, `é` is 2 of them but a single UTF-16 unit
+        let mut a = message(1, b"src/a.rs");
+        a.extend(message(5, "fn foo() {}\nlet é = foo(é);\n".as_bytes()));
+        a.extend(number(6, 1));
+        a.extend(occurrence(&[0, 3, 6], FOO, true));
+        a.extend(occurrence(&[1, 4, 6], "local 0", true));
+        a.extend(occurrence(&[1, 9, 12], FOO, false));
+        a.extend(occurrence(&[1, 13, 15], "local 0", false));
+        let mut local = message(1, b"local 0");
+        local.extend(message(3, b"A local"));
+        a.extend(message(3, &local));
+
+        let mut b = message(1, b"src/b.rs");
+        b.extend(message(5, b"x\n"));
+        b.extend(occurrence(&[0, 0, 1], "local 0", true));
+
+        let mut foo = message(1, FOO.as_bytes());
+        foo.extend(message(3, b"Does nothing"));
+        let mut index = message(2, &a);
+        index.extend(message(2, &b));
+        index.extend(message(3, &foo));
+        index
+    }
+
+    fn range(line: u32, start: u32, end: u32) -> lsp::Range {
+        lsp::Range::new(
+            lsp::Position::new(line, start),
+            lsp::Position::new(line, end),
+        )
+    }
+
+    #[test]
+    fn local_and_global_symbols() {
+        let snapshot = Snapshot::new(Path::new("/ws"), parse(&index()).unwrap());
+        let (a, b) = (Path::new("/ws/src/a.rs"), Path::new("/ws/src/b.rs"));
+
+        let global = snapshot.symbol_at(a, lsp::Position::new(1, 9)).unwrap();
+        assert_eq!(global, FOO);
+        assert!(Snapshot::is_shared(global));
+        let local = snapshot.symbol_at(a, lsp::Position::new(1, 4)).unwrap();
+        assert_eq!(local, "local 0 src/a.rs");
+        assert!(!Snapshot::is_shared(local));
+        assert_eq!(
+            snapshot.symbol_at(b, lsp::Position::new(0, 0)),
+            Some("local 0 src/b.rs")
+        );
+
+        let references = snapshot.references(local, true);
+        let ranges: Vec<_> = references.iter().map(|location| location.range).collect();
+        assert_eq!(ranges, [range(1, 4, 5), range(1, 12, 13)]);
+        let hover = snapshot.hover(a, lsp::Position::new(1, 12)).unwrap();
+        assert!(
+            matches!(hover.contents, lsp::HoverContents::Markup(markup) if markup.value == "A local")
+        );
+
+        let symbols = snapshot.document_symbols(a).unwrap();
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].name, "foo");
+        assert_eq!(symbols[0].kind, lsp::SymbolKind::FUNCTION);
+    }
+
+    #[test]
+    fn utf8_to_utf16() {
+        let snapshot = Snapshot::new(Path::new("/ws"), parse(&index()).unwrap());
+        let a = Path::new("/ws/src/a.rs");
+        let Some(lsp::GotoDefinitionResponse::Array(locations)) =
+            snapshot.definition(a, lsp::Position::new(1, 8), false)
+        else {
+            panic!("no definition");
+        };
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].uri.path(), "/ws/src/a.rs");
+        assert_eq!(locations[0].range, range(0, 3, 6));
+        assert_eq!(
+            snapshot.references(FOO, false)[0].range,
+            range(1, 8, 11) // INFO: from bytes 9..12
+        );
+    }
+
+    #[test]
+    fn symbol_descriptors() {
+        let mut symbol = Symbol::default();
+        describe(
+            &mut symbol,
+            "scip-java maven com.example 1.0 com/example/Foo#bar().",
+        );
+        assert_eq!(symbol.name.as_deref(), Some("bar"));
+        assert_eq!(symbol.container.as_deref(), Some("Foo"));
+        assert_eq!(symbol.kind, Some(lsp::SymbolKind::METHOD));
+
+        // INFO: a double space escape one in the package, backticks quote a name
+        let mut symbol = Symbol::default();
+        describe(&mut symbol, "scip-ts npm my  pkg 1.0 `a b`/`c``d`.");
+        assert_eq!(symbol.name.as_deref(), Some("c`d"));
+        assert_eq!(symbol.container.as_deref(), Some("a b"));
+        assert_eq!(symbol.kind, Some(lsp::SymbolKind::VARIABLE));
+
+        assert!(descriptors("local 3").is_empty());
+    }
+
+    #[test]
+    fn truncated_message() {
+        assert_eq!(
+            parse(&[0x12, 0x05, 0x0a]).err().as_deref(),
+            Some("truncated message")
+        );
+        assert_eq!(
+            parse(&[0x12, 0x80]).err().as_deref(),
+            Some("truncated varint")
+        );
+    }
+}
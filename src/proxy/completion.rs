@@ -1,11 +1,26 @@
-use super::{Capabilities, PassThrough, Proxy};
+pub mod filter;
+pub mod shape;
+
+use super::{markdown, unwrap_data, wrap_data, Capabilities, PassThrough, Proxy};
 use crate::{mock, Content};
+use dashmap::DashMap;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types as lsp;
 
 pub struct Completion {
     pub proxy: PassThrough,
     pub trigger_characters: Option<Vec<String>>,
+    pub resolve: Option<PassThrough>, // fill `documentation`, `detail`, and `additionalTextEdits` per item
+    pub max_items: Option<usize>,     // cap the list then mark it as `isIncomplete`
+    pub max_documentation: Option<usize>, // in bytes, longer documentation is truncated
+    pub cache: DashMap<lsp::Url, Cached>,
+}
+
+/// Unfiltered items of the last completion, reused while typing the same word.
+pub struct Cached {
+    start: lsp::Position,
+    prefix: String,
+    items: Vec<lsp::CompletionItem>,
 }
 
 impl<'a, Proxies> Capabilities for Proxies
@@ -16,18 +31,25 @@ where
     type ClientCapabilities = lsp::CompletionClientCapabilities;
 
     fn resolve_provider(self, _: Option<Self::ClientCapabilities>) -> Option<Self::ServerOptions> {
-        if let (0, None | Some(0)) = self.size_hint() {
+        let completions: Vec<_> = self.collect();
+        if completions.is_empty() {
             None
         } else {
             Some(lsp::CompletionOptions {
                 trigger_characters: {
-                    let result: Vec<_> = self
-                        .map_while(|completion| completion.trigger_characters.as_ref())
+                    let result: Vec<_> = completions
+                        .iter()
+                        .filter_map(|completion| completion.trigger_characters.as_ref())
                         .flat_map(|chars| chars.iter().map(String::from))
                         .collect();
                     (!result.is_empty()).then_some(result)
                 },
 
+                resolve_provider: completions
+                    .iter()
+                    .any(|completion| completion.resolve.is_some())
+                    .then_some(true),
+
                 completion_item: Some(lsp::CompletionOptionsCompletionItem {
                     label_details_support: Some(true),
                 }),
@@ -46,7 +68,232 @@ impl Proxy for Completion {
         &self,
         params: Self::Params,
         content: &Content,
+        client: Option<&lsp::ClientCapabilities>,
     ) -> Result<Option<Self::Response>> {
-        mock::rescript::completion(&self.proxy, params, content).await
+        let lsp::TextDocumentPositionParams {
+            text_document,
+            position,
+        } = &params.text_document_position;
+        let (uri, position) = (text_document.uri.clone(), *position);
+        let prefix = filter::prefix(&content.text, position);
+        let start = lsp::Position {
+            character: position.character - prefix.encode_utf16().count() as u32,
+            ..position
+        };
+
+        let retrigger = params.context.as_ref().is_some_and(|context| {
+            context.trigger_kind == lsp::CompletionTriggerKind::TRIGGER_CHARACTER
+        });
+        let cached = self
+            .cache
+            .get(&uri)
+            .filter(|cached| !retrigger && cached.start == start)
+            .filter(|cached| prefix.starts_with(&cached.prefix))
+            .map(|cached| cached.items.clone());
+
+        let (items, is_incomplete) = match cached {
+            Some(items) => (items, false),
+            None => {
+                let (items, is_incomplete) =
+                    match mock::rescript::completion(&self.proxy, params, content).await? {
+                        Some(lsp::CompletionResponse::Array(items)) => (items, false),
+                        Some(lsp::CompletionResponse::List(list)) => {
+                            (list.items, list.is_incomplete)
+                        }
+                        None => return Ok(None),
+                    };
+
+                // INFO: attach the origin document so `completionItem/resolve` can find this proxy again
+                let items: Vec<_> = match self.resolve {
+                    Some(_) => items
+                        .into_iter()
+                        .map(|item| lsp::CompletionItem {
+                            data: wrap_data(&uri, item.data),
+                            ..item
+                        })
+                        .collect(),
+                    None => items,
+                };
+
+                if is_incomplete {
+                    self.cache.remove(&uri);
+                } else {
+                    let items = items.clone();
+                    self.cache.insert(
+                        uri,
+                        Cached {
+                            start,
+                            prefix: prefix.clone(),
+                            items,
+                        },
+                    );
+                }
+                (items, is_incomplete)
+            }
+        };
+
+        let (items, truncated) = filter::rank(items, &prefix, self.max_items);
+        let render = self.render(content, client);
+        let items = (items.into_iter())
+            .map(|item| lsp::CompletionItem {
+                documentation: (item.documentation).map(|it| render.documentation(it)),
+                ..item
+            })
+            .map(|item| match capability(client) {
+                Some(client) => shape::item(item, client),
+                None => item,
+            })
+            .collect();
+        Ok(Some(lsp::CompletionResponse::List(lsp::CompletionList {
+            is_incomplete: is_incomplete || truncated,
+            items,
+        })))
+    }
+}
+
+impl Completion {
+    /// Run the secondary (usually more expensive) command for a single completion `item`.
+    pub async fn resolve(
+        &self,
+        item: lsp::CompletionItem,
+        content: &Content,
+        client: Option<&lsp::ClientCapabilities>,
+    ) -> Result<lsp::CompletionItem> {
+        let (_, data) = unwrap_data(item.data);
+        let item = lsp::CompletionItem { data, ..item };
+        let Some(resolve) = &self.resolve else {
+            return Ok(item);
+        };
+
+        let json = serde_json::to_string(&item).unwrap_or_default();
+        let resolved: Option<lsp::CompletionItem> = resolve
+            .exec(content, &[("label", &item.label), ("item", &json)])
+            .await?;
+        let render = self.render(content, client);
+        let item = match resolved {
+            Some(resolved) => lsp::CompletionItem {
+                // INFO: the documentation of the `item` was already rendered in the completion list
+                documentation: (resolved.documentation)
+                    .map(|it| render.documentation(it))
+                    .or(item.documentation),
+                detail: resolved.detail.or(item.detail),
+                additional_text_edits: resolved
+                    .additional_text_edits
+                    .or(item.additional_text_edits),
+                ..item
+            },
+            None => item,
+        };
+        Ok(match capability(client) {
+            Some(client) => shape::item(item, client),
+            None => item,
+        })
+    }
+
+    /// How the documentation printed by the tool is normalized, according to what the client can show.
+    fn render<'a>(
+        &self,
+        content: &'a Content,
+        client: Option<&lsp::ClientCapabilities>,
+    ) -> markdown::Render<'a> {
+        markdown::Render {
+            language_id: &content.language_id,
+            max_length: self.max_documentation,
+            markdown: markdown::supported(capability(client), |item| {
+                item.documentation_format.as_ref()
+            }),
+        }
+    }
+}
+
+fn capability(client: Option<&lsp::ClientCapabilities>) -> Option<&lsp::CompletionItemCapability> {
+    let text_document = client?.text_document.as_ref()?;
+    text_document.completion.as_ref()?.completion_item.as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::{Exec, InFlight, Parser};
+
+    /// A completion which prints the same items each time, counting its runs in `runs`.
+    fn completion(runs: &std::path::Path) -> Completion {
+        let script =
+            r#"echo >> "$1"; echo '[{"label": "foo"}, {"label": "format"}, {"label": "bar"}]'"#;
+        let exec = Exec {
+            in_flight: InFlight::new(true),
+            ..Exec::test(
+                "sh",
+                &["-c", script, "sh", &runs.to_string_lossy()],
+                Parser::Json,
+            )
+        };
+        Completion {
+            proxy: PassThrough::ExecCommand(exec),
+            trigger_characters: Some(vec![".".into()]),
+            resolve: None,
+            max_items: None,
+            max_documentation: None,
+            cache: DashMap::new(),
+        }
+    }
+
+    /// The labels completed at the end of `text`, the document at the `version` of a keystroke.
+    fn complete(
+        completion: &Completion,
+        (version, text): (i32, &str),
+        trigger: Option<&str>,
+    ) -> Vec<String> {
+        let content = Content {
+            version,
+            ..Content::test("rescript", "/mirror/main.res", text)
+        };
+        let params = lsp::CompletionParams {
+            text_document_position: lsp::TextDocumentPositionParams {
+                text_document: lsp::TextDocumentIdentifier {
+                    uri: "file:///main.res".parse().unwrap(),
+                },
+                position: lsp::Position::new(0, text.encode_utf16().count() as u32),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: trigger.map(|trigger| lsp::CompletionContext {
+                trigger_kind: lsp::CompletionTriggerKind::TRIGGER_CHARACTER,
+                trigger_character: Some(trigger.into()),
+            }),
+        };
+        let response = smol::block_on(completion.proxy_response(params, &content, None));
+        let Ok(Some(lsp::CompletionResponse::List(list))) = response else {
+            panic!("expected a completion list");
+        };
+        list.items.into_iter().map(|item| item.label).collect()
+    }
+
+    #[test]
+    fn refilter_while_typing() {
+        let runs = std::env::temp_dir().join(format!("lspcat-runs-{}", std::process::id()));
+        let _ = std::fs::remove_file(&runs);
+        let count = || {
+            std::fs::read_to_string(&runs)
+                .unwrap_or_default()
+                .lines()
+                .count()
+        };
+        let completion = completion(&runs);
+
+        assert_eq!(complete(&completion, (1, "x.f"), None), ["foo", "format"]);
+        assert_eq!(complete(&completion, (2, "x.fo"), None), ["foo", "format"]);
+        assert_eq!(complete(&completion, (3, "x.for"), None), ["format"]);
+        assert_eq!(count(), 1);
+
+        // INFO: a word which doesn't extend the cached one, or a trigger character, run the tool again
+        assert_eq!(complete(&completion, (4, "x.b"), None), ["bar"]);
+        assert_eq!(count(), 2);
+        assert_eq!(
+            complete(&completion, (5, "x.b."), Some(".")),
+            ["bar", "foo", "format"]
+        );
+        assert_eq!(count(), 3);
+        let _ = std::fs::remove_file(&runs);
     }
 }
@@ -0,0 +1,178 @@
+use tower_lsp::lsp_types as lsp;
+
+/// Get the word right before the cursor `position` from the document content.
+pub fn prefix(text: &str, position: lsp::Position) -> String {
+    let Some(line) = text.lines().nth(position.line as usize) else {
+        return String::new();
+    };
+
+    let mut offset = 0; // INFO: `position.character` is counted in UTF-16 code units
+    let before: Vec<_> = line
+        .chars()
+        .take_while(|char| {
+            offset += char.len_utf16();
+            offset <= position.character as usize
+        })
+        .collect();
+    let word: Vec<_> = before
+        .into_iter()
+        .rev()
+        .take_while(|char| char.is_alphanumeric() || *char == '_')
+        .collect();
+    word.into_iter().rev().collect()
+}
+
+/// Fuzzy-filter `items` by `prefix` then sort them by score,
+/// returning `true` when the list is truncated to `max_items`.
+pub fn rank(
+    items: Vec<lsp::CompletionItem>,
+    prefix: &str,
+    max_items: Option<usize>,
+) -> (Vec<lsp::CompletionItem>, bool) {
+    let mut scored: Vec<_> = items
+        .into_iter()
+        .filter_map(|item| {
+            let text = item.filter_text.as_ref().unwrap_or(&item.label);
+            score(text, prefix).map(|score| (score, item))
+        })
+        .collect();
+    scored.sort_by(|(a, x), (b, y)| {
+        let sort_text =
+            |item: &lsp::CompletionItem| item.sort_text.clone().unwrap_or(item.label.clone());
+        b.cmp(a).then_with(|| sort_text(x).cmp(&sort_text(y)))
+    });
+
+    let truncated = max_items.is_some_and(|max| scored.len() > max);
+    scored.truncate(max_items.unwrap_or(usize::MAX));
+    let width = scored.len().to_string().len();
+    let items = scored
+        .into_iter()
+        .enumerate()
+        .map(|(rank, (_, item))| lsp::CompletionItem {
+            sort_text: Some(format!("{rank:0width$}")), // INFO: keep our ranking when the client sort the items
+            ..item
+        })
+        .collect();
+    (items, truncated)
+}
+
+/// Score how well `candidate` match `pattern` as a (case-insensitive) subsequence.
+/// Return `None` when it doesn't match at all.
+fn score(candidate: &str, pattern: &str) -> Option<i64> {
+    let mut pattern = pattern.chars().peekable();
+    let (mut score, mut last_match, mut prev) = (0, None, None::<char>);
+    for (i, char) in candidate.chars().enumerate() {
+        let Some(&expected) = pattern.peek() else {
+            break;
+        };
+        if char.to_lowercase().eq(expected.to_lowercase()) {
+            score += if char == expected { 2 } else { 1 };
+            score += match prev {
+                None => 8,                                                     // start of candidate
+                Some(_) if last_match == Some(i - 1) => 4,                     // consecutive
+                Some(prev) if !prev.is_alphanumeric() => 6, // word boundary, e.g. snake_case
+                Some(prev) if prev.is_lowercase() && char.is_uppercase() => 6, // camelCase
+                _ => 0,
+            };
+            last_match = Some(i);
+            pattern.next();
+        }
+        prev = Some(char);
+    }
+    // INFO: prefer shorter candidate when the score is similar
+    pattern
+        .peek()
+        .is_none()
+        .then(|| score - candidate.chars().count() as i64 / 8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(label: &str) -> lsp::CompletionItem {
+        lsp::CompletionItem {
+            label: label.into(),
+            ..Default::default()
+        }
+    }
+
+    fn labels(items: &[lsp::CompletionItem]) -> Vec<&str> {
+        items.iter().map(|item| item.label.as_str()).collect()
+    }
+
+    #[test]
+    fn prefix_before_the_cursor() {
+        let text = "let foo_bar = 1;\n  x.élan🦀ba\n";
+        let cases = [
+            ((0, 11), "foo_bar"),
+            ((0, 7), "foo"),
+            ((0, 3), "let"),
+            ((0, 4), ""),
+            // INFO: `é` is one UTF-16 unit, the crab two
+            ((1, 6), "él"),
+            ((1, 10), ""),
+            ((1, 12), "ba"),
+            ((1, 9), "élan"), // INFO: inside the surrogate pair, the crab isn't counted
+            ((5, 0), ""),
+        ];
+        for ((line, character), prefix) in cases {
+            let position = lsp::Position::new(line, character);
+            assert_eq!(super::prefix(text, position), prefix, "{line}:{character}");
+        }
+    }
+
+    #[test]
+    fn score_matches() {
+        assert_eq!(score("foo", "bar"), None);
+        assert_eq!(score("fo", "foo"), None);
+        assert!(score("anything", "").is_some());
+        // INFO: the start, consecutive matches and the exact case score higher
+        assert!(score("foo", "fo") > score("afoo", "fo"));
+        assert!(score("foo", "fo") > score("fxo", "fo"));
+        assert!(score("Foo", "Fo") > score("foo", "Fo"));
+        // INFO: word boundaries score higher than the middle of a word
+        assert!(score("get_value", "gv") > score("gravy", "gv"));
+        assert!(score("getValue", "gV") > score("gravy", "gV"));
+        assert!(score("map", "m") > score("map_with_a_long_name", "m"));
+    }
+
+    #[test]
+    fn rank_items() {
+        let items = ["format", "from_str", "afoo", "bar", "Foo"]
+            .map(item)
+            .to_vec();
+        let (ranked, truncated) = rank(items.clone(), "fo", None);
+        assert_eq!(labels(&ranked), ["format", "Foo", "from_str", "afoo"]);
+        assert_eq!(ranked[0].sort_text.as_deref(), Some("0"));
+        assert!(!truncated);
+
+        let (ranked, truncated) = rank(items.clone(), "fo", Some(2));
+        assert_eq!(labels(&ranked), ["format", "Foo"]);
+        assert!(truncated);
+
+        // INFO: `filterText` is matched instead of the label, and `sortText` break the ties
+        let items = vec![
+            lsp::CompletionItem {
+                filter_text: Some("zzz".into()),
+                ..item("fo")
+            },
+            lsp::CompletionItem {
+                sort_text: Some("b".into()),
+                ..item("x")
+            },
+            lsp::CompletionItem {
+                sort_text: Some("a".into()),
+                ..item("y")
+            },
+        ];
+        let (ranked, _) = rank(items.clone(), "", None);
+        assert_eq!(labels(&ranked), ["y", "x", "fo"]);
+        let (ranked, _) = rank(items, "fo", None);
+        assert!(ranked.is_empty());
+
+        let many: Vec<_> = (0..12).map(|i| item(&format!("item{i}"))).collect();
+        let (ranked, _) = rank(many, "item", None);
+        assert_eq!(ranked[0].sort_text.as_deref(), Some("00"));
+    }
+}
@@ -0,0 +1,166 @@
+use tower_lsp::lsp_types as lsp;
+
+/// Strip every feature from a completion `item` which the client doesn't support.
+pub fn item(
+    item: lsp::CompletionItem,
+    client: &lsp::CompletionItemCapability,
+) -> lsp::CompletionItem {
+    let mut item = item;
+
+    if item.insert_text_format == Some(lsp::InsertTextFormat::SNIPPET)
+        && !client.snippet_support.unwrap_or_default()
+    {
+        item.insert_text_format = Some(lsp::InsertTextFormat::PLAIN_TEXT);
+        item.insert_text = item.insert_text.as_deref().map(plain_snippet);
+        match &mut item.text_edit {
+            Some(lsp::CompletionTextEdit::Edit(edit)) => {
+                edit.new_text = plain_snippet(&edit.new_text)
+            }
+            Some(lsp::CompletionTextEdit::InsertAndReplace(edit)) => {
+                edit.new_text = plain_snippet(&edit.new_text)
+            }
+            None => {}
+        }
+    }
+
+    if !client.insert_replace_support.unwrap_or_default() {
+        if let Some(lsp::CompletionTextEdit::InsertAndReplace(edit)) = item.text_edit {
+            item.text_edit = Some(lsp::CompletionTextEdit::Edit(lsp::TextEdit {
+                range: edit.insert,
+                new_text: edit.new_text,
+            }));
+        }
+    }
+
+    let markdown = client
+        .documentation_format
+        .as_ref()
+        .is_some_and(|formats| formats.contains(&lsp::MarkupKind::Markdown));
+    if let Some(lsp::Documentation::MarkupContent(doc)) = &mut item.documentation {
+        if doc.kind == lsp::MarkupKind::Markdown && !markdown {
+            doc.kind = lsp::MarkupKind::PlainText;
+            doc.value = plain_markdown(&doc.value);
+        }
+    }
+
+    if !client.label_details_support.unwrap_or_default() {
+        item.label_details = None;
+    }
+
+    item.tags = match (item.tags, &client.tag_support) {
+        (Some(tags), Some(support)) => Some(tags)
+            .map(|tags| {
+                tags.into_iter()
+                    .filter(|tag| support.value_set.contains(tag))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|tags| !tags.is_empty()),
+        _ => None,
+    };
+
+    item
+}
+
+/// Turn a snippet into plain text by keeping only the text of its placeholders.
+///
+/// # References
+/// - [Snippet Syntax](https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#snippet_syntax)
+pub fn plain_snippet(snippet: &str) -> String {
+    let mut plain = String::with_capacity(snippet.len());
+    let mut chars = snippet.chars().peekable();
+    let mut depth = 0; // nested `${1:placeholder}`
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => plain.extend(chars.next()),
+            '$' => match chars.peek() {
+                Some('{') => {
+                    chars.next();
+                    while chars
+                        .next_if(|c| c.is_alphanumeric() || *c == '_')
+                        .is_some()
+                    {}
+                    match chars.next() {
+                        Some(':') => depth += 1,
+                        Some('|') => {
+                            // INFO: keep only the first choice of `${1|one,two|}`
+                            let mut first = true;
+                            while let Some(char) = chars.next() {
+                                match char {
+                                    '|' => {
+                                        chars.next_if_eq(&'}');
+                                        break;
+                                    }
+                                    ',' => first = false,
+                                    '\\' if first => plain.extend(chars.next()),
+                                    char if first => plain.push(char),
+                                    _ => {}
+                                }
+                            }
+                        }
+                        _ => {} // `${1}` or `${VAR}`
+                    }
+                }
+                Some(c) if c.is_alphanumeric() || *c == '_' => {
+                    while chars
+                        .next_if(|c| c.is_alphanumeric() || *c == '_')
+                        .is_some()
+                    {}
+                }
+                _ => plain.push(char),
+            },
+            '}' if depth > 0 => depth -= 1,
+            char => plain.push(char),
+        }
+    }
+    plain
+}
+
+/// Turn Markdown into plain text by removing the most common markup.
+pub fn plain_markdown(markdown: &str) -> String {
+    markdown
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .map(|line| {
+            let heading = line.trim_start_matches('#');
+            let line = match heading.strip_prefix(' ') {
+                Some(heading) if heading.len() < line.len() - 1 => heading,
+                _ => line,
+            };
+            line.replace("**", "").replace("__", "").replace('`', "")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippet_into_plain_text() {
+        let cases = [
+            ("foo($1)$0", "foo()"),
+            ("${1:foo}(${2:bar(${3:x})})", "foo(bar(x))"),
+            ("${1|one,two|} ${2|a\\,b,c|}", "one a,b"),
+            ("$TM_FILENAME ${VAR} ${1}", "  "),
+            // escapes, which are only needed for `$`, `}` and `\`
+            ("\\$1 \\\\ ${1:a\\}b} \\}", "$1 \\ a}b }"),
+            ("cost $ 5 $", "cost $ 5 $"),
+            ("map{}", "map{}"),
+        ];
+        for (snippet, plain) in cases {
+            assert_eq!(plain_snippet(snippet), plain, "{snippet:?}");
+        }
+    }
+
+    #[test]
+    fn markdown_into_plain_text() {
+        let markdown =
+            "# Title\n#hashtag\n```rust\nlet **a** = `b`;\n```\n**bold** `code` __under__";
+        assert_eq!(
+            plain_markdown(markdown),
+            "Title\n#hashtag\nlet a = b;\nbold code under"
+        );
+        assert_eq!(plain_markdown("## \n#"), "\n#"); // INFO: an empty heading, not a hashtag
+    }
+}
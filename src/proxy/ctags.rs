@@ -0,0 +1,695 @@
+use super::{
+    completion::filter,
+    parse::{completion_kind, symbol_kind},
+    process::{self, Capture},
+};
+use dashmap::DashMap;
+use serde_json::Value;
+use smol::{channel, future, lock::OnceCell};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tower_lsp::lsp_types as lsp;
+
+const MAX_ITEMS: usize = 256;
+const BATCH: usize = 1024; // lines of ctags output parsed together
+
+/// Definitions, symbols and identifier completion from Universal Ctags,
+/// a baseline for languages without a real analyzer.
+pub struct Ctags {
+    pub program: String, // run as `ctags --output-format=json --fields=+nKS -f - {args} {target}`
+    pub args: Vec<String>, // e.g. `--languages=Lua`
+    pub tags_file: Option<String>, // read `{workspace}/{tags_file}` (classic or JSON) instead of indexing it, e.g. "tags"
+    pub timeout: Option<Duration>,
+    pub max_output: Option<usize>, // in bytes, a whole workspace may have a lot of tags
+    pub index: Index,
+}
+
+/// Tags of every indexed file, a workspace is indexed on the first request which need it
+/// and a file again whenever it is saved.
+#[derive(Default)]
+pub struct Index {
+    files: DashMap<PathBuf, Vec<Tag>>,
+    workspaces: DashMap<PathBuf, Arc<OnceCell<()>>>,
+}
+
+#[derive(Clone)]
+struct Tag {
+    name: String,
+    path: PathBuf,
+    line: u32,   // 0-based
+    column: u32, // of the name in the line, counted in UTF-16 code units
+    kind: Option<String>,
+    scope: Option<String>,
+    signature: Option<String>,
+}
+
+impl Ctags {
+    /// Index every workspace which isn't yet, a failed one is tried again on the next call.
+    /// The symbol of each new tag is sent to an unbounded `symbols` channel as soon as ctags print it.
+    pub async fn index_workspaces(
+        &self,
+        roots: impl IntoIterator<Item = PathBuf>,
+        symbols: Option<channel::Sender<lsp::SymbolInformation>>,
+    ) -> Result<(), String> {
+        for root in roots {
+            let indexed = self
+                .index
+                .workspaces
+                .entry(root.clone())
+                .or_default()
+                .clone();
+            indexed
+                .get_or_try_init(|| self.index_workspace(&root, symbols.as_ref()))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Index a `file` again, e.g. after it was saved.
+    pub async fn index_file(&self, file: &Path) -> Result<(), String> {
+        let tags = self.run(file, None).await?;
+        self.index.files.insert(file.to_path_buf(), tags);
+        Ok(())
+    }
+
+    /// Index a `file` outside of any workspace, unless it already is.
+    pub async fn ensure_file(&self, file: &Path) -> Result<(), String> {
+        match self.index.files.contains_key(file) {
+            true => Ok(()),
+            false => self.index_file(file).await,
+        }
+    }
+
+    async fn index_workspace(
+        &self,
+        root: &Path,
+        symbols: Option<&channel::Sender<lsp::SymbolInformation>>,
+    ) -> Result<(), String> {
+        let tags = match &self.tags_file {
+            Some(tags_file) => {
+                let path = root.join(tags_file);
+                let text = (smol::fs::read_to_string(&path).await)
+                    .map_err(|err| format!("{}: {err}", path.display()))?;
+                // INFO: paths in a tags file are relative to its directory
+                let base = path.parent().unwrap_or(root).to_path_buf();
+                smol::unblock(move || parse(&text, &base)).await
+            }
+            None => self.run(root, symbols).await?,
+        };
+
+        let mut files: HashMap<_, Vec<_>> = HashMap::new();
+        for tag in tags {
+            files.entry(tag.path.clone()).or_default().push(tag);
+        }
+        for (path, tags) in files {
+            // INFO: a file saved while its workspace was being indexed is already up to date
+            self.index.files.entry(path).or_insert(tags);
+        }
+        Ok(())
+    }
+
+    /// Run ctags on a file or a directory, its tags are parsed by batch while it is still running.
+    async fn run(
+        &self,
+        target: &Path,
+        symbols: Option<&channel::Sender<lsp::SymbolInformation>>,
+    ) -> Result<Vec<Tag>, String> {
+        let args = ["--output-format=json", "--fields=+nKS", "-f", "-"].map(String::from);
+        let recursive = target.is_dir().then(|| "-R".to_owned());
+        let args = (args.into_iter())
+            .chain(self.args.iter().cloned())
+            .chain(recursive)
+            .chain([target.to_string_lossy().into_owned()]);
+        let (lines, printed) = channel::unbounded();
+        let capture = Capture {
+            max_output: self.max_output,
+            lines: Some(lines),
+        };
+        let command = process::command(&self.program, args);
+        let parse = async {
+            let mut tags = vec![];
+            while let Ok(line) = printed.recv().await {
+                let batch: Vec<_> = std::iter::once(line)
+                    .chain(std::iter::from_fn(|| printed.try_recv().ok()).take(BATCH))
+                    .collect();
+                let batch = smol::unblock(move || parse(&batch.join("\n"), Path::new(""))).await;
+                if let Some(symbols) = symbols {
+                    for symbol in batch.iter().filter_map(Tag::symbol) {
+                        let _ = symbols.try_send(symbol); // INFO: only fail once nobody listen
+                    }
+                }
+                tags.extend(batch);
+            }
+            tags
+        };
+        let (output, tags) =
+            future::zip(process::run(command, None, self.timeout, capture), parse).await;
+        let output = output.map_err(|failure| format!("{}: {failure}", self.program))?;
+        if !output.status.success() && output.stdout.is_empty() {
+            return Err(String::from_utf8_lossy(&output.stderr).into_owned());
+        }
+        Ok(tags)
+    }
+
+    /// Tags named like the word under the cursor, those of the `document` first.
+    pub fn definition(
+        &self,
+        document: &Path,
+        text: &str,
+        position: lsp::Position,
+        link_support: bool,
+    ) -> Option<lsp::GotoDefinitionResponse> {
+        let (word, origin) = word_at(text, position)?;
+        let mut tags: Vec<_> = (self.index.files.iter())
+            .flat_map(|file| {
+                file.value()
+                    .iter()
+                    .filter(|tag| tag.name == word)
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        if tags.is_empty() {
+            return None;
+        }
+        tags.sort_by_key(|tag| (tag.path != document, tag.path.clone(), tag.line));
+
+        Some(match link_support {
+            true => lsp::GotoDefinitionResponse::Link(
+                (tags.iter())
+                    .filter_map(|tag| {
+                        Some(lsp::LocationLink {
+                            origin_selection_range: Some(origin),
+                            target_uri: lsp::Url::from_file_path(&tag.path).ok()?,
+                            target_range: tag.line_range(),
+                            target_selection_range: tag.name_range(),
+                        })
+                    })
+                    .collect(),
+            ),
+            false => {
+                lsp::GotoDefinitionResponse::Array(tags.iter().filter_map(Tag::location).collect())
+            }
+        })
+    }
+
+    pub fn document_symbols(&self, document: &Path) -> Vec<lsp::SymbolInformation> {
+        (self.index.files.get(document).iter())
+            .flat_map(|tags| tags.iter().filter_map(Tag::symbol))
+            .collect()
+    }
+
+    /// Whether a `name` contain the characters of `query` in order, ignoring case.
+    pub fn matches(query: &str, name: &str) -> bool {
+        let mut name = name.chars().flat_map(char::to_lowercase);
+        (query.chars().flat_map(char::to_lowercase))
+            .all(|char| name.any(|candidate| candidate == char))
+    }
+
+    /// Symbols whose name [`matches`](Self::matches) the `query`.
+    pub fn workspace_symbols(&self, query: &str) -> Vec<lsp::SymbolInformation> {
+        (self.index.files.iter())
+            .flat_map(|file| {
+                (file.value().iter())
+                    .filter(|tag| Self::matches(query, &tag.name))
+                    .filter_map(Tag::symbol)
+                    .collect::<Vec<_>>()
+            })
+            .take(MAX_ITEMS)
+            .collect()
+    }
+
+    /// Every tag name which match the word before the cursor.
+    pub fn completion(&self, text: &str, position: lsp::Position) -> lsp::CompletionResponse {
+        let prefix = filter::prefix(text, position);
+        let first = prefix
+            .chars()
+            .next()
+            .map(|char| char.to_lowercase().to_string());
+        let mut items: HashMap<&str, lsp::CompletionItem> = HashMap::new();
+        let files: Vec<_> = self.index.files.iter().collect();
+        for tag in files.iter().flat_map(|file| file.value()) {
+            // INFO: like most editors, the first character must match so that ranking stay cheap
+            if first
+                .as_ref()
+                .is_some_and(|first| !tag.name.to_lowercase().starts_with(first))
+            {
+                continue;
+            }
+            items
+                .entry(&tag.name)
+                .or_insert_with(|| lsp::CompletionItem {
+                    label: tag.name.clone(),
+                    kind: tag
+                        .kind
+                        .as_deref()
+                        .and_then(|kind| completion_kind(kind_name(kind))),
+                    detail: tag.signature.clone().or_else(|| tag.scope.clone()),
+                    ..lsp::CompletionItem::default()
+                });
+        }
+        let (items, truncated) =
+            filter::rank(items.into_values().collect(), &prefix, Some(MAX_ITEMS));
+        lsp::CompletionResponse::List(lsp::CompletionList {
+            is_incomplete: truncated,
+            items,
+        })
+    }
+}
+
+impl Tag {
+    fn line_range(&self) -> lsp::Range {
+        lsp::Range::new(
+            lsp::Position::new(self.line, 0),
+            lsp::Position::new(self.line + 1, 0),
+        )
+    }
+
+    fn name_range(&self) -> lsp::Range {
+        let start = lsp::Position::new(self.line, self.column);
+        let end = lsp::Position::new(
+            self.line,
+            self.column + self.name.encode_utf16().count() as u32,
+        );
+        lsp::Range::new(start, end)
+    }
+
+    fn location(&self) -> Option<lsp::Location> {
+        Some(lsp::Location {
+            uri: lsp::Url::from_file_path(&self.path).ok()?,
+            range: self.name_range(),
+        })
+    }
+
+    fn symbol(&self) -> Option<lsp::SymbolInformation> {
+        #[allow(deprecated)] // INFO: `deprecated` is replaced by `tags`
+        Some(lsp::SymbolInformation {
+            name: self.name.clone(),
+            kind: (self.kind.as_deref())
+                .and_then(|kind| symbol_kind(kind_name(kind)))
+                .unwrap_or(lsp::SymbolKind::VARIABLE),
+            tags: None,
+            deprecated: None,
+            location: self.location()?,
+            container_name: self.scope.clone(),
+        })
+    }
+}
+
+/// Parse the tags printed by `--output-format=json` or written in a classic tags file,
+/// relative paths are resolved against `base`.
+fn parse(text: &str, base: &Path) -> Vec<Tag> {
+    let mut lines: HashMap<PathBuf, Option<Vec<String>>> = HashMap::new(); // INFO: files read to locate a pattern
+    (text.lines())
+        .filter_map(|line| match line.starts_with('{') {
+            true => json(line, base),
+            false => classic(line, base),
+        })
+        .filter_map(|(mut tag, pattern, line)| {
+            let pattern = pattern.as_deref().map(unescape);
+            tag.line = match line {
+                Some(line) => line.saturating_sub(1),
+                None => {
+                    let (text, anchored) = pattern.as_ref()?;
+                    let lines = (lines.entry(tag.path.clone()))
+                        .or_insert_with(|| {
+                            Some(
+                                std::fs::read_to_string(&tag.path)
+                                    .ok()?
+                                    .lines()
+                                    .map(String::from)
+                                    .collect(),
+                            )
+                        })
+                        .as_ref()?;
+                    let found = (lines.iter()).position(|line| match anchored {
+                        true => line == text,
+                        false => line.starts_with(text.as_str()),
+                    })?;
+                    found as u32
+                }
+            };
+            if let Some((text, _)) = &pattern {
+                // INFO: the name as a whole word, not in e.g. the keyword before it
+                let is_word = |char: Option<char>| {
+                    char.is_some_and(|char| char.is_alphanumeric() || char == '_')
+                };
+                let index = (text.match_indices(&tag.name))
+                    .map(|(index, _)| index)
+                    .find(|&index| {
+                        !is_word(text[..index].chars().next_back())
+                            && !is_word(text[index + tag.name.len()..].chars().next())
+                    })
+                    .or_else(|| text.find(&tag.name));
+                if let Some(index) = index {
+                    tag.column = text[..index].encode_utf16().count() as u32;
+                }
+            }
+            Some(tag)
+        })
+        .collect()
+}
+
+/// A tag, its search pattern and its 1-based line from a line of `--output-format=json`.
+fn json(line: &str, base: &Path) -> Option<(Tag, Option<String>, Option<u32>)> {
+    let tag: Value = serde_json::from_str(line).ok()?;
+    if tag["_type"] != "tag" {
+        return None;
+    }
+    let text = |key: &str| tag[key].as_str().map(String::from);
+    Some((
+        Tag {
+            name: text("name")?,
+            path: resolve(base, tag["path"].as_str()?),
+            line: 0,
+            column: 0,
+            kind: text("kind"),
+            scope: text("scope"),
+            signature: text("signature"),
+        },
+        text("pattern"),
+        tag["line"].as_u64().map(|line| line as u32),
+    ))
+}
+
+/// A tag from a line `{name}\t{file}\t{address};"\t{kind}\t{key}:{value}…` of a classic tags file.
+fn classic(line: &str, base: &Path) -> Option<(Tag, Option<String>, Option<u32>)> {
+    if line.starts_with("!_") {
+        return None; // INFO: pseudo tags
+    }
+    let (name, rest) = line.split_once('\t')?;
+    let (path, rest) = rest.split_once('\t')?;
+    // INFO: the address is a line number or a pattern, which may contain `;"`
+    let (address, fields) = match rest.chars().next()? {
+        delimiter @ ('/' | '?') => {
+            let end = (rest.char_indices().skip(1))
+                .scan(false, |escaped, (index, char)| {
+                    let end = !*escaped && char == delimiter;
+                    *escaped = !*escaped && char == '\\';
+                    Some((index, end))
+                })
+                .find_map(|(index, end)| end.then_some(index))?;
+            (&rest[..=end], &rest[end + 1..])
+        }
+        _ => rest
+            .split_once(";\"")
+            .map_or((rest, ""), |(address, fields)| (address, fields)),
+    };
+    let fields = fields.trim_start_matches(";\"");
+
+    let mut tag = Tag {
+        name: name.into(),
+        path: resolve(base, path),
+        line: 0,
+        column: 0,
+        kind: None,
+        scope: None,
+        signature: None,
+    };
+    let mut line = address.trim().parse().ok();
+    for field in fields.split('\t').filter(|field| !field.is_empty()) {
+        match field.split_once(':') {
+            None => tag.kind = Some(field.into()),
+            Some(("kind", kind)) => tag.kind = Some(kind.into()),
+            Some(("line", number)) => line = number.parse().ok().or(line),
+            Some(("signature", signature)) => tag.signature = Some(signature.into()),
+            // INFO: `scope:class:Foo` with `--fields=+Z`, otherwise `class:Foo`
+            Some(("scope", scope)) => {
+                tag.scope = scope.split_once(':').map(|(_, scope)| scope.into())
+            }
+            Some((
+                "class" | "struct" | "union" | "enum" | "namespace" | "module" | "interface"
+                | "function" | "trait" | "package",
+                scope,
+            )) => tag.scope = Some(scope.into()),
+            Some(_) => {}
+        }
+    }
+    let pattern = matches!(address.chars().next(), Some('/' | '?')).then(|| address.into());
+    Some((tag, pattern, line))
+}
+
+fn resolve(base: &Path, path: &str) -> PathBuf {
+    let path = Path::new(path);
+    base.join(path.strip_prefix(".").unwrap_or(path))
+}
+
+/// The text of a `/^line$/` search pattern and whether it is the whole line.
+fn unescape(pattern: &str) -> (String, bool) {
+    let pattern = pattern
+        .get(1..pattern.len().saturating_sub(1))
+        .unwrap_or_default();
+    let pattern = pattern.strip_prefix('^').unwrap_or(pattern);
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) if !pattern.ends_with('\\') => (pattern, true),
+        _ => (pattern, false),
+    };
+    let mut text = String::new();
+    let mut chars = pattern.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => text.extend(chars.next()),
+            char => text.push(char),
+        }
+    }
+    (text, anchored)
+}
+
+/// Long name of a single letter kind of the classic format, which depend on the language.
+fn kind_name(kind: &str) -> &str {
+    match kind {
+        "f" | "p" => "function",
+        "c" => "class",
+        "m" => "member",
+        "v" | "l" => "variable",
+        "s" => "struct",
+        "u" => "union",
+        "g" => "enum",
+        "e" => "enumerator",
+        "t" => "typedef",
+        "d" => "macro",
+        "n" => "namespace",
+        "i" => "interface",
+        "M" => "module",
+        "prototype" => "function",
+        kind => kind,
+    }
+}
+
+/// The identifier under the cursor (or right before it) and its range.
+fn word_at(text: &str, position: lsp::Position) -> Option<(String, lsp::Range)> {
+    let line = text.lines().nth(position.line as usize)?;
+    let is_word = |char: char| char.is_alphanumeric() || char == '_';
+    let mut offset = 0; // INFO: `position.character` is counted in UTF-16 code units
+    let chars: Vec<_> = (line.char_indices())
+        .map(|(index, char)| {
+            offset += char.len_utf16();
+            (index, offset - char.len_utf16(), char)
+        })
+        .collect();
+    let cursor = (chars.iter())
+        .position(|(_, offset, _)| *offset >= position.character as usize)
+        .unwrap_or(chars.len());
+    let at = match chars.get(cursor) {
+        Some(&(_, _, char)) if is_word(char) => cursor,
+        _ if cursor > 0 && is_word(chars[cursor - 1].2) => cursor - 1,
+        _ => return None,
+    };
+    let start = (chars[..at].iter())
+        .rposition(|(_, _, char)| !is_word(*char))
+        .map_or(0, |index| index + 1);
+    let end = (chars[at..].iter())
+        .position(|(_, _, char)| !is_word(*char))
+        .map_or(chars.len(), |index| at + index);
+    let byte = |index: usize| chars.get(index).map_or(line.len(), |(byte, _, _)| *byte);
+    let utf16 = |index: usize| chars.get(index).map_or(offset, |(_, offset, _)| *offset) as u32;
+    Some((
+        line[byte(start)..byte(end)].into(),
+        lsp::Range::new(
+            lsp::Position::new(position.line, utf16(start)),
+            lsp::Position::new(position.line, utf16(end)),
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(name, path, line, column, kind, scope, signature)` of each tag
+    #[allow(clippy::type_complexity)]
+    fn fields(
+        tags: Vec<Tag>,
+    ) -> Vec<(
+        String,
+        PathBuf,
+        u32,
+        u32,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+    )> {
+        (tags.into_iter())
+            .map(|tag| {
+                let Tag {
+                    name,
+                    path,
+                    line,
+                    column,
+                    kind,
+                    scope,
+                    signature,
+                } = tag;
+                (name, path, line, column, kind, scope, signature)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn classic_lines() {
+        let text = "\
+!_TAG_FILE_FORMAT\t2\t/extended format/
+add\tsrc/math.lua\t/^local function add(a, b)$/;\"\tf\tline:3\tsignature:(a, b)
+Point\t./src/geo.lua\t12;\"\tc
+get\tsrc/geo.lua\t/^  function Point:get() -- a\\/b;\" not the end$/;\"\tkind:function\tline:20\tclass:Point
+";
+        let some = |text: &str| Some(text.to_owned());
+        assert_eq!(
+            fields(parse(text, Path::new("/ws"))),
+            [
+                (
+                    "add".into(),
+                    "/ws/src/math.lua".into(),
+                    2,
+                    15,
+                    some("f"),
+                    None,
+                    some("(a, b)")
+                ),
+                (
+                    "Point".into(),
+                    "/ws/src/geo.lua".into(),
+                    11,
+                    0,
+                    some("c"),
+                    None,
+                    None
+                ),
+                (
+                    "get".into(),
+                    "/ws/src/geo.lua".into(),
+                    19,
+                    17,
+                    some("function"),
+                    some("Point"),
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn json_lines() {
+        let text = r#"{"_type": "ptag", "name": "JSON_OUTPUT_VERSION", "path": "0.0"}
+{"_type": "tag", "name": "x", "path": "src/a.lua", "pattern": "/^local é, 𝔸, x = 1$/", "line": 1, "kind": "variable"}
+{"_type": "tag", "name": "f", "path": "/abs/b.lua", "pattern": "/^function M.f() end$/", "line": 4, "kind": "function", "scope": "M"}
+"#;
+        let some = |text: &str| Some(text.to_owned());
+        assert_eq!(
+            fields(parse(text, Path::new("/ws"))),
+            [
+                (
+                    "x".into(),
+                    "/ws/src/a.lua".into(),
+                    0,
+                    13,
+                    some("variable"),
+                    None,
+                    None
+                ),
+                (
+                    "f".into(),
+                    "/abs/b.lua".into(),
+                    3,
+                    11,
+                    some("function"),
+                    some("M"),
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn locate_a_pattern_without_line() {
+        let dir = std::env::temp_dir().join(format!("lspcat-ctags-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("a.lua"),
+            "-- a\nlocal t = {}\nfunction t.new() end\n",
+        )
+        .unwrap();
+        let text = "new\ta.lua\t/^function t.new() end$/;\"\tf\nmissing\ta.lua\t/^function gone()$/;\"\tf\n";
+        let tags = parse(text, &dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!((tags.len(), tags[0].line, tags[0].column), (1, 2, 11));
+    }
+
+    #[test]
+    fn escaped_patterns() {
+        assert_eq!(unescape(r"/^a\/b \\ c$/"), (r"a/b \ c".into(), true));
+        assert_eq!(unescape(r"/^ends with \$/"), ("ends with $".into(), false));
+        assert_eq!(unescape(r"?^back?"), ("back".into(), false));
+    }
+
+    #[test]
+    fn word_at_boundaries() {
+        let text = "let foo_bar = αβγ(𝔸x);\n";
+        let word = |character| {
+            word_at(text, lsp::Position::new(0, character))
+                .map(|(word, range)| (word, range.start.character, range.end.character))
+        };
+        assert_eq!(word(4), Some(("foo_bar".into(), 4, 11)));
+        assert_eq!(word(11), Some(("foo_bar".into(), 4, 11))); // INFO: right after the word
+        assert_eq!(word(12), None);
+        assert_eq!(word(15), Some(("αβγ".into(), 14, 17)));
+        // INFO: `𝔸` is 2 UTF-16 code units, even from its second half
+        assert_eq!(word(19), Some(("𝔸x".into(), 18, 21)));
+        assert_eq!(word(23), None);
+        assert_eq!(word_at(text, lsp::Position::new(1, 0)), None);
+    }
+
+    #[test]
+    fn definitions_of_the_document_first() {
+        let ctags = Ctags {
+            program: "ctags".into(),
+            args: vec![],
+            tags_file: None,
+            timeout: None,
+            max_output: None,
+            index: Index::default(),
+        };
+        let text = "f\tb.lua\t1;\"\tf\nf\ta.lua\t7;\"\tf\ng\ta.lua\t2;\"\tf\n";
+        for tag in parse(text, Path::new("/ws")) {
+            ctags
+                .index
+                .files
+                .entry(tag.path.clone())
+                .or_default()
+                .push(tag);
+        }
+        let position = lsp::Position::new(0, 6);
+        let definition = ctags.definition(Path::new("/ws/b.lua"), "print(f())", position, false);
+        let Some(lsp::GotoDefinitionResponse::Array(locations)) = definition else {
+            panic!("{definition:?}")
+        };
+        let locations: Vec<_> = (locations.iter())
+            .map(|location| (location.uri.path(), location.range.start.line))
+            .collect();
+        assert_eq!(locations, [("/ws/b.lua", 0), ("/ws/a.lua", 6)]);
+    }
+}
@@ -0,0 +1,57 @@
+use super::PassThrough;
+use crate::Content;
+use serde::Deserialize;
+use serde_json::Value;
+use smol::channel;
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types as lsp;
+
+/// Run a linter whenever a document is opened or changed, then publish what it reports.
+pub struct Diagnostics {
+    pub proxy: PassThrough,
+}
+
+/// A fix kept in the `data` of a diagnostic, e.g. from the `fixes` of a SARIF result.
+#[derive(Deserialize)]
+struct Fix {
+    title: String,
+    edit: lsp::WorkspaceEdit,
+}
+
+impl Diagnostics {
+    /// The diagnostics of a document, each one is also sent to `found` as soon as an NDJSON linter print it.
+    pub async fn diagnose(
+        &self,
+        content: &Content,
+        found: channel::Sender<Value>,
+    ) -> Result<Vec<lsp::Diagnostic>> {
+        self.proxy.stream(content, &[], found).await
+    }
+
+    /// Quick fixes of the diagnostics in the requested range, which the client send back with their `data`.
+    pub fn code_actions(&self, params: &lsp::CodeActionParams) -> Vec<lsp::CodeActionOrCommand> {
+        let kind = lsp::CodeActionKind::QUICKFIX;
+        if (params.context.only.as_ref()).is_some_and(|only| !only.contains(&kind)) {
+            return vec![];
+        }
+        (params.context.diagnostics.iter())
+            .flat_map(|diagnostic| {
+                let fixes = (diagnostic.data.as_ref())
+                    .and_then(|data| Vec::<Fix>::deserialize(data.get("fixes")?).ok())
+                    .unwrap_or_default();
+                let preferred = fixes.len() == 1;
+                let kind = kind.clone();
+                fixes.into_iter().map(move |fix| {
+                    lsp::CodeActionOrCommand::CodeAction(lsp::CodeAction {
+                        title: fix.title,
+                        kind: Some(kind.clone()),
+                        diagnostics: Some(vec![diagnostic.clone()]),
+                        edit: Some(fix.edit),
+                        is_preferred: Some(preferred),
+                        ..lsp::CodeAction::default()
+                    })
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,37 @@
+use super::PassThrough;
+use crate::Content;
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types as lsp;
+
+/// Every automatic fix of a linter at once as a `source.fixAll` code action,
+/// e.g. with `Parser::DiffWorkspace` for a linter printing its fixes as a unified diff.
+pub struct FixAll {
+    pub proxy: PassThrough,
+    pub title: String, // e.g. "Fix all ruff issues"
+}
+
+impl FixAll {
+    /// INFO: only when the client asks for it (e.g. on save), since the linter runs for each request
+    pub fn is_requested(&self, params: &lsp::CodeActionParams) -> bool {
+        let kind = lsp::CodeActionKind::SOURCE_FIX_ALL;
+        (params.context.only.iter().flatten()).any(|only| {
+            let only = only.as_str();
+            kind.as_str() == only || kind.as_str().starts_with(&format!("{only}."))
+        })
+    }
+
+    pub async fn code_actions(&self, content: &Content) -> Result<Vec<lsp::CodeActionOrCommand>> {
+        let edit: lsp::WorkspaceEdit = self.proxy.exec(content, &[]).await?;
+        if (edit.changes.iter().flatten()).all(|(_, edits)| edits.is_empty()) {
+            return Ok(vec![]);
+        }
+        Ok(vec![lsp::CodeActionOrCommand::CodeAction(
+            lsp::CodeAction {
+                title: self.title.clone(),
+                kind: Some(lsp::CodeActionKind::SOURCE_FIX_ALL),
+                edit: Some(edit),
+                ..lsp::CodeAction::default()
+            },
+        )])
+    }
+}
@@ -0,0 +1,33 @@
+use super::{PassThrough, Proxy};
+use crate::Content;
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types as lsp;
+
+/// Format a whole document, e.g. with `Parser::Diff` for a formatter printing a unified diff.
+pub struct Formatting {
+    pub proxy: PassThrough, // with `{tab_size}` and `{insert_spaces}`
+}
+
+impl Proxy for Formatting {
+    type Params = lsp::DocumentFormattingParams;
+    type Response = Vec<lsp::TextEdit>;
+
+    async fn proxy_response(
+        &self,
+        params: Self::Params,
+        content: &Content,
+        _: Option<&lsp::ClientCapabilities>,
+    ) -> Result<Option<Self::Response>> {
+        let options = params.options;
+        let (tab_size, insert_spaces) = (
+            options.tab_size.to_string(),
+            options.insert_spaces.to_string(),
+        );
+        (self.proxy)
+            .exec(
+                content,
+                &[("tab_size", &tab_size), ("insert_spaces", &insert_spaces)],
+            )
+            .await
+    }
+}
@@ -0,0 +1,129 @@
+use super::{unwrap_data, wrap_data, PassThrough};
+use crate::Content;
+use serde::Serialize;
+use serde_json::Value;
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types as lsp;
+
+/// An item of a call or type hierarchy, which the client send back to resolve its neighbours.
+pub trait Item: Serialize {
+    fn selection_range(&self) -> lsp::Range;
+    fn uri(&self) -> &lsp::Url;
+    fn data(&mut self) -> &mut Option<Value>;
+}
+
+impl Item for lsp::CallHierarchyItem {
+    fn selection_range(&self) -> lsp::Range {
+        self.selection_range
+    }
+    fn uri(&self) -> &lsp::Url {
+        &self.uri
+    }
+    fn data(&mut self) -> &mut Option<Value> {
+        &mut self.data
+    }
+}
+
+impl Item for lsp::TypeHierarchyItem {
+    fn selection_range(&self) -> lsp::Range {
+        self.selection_range
+    }
+    fn uri(&self) -> &lsp::Url {
+        &self.uri
+    }
+    fn data(&mut self) -> &mut Option<Value> {
+        &mut self.data
+    }
+}
+
+/// Run a follow-up command with the original `item` as it was returned by the tool,
+/// where `content` is the document of the `item` so `{file}` and `{line}` belong together.
+pub async fn exec<T>(proxy: &PassThrough, item: &impl Item, content: &Content) -> Result<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let start = item.selection_range().start;
+    let (line, character, item) = (
+        start.line.to_string(),
+        start.character.to_string(),
+        serde_json::to_string(item).unwrap_or_default(),
+    );
+    proxy
+        .exec(
+            content,
+            &[("line", &line), ("character", &character), ("item", &item)],
+        )
+        .await
+}
+
+/// Remember the `uri` of the document an `item` was requested from, see [`wrap_data`].
+pub fn origin<I: Item>(mut item: I, uri: &lsp::Url) -> I {
+    let data = item.data().take();
+    *item.data() = wrap_data(uri, data);
+    item
+}
+
+/// The document an `item` was requested from, and the `item` as the tool returned it.
+pub fn restore<I: Item>(mut item: I) -> (lsp::Url, I) {
+    let (uri, data) = unwrap_data(item.data().take());
+    *item.data() = data;
+    (uri.unwrap_or_else(|| item.uri().clone()), item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::{Exec, Parser};
+    use serde_json::json;
+
+    fn item(uri: &str, data: Option<Value>) -> lsp::CallHierarchyItem {
+        let range = lsp::Range::new(lsp::Position::new(3, 4), lsp::Position::new(3, 7));
+        lsp::CallHierarchyItem {
+            name: "main".into(),
+            kind: lsp::SymbolKind::FUNCTION,
+            tags: None,
+            detail: None,
+            uri: uri.parse().unwrap(),
+            range,
+            selection_range: range,
+            data,
+        }
+    }
+
+    #[test]
+    fn round_trip_the_origin() {
+        let origin_uri: lsp::Url = "file:///a.c".parse().unwrap();
+        let wrapped = origin(item("file:///b.c", Some(json!({"id": 7}))), &origin_uri);
+        assert_eq!(
+            wrapped.data,
+            Some(json!({"uri": "file:///a.c", "data": {"id": 7}}))
+        );
+        let (uri, restored) = restore(wrapped);
+        assert_eq!(uri, origin_uri);
+        assert_eq!(restored, item("file:///b.c", Some(json!({"id": 7}))));
+
+        let (_, restored) = restore(origin(item("file:///b.c", None), &origin_uri));
+        assert_eq!(restored.data, None);
+    }
+
+    #[test]
+    fn restore_an_item_of_another_server() {
+        let (uri, restored) = restore(item("file:///b.c", Some(json!([1, 2]))));
+        assert_eq!(uri.as_str(), "file:///b.c");
+        assert_eq!(restored.data, Some(json!([1, 2])));
+    }
+
+    #[test]
+    fn exec_with_the_item_position() {
+        let exec = Exec::test("echo", &["{file}:{line}:{character}"], Parser::Text);
+        let content = Content::test("c", "/mirror/b.c", "");
+        let item = item("file:///mirror/b.c", None);
+        let printed: String = smol::block_on(super::exec(
+            &PassThrough::ExecCommand(exec),
+            &item,
+            &content,
+        ))
+        .unwrap();
+        assert_eq!(printed, "/mirror/b.c:3:4\n");
+    }
+}
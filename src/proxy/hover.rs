@@ -0,0 +1,61 @@
+use super::{markdown, PassThrough, Proxy};
+use crate::Content;
+use serde::Deserialize;
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types as lsp;
+
+/// Documentation of the symbol under the cursor, normalized into Markdown whatever the tool print.
+pub struct Hover {
+    pub proxy: PassThrough,
+    pub max_documentation: Option<usize>, // in bytes, longer documentation is truncated
+}
+
+/// Either an `lsp::Hover` or the plain text of `Parser::Text`, e.g. the colored output of a doc command.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Printed {
+    Hover(lsp::Hover),
+    Text(String),
+}
+
+impl Proxy for Hover {
+    type Params = lsp::HoverParams;
+    type Response = lsp::Hover;
+
+    async fn proxy_response(
+        &self,
+        params: Self::Params,
+        content: &Content,
+        client: Option<&lsp::ClientCapabilities>,
+    ) -> Result<Option<Self::Response>> {
+        let position = params.text_document_position_params.position;
+        let (line, character) = (position.line.to_string(), position.character.to_string());
+        let printed: Option<Printed> = self
+            .proxy
+            .exec(content, &[("line", &line), ("character", &character)])
+            .await?;
+        let render = markdown::Render {
+            language_id: &content.language_id,
+            max_length: self.max_documentation,
+            markdown: markdown::supported(
+                client.and_then(|client| client.text_document.as_ref()?.hover.as_ref()),
+                |hover| hover.content_format.as_ref(),
+            ),
+        };
+        Ok(match printed {
+            Some(Printed::Hover(hover)) => Some(lsp::Hover {
+                contents: render.hover(hover.contents),
+                ..hover
+            }),
+            Some(Printed::Text(text)) if !markdown::strip(&text).trim().is_empty() => {
+                Some(lsp::Hover {
+                    contents: lsp::HoverContents::Markup(
+                        render.markup(&text, lsp::MarkupKind::PlainText),
+                    ),
+                    range: None,
+                })
+            }
+            Some(Printed::Text(_)) | None => None,
+        })
+    }
+}
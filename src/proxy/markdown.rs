@@ -0,0 +1,514 @@
+use super::completion::shape::plain_markdown;
+use std::{iter::Peekable, ops::Range, str::Chars};
+use tower_lsp::lsp_types as lsp;
+
+const ELLIPSIS: &str = "\n\n…";
+
+/// How documentation printed by a tool is shown to the client.
+pub struct Render<'a> {
+    pub language_id: &'a str, // of the fenced code blocks, e.g. the one of the document
+    pub max_length: Option<usize>, // in bytes, longer documentation is truncated
+    pub markdown: bool,       // whether the client can show Markdown, otherwise plain text
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+struct Style {
+    bold: bool,
+    italic: bool, // also underline, which Markdown doesn't have
+}
+
+impl Render<'_> {
+    /// Documentation which is `kind` Markdown or plain text, maybe with ANSI escapes or man page formatting.
+    pub fn markup(&self, value: &str, kind: lsp::MarkupKind) -> lsp::MarkupContent {
+        let is_markdown = kind == lsp::MarkupKind::Markdown;
+        let value = match (is_markdown, self.markdown) {
+            (true, true) => strip(value),
+            (true, false) => plain_markdown(&strip(value)),
+            (false, true) => from_text(value, self.language_id),
+            (false, false) => strip(value),
+        };
+        lsp::MarkupContent {
+            kind: match self.markdown {
+                true => lsp::MarkupKind::Markdown,
+                false => lsp::MarkupKind::PlainText,
+            },
+            value: match self.max_length {
+                Some(max_length) => truncate(&value, max_length, self.markdown),
+                None => value,
+            },
+        }
+    }
+
+    pub fn documentation(&self, documentation: lsp::Documentation) -> lsp::Documentation {
+        lsp::Documentation::MarkupContent(match documentation {
+            lsp::Documentation::String(text) => self.markup(&text, lsp::MarkupKind::PlainText),
+            lsp::Documentation::MarkupContent(content) => self.markup(&content.value, content.kind),
+        })
+    }
+
+    pub fn hover(&self, contents: lsp::HoverContents) -> lsp::HoverContents {
+        let marked = |marked| match marked {
+            lsp::MarkedString::String(markdown) => markdown,
+            lsp::MarkedString::LanguageString(code) => fence(&code.value, &code.language),
+        };
+        let (value, kind) = match contents {
+            lsp::HoverContents::Scalar(scalar) => (marked(scalar), lsp::MarkupKind::Markdown),
+            lsp::HoverContents::Array(array) => {
+                let sections: Vec<_> = array.into_iter().map(marked).collect();
+                (sections.join("\n\n---\n\n"), lsp::MarkupKind::Markdown)
+            }
+            lsp::HoverContents::Markup(content) => (content.value, content.kind),
+        };
+        lsp::HoverContents::Markup(self.markup(&value, kind))
+    }
+}
+
+/// Whether a client accept Markdown, according to the formats (if any) of its `capability` for a request.
+/// INFO: a client which didn't tell that capability at all is assumed to, like every editor nowadays
+pub fn supported<T>(
+    capability: Option<&T>,
+    formats: fn(&T) -> Option<&Vec<lsp::MarkupKind>>,
+) -> bool {
+    capability.is_none_or(|capability| {
+        formats(capability).is_some_and(|formats| formats.contains(&lsp::MarkupKind::Markdown))
+    })
+}
+
+/// The text without any ANSI escape nor overstrike.
+pub fn strip(text: &str) -> String {
+    (lines(text).iter())
+        .map(|runs| {
+            runs.iter()
+                .map(|(_, text)| text.as_str())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Turn plain text into Markdown: styles become emphasis, the rest of the prose is escaped,
+/// and paragraphs of code are fenced with `language_id`.
+fn from_text(text: &str, language_id: &str) -> String {
+    let lines = lines(text);
+    let plain: Vec<String> = (lines.iter())
+        .map(|runs| runs.iter().map(|(_, text)| text.as_str()).collect())
+        .collect();
+    // INFO: a tool which already fence its code print Markdown
+    if (plain.iter()).any(|line| line.trim_start().starts_with("```")) {
+        return plain.join("\n");
+    }
+
+    let mut blocks = vec![];
+    let mut code: Option<Range<usize>> = None; // consecutive paragraphs of code, with the blank lines between
+    let mut example = None; // indentation of a paragraph ending with `:`, e.g. "Example:", after which indented ones are code
+    for paragraph in paragraphs(&plain) {
+        let indent = indentation(&plain[paragraph.start]);
+        let is_code = example.is_some_and(|example| indent >= example + 4)
+            || (paragraph.clone()).all(|line| looks_like_code(&plain[line]));
+        if is_code {
+            code = Some(match code {
+                Some(code) => code.start..paragraph.end,
+                None => paragraph,
+            });
+            continue;
+        }
+        if let Some(code) = code.take() {
+            blocks.push(fence(&dedent(&plain[code]), language_id));
+        }
+        let last = &plain[paragraph.end - 1];
+        example = (last.trim_end().ends_with(':')).then(|| indentation(last));
+        let prose: Vec<_> = lines[paragraph].iter().map(|runs| prose(runs)).collect();
+        blocks.push(prose.join("  \n")); // INFO: hard breaks keep the layout of option lists and alike
+    }
+    if let Some(code) = code {
+        blocks.push(fence(&dedent(&plain[code]), language_id));
+    }
+    blocks.join("\n\n")
+}
+
+/// The styled runs of each line, from ANSI SGR escapes and the overstrikes of man pages,
+/// i.e. `X\bX` for bold and `_\bX` for underline.
+fn lines(text: &str) -> Vec<Vec<(Style, String)>> {
+    let mut style = Style::default();
+    let mut lines = vec![];
+    for line in text.lines() {
+        // INFO: a line rewritten after `\r` (e.g. a progress bar) is shown as it was last
+        let line = (line.rsplit('\r').find(|part| !part.is_empty())).unwrap_or_default();
+        let mut runs: Vec<(Style, String)> = vec![];
+        let mut chars = line.chars().peekable();
+        while let Some(char) = chars.next() {
+            match char {
+                '\x1b' => escape(&mut chars, &mut style),
+                '\x08' => {
+                    let Some((previous, mut style)) = pop(&mut runs) else {
+                        continue;
+                    };
+                    let Some(char) = chars.next() else {
+                        continue;
+                    };
+                    match previous {
+                        '_' if char != '_' => style.italic = true,
+                        previous if previous == char => style.bold = true,
+                        _ => {}
+                    }
+                    push(&mut runs, style, char);
+                }
+                '\t' => push(&mut runs, style, char),
+                char if char.is_control() => {}
+                char => push(&mut runs, style, char),
+            }
+        }
+        lines.push(runs);
+    }
+    lines
+}
+
+fn push(runs: &mut Vec<(Style, String)>, style: Style, char: char) {
+    match runs.last_mut() {
+        Some((last, text)) if *last == style => text.push(char),
+        _ => runs.push((style, char.into())),
+    }
+}
+
+fn pop(runs: &mut Vec<(Style, String)>) -> Option<(char, Style)> {
+    let (style, text) = runs.last_mut()?;
+    let (char, style) = (text.pop()?, *style);
+    if text.is_empty() {
+        runs.pop();
+    }
+    Some((char, style))
+}
+
+/// Skip an escape sequence after `ESC`, applying it to `style` when it is SGR.
+fn escape(chars: &mut Peekable<Chars>, style: &mut Style) {
+    match chars.next() {
+        // INFO: CSI parameters are followed by a final byte in `@`..=`~`
+        Some('[') => {
+            let mut parameters = String::new();
+            for char in chars.by_ref() {
+                if ('@'..='~').contains(&char) {
+                    if char == 'm' {
+                        sgr(&parameters, style);
+                    }
+                    return;
+                }
+                parameters.push(char);
+            }
+        }
+        // INFO: OSC (e.g. a hyperlink) is ended by BEL or `ESC \`
+        Some(']') => {
+            while let Some(char) = chars.next() {
+                match char {
+                    '\x07' => return,
+                    '\x1b' => {
+                        chars.next_if_eq(&'\\');
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Some('(' | ')') => _ = chars.next(), // character set, e.g. `ESC (B`
+        _ => {}
+    }
+}
+
+/// Apply the parameters of `ESC [ ... m`, of which only bold, italic and underline have a Markdown equivalent.
+fn sgr(parameters: &str, style: &mut Style) {
+    let mut codes = (parameters.split([';', ':'])).map(|code| code.parse::<u32>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        match code {
+            0 => *style = Style::default(),
+            1 => style.bold = true,
+            3 | 4 => style.italic = true,
+            22 => style.bold = false,
+            23 | 24 => style.italic = false,
+            // INFO: the arguments of a 256 or RGB color must not be mistaken for other codes
+            38 | 48 | 58 => match codes.next() {
+                Some(5) => _ = codes.next(),
+                Some(2) => _ = codes.nth(2),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Ranges of consecutive non-blank lines, also split after a line ending with `:` followed by indented ones
+/// (e.g. "Example:") and after the lines of code which start a paragraph (e.g. a signature above its documentation).
+fn paragraphs(lines: &[String]) -> Vec<Range<usize>> {
+    let mut paragraphs: Vec<Range<usize>> = vec![];
+    let mut code = false; // whether every line of the last paragraph looks like code
+    for (index, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let looks_like_code = looks_like_code(line);
+        match paragraphs.last_mut() {
+            Some(paragraph) if paragraph.end == index => {
+                let previous = &lines[index - 1];
+                let example = previous.trim_end().ends_with(':')
+                    && indentation(line) >= indentation(previous) + 4;
+                if example || (code && !looks_like_code) {
+                    paragraphs.push(index..index + 1);
+                } else {
+                    paragraph.end += 1;
+                }
+            }
+            _ => paragraphs.push(index..index + 1),
+        }
+        code = match paragraphs.last() {
+            Some(paragraph) if paragraph.len() > 1 => code && looks_like_code,
+            _ => looks_like_code,
+        };
+    }
+    paragraphs
+}
+
+/// Whether a line is rather code than prose, e.g. a signature, a statement or a block delimiter.
+fn looks_like_code(line: &str) -> bool {
+    let line = line.trim();
+    if line.ends_with(['.', '?', '!']) && !line.ends_with("..") {
+        return false;
+    }
+    let call = (line.find('('))
+        .is_some_and(|at| line[..at].ends_with(|char: char| char.is_alphanumeric() || char == '_'));
+    let symbols = (line.chars())
+        .filter(|char| "{}[]()<>=;:*&|".contains(*char))
+        .count();
+    line.ends_with(['{', '}', ';'])
+        || line.starts_with('}')
+        || (line.ends_with(')') && call)
+        || ["->", "=>", "::", ":=", "==", "!="]
+            .iter()
+            .any(|op| line.contains(op))
+        || symbols * 8 > line.len()
+}
+
+fn indentation(line: &str) -> usize {
+    (line.chars())
+        .take_while(|char| char.is_whitespace())
+        .map(|char| if char == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// The lines without their common indentation.
+fn dedent(lines: &[String]) -> String {
+    let common = (lines.iter())
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or_default();
+    (lines.iter())
+        .map(|line| line.get(common..).unwrap_or_default().trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A line of prose with its styled runs as emphasis and the rest escaped.
+fn prose(runs: &[(Style, String)]) -> String {
+    let mut line = String::new();
+    for (style, text) in runs {
+        let text = match line.is_empty() {
+            true => text.trim_start(),
+            false => text,
+        };
+        let mark = match (style.bold, style.italic) {
+            (true, true) => "***",
+            (true, false) => "**",
+            (false, true) => "*",
+            (false, false) => "",
+        };
+        let trimmed = text.trim();
+        if mark.is_empty() || trimmed.is_empty() {
+            line.push_str(&escape_markdown(text));
+            continue;
+        }
+        let leading = &text[..text.len() - text.trim_start().len()];
+        let trailing = &text[text.trim_end().len()..];
+        let trimmed = escape_markdown(trimmed);
+        line.push_str(&format!("{leading}{mark}{trimmed}{mark}{trailing}"));
+    }
+    // INFO: bullets are kept as a Markdown list
+    match line.strip_prefix("\\* ") {
+        Some(item) => format!("* {item}"),
+        None => line.trim_end().into(),
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        if "\\`*_[]<>#|".contains(char) {
+            escaped.push('\\');
+        }
+        escaped.push(char);
+    }
+    escaped
+}
+
+/// A fenced code block, with a fence longer than any run of backticks in the `code`.
+fn fence(code: &str, language: &str) -> String {
+    let longest = (code.split(|char| char != '`').map(str::len).max()).unwrap_or_default();
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{fence}{language}\n{code}\n{fence}")
+}
+
+/// Cut a text to at most `max_length` bytes, at the end of a line or a word when it doesn't lose too much,
+/// then close the code block which was cut if any. The fence and the ellipsis count in `max_length`.
+fn truncate(text: &str, max_length: usize, markdown: bool) -> String {
+    if text.len() <= max_length {
+        return text.into();
+    }
+    let Some(mut budget) = max_length.checked_sub(ELLIPSIS.len()) else {
+        return text[..char_boundary(text, max_length)].into(); // INFO: no room for the ellipsis
+    };
+    loop {
+        let mut truncated = cut(text, budget).to_string();
+        let fence = open_fence(&truncated).filter(|_| markdown);
+        let closing = fence.as_ref().map_or(0, |fence| fence.len() + 1);
+        if truncated.len() + closing > budget {
+            // INFO: a shorter cut may also be out of the code block
+            budget -= closing.min(budget);
+            continue;
+        }
+        if let Some(fence) = fence {
+            truncated.push('\n');
+            truncated.push_str(&fence);
+        }
+        truncated.push_str(ELLIPSIS);
+        return truncated;
+    }
+}
+
+/// The text before `end`, or before the last line or word within it when it doesn't lose more than half.
+fn cut(text: &str, end: usize) -> &str {
+    let end = char_boundary(text, end);
+    let half = end / 2;
+    let end = (text[..end].rfind('\n').filter(|line| *line >= half))
+        .or_else(|| text[..end].rfind(' ').filter(|word| *word >= half))
+        .unwrap_or(end);
+    text[..end].trim_end()
+}
+
+/// The last char boundary of `text` at or before `index`.
+fn char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// The fence of the code block which is still open at the end of some Markdown.
+fn open_fence(markdown: &str) -> Option<String> {
+    let mut open: Option<String> = None;
+    for line in markdown.lines() {
+        let line = line.trim();
+        let ticks = line.len() - line.trim_start_matches('`').len();
+        if ticks < 3 {
+            continue;
+        }
+        match &open {
+            Some(fence) if ticks >= fence.len() && ticks == line.len() => open = None,
+            Some(_) => {}
+            None => open = Some(line[..ticks].into()),
+        }
+    }
+    open
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_into_markdown() {
+        let cases = [
+            // ANSI bold, and a 256-color whose `5;1` is not bold
+            (
+                "\x1b[1mls\x1b[0m \x1b[38;5;1mlists\x1b[0m files.",
+                "**ls** lists files.",
+            ),
+            ("\x1b[1;3mboth\x1b[22m italic\x1b[m", "***both*** *italic*"),
+            // man page overstrikes, bold then underline
+            ("N\x08NA\x08AM\x08ME\x08E", "**NAME**"),
+            ("Open _\x08f_\x08i_\x08l_\x08e now.", "Open *file* now."),
+            ("__\x08_ is bold.", "\\_**\\_** is bold."), // `_\b_` is a bold underscore
+            // indented paragraphs after "Example:" are code, with or without a blank line
+            (
+                "Add two numbers.\n\nExample:\n\n    add(1, 2)\n    sum = 3 + 4",
+                "Add two numbers.\n\nExample:\n\n```python\nadd(1, 2)\nsum = 3 + 4\n```",
+            ),
+            (
+                "Example:\n    total = add(1, 2)\nThen print it.",
+                "Example:\n\n```python\ntotal = add(1, 2)\n```\n\nThen print it.",
+            ),
+            // a signature above its documentation
+            (
+                "def add(a, b) -> int\nAdd two numbers.",
+                "```python\ndef add(a, b) -> int\n```\n\nAdd two numbers.",
+            ),
+            (
+                "already\n```python\nfenced\n```",
+                "already\n```python\nfenced\n```",
+            ),
+            (
+                "* Returns the sum of both numbers\n  or <none> when empty.",
+                "* Returns the sum of both numbers  \nor \\<none\\> when empty.",
+            ),
+        ];
+        for (text, markdown) in cases {
+            assert_eq!(from_text(text, "python"), markdown, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn strip_escapes() {
+        let cases = [
+            ("\x1b[1mbold\x1b[0m", "bold"),
+            ("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x07", "link"),
+            ("\x1b(Bplain", "plain"),
+            ("50%\r100%", "100%"),
+            ("b\x08bo\x08ol\x08ld\x08d", "bold"),
+        ];
+        for (text, stripped) in cases {
+            assert_eq!(strip(text), stripped, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn truncate_at_a_boundary() {
+        let code = "Doc.\n\n```rust\nfn a() {}\nfn b() {}\nfn c() {}\n```";
+        let cases = [
+            ("short", 10, true, "short"),
+            // the cut code block is closed when the client shows Markdown
+            (code, 40, true, "Doc.\n\n```rust\nfn a() {}\n```\n\n…"),
+            (
+                code,
+                40,
+                false,
+                "Doc.\n\n```rust\nfn a() {}\nfn b() {}\n\n…",
+            ),
+            ("one two three four", 15, false, "one two\n\n…"),
+            // never inside a multibyte char
+            ("ééééé", 8, false, "é\n\n…"),
+            ("🦀🦀🦀", 10, false, "🦀\n\n…"),
+            // no room for the ellipsis
+            ("one two", 3, false, "one"),
+        ];
+        for (text, max_length, markdown, truncated) in cases {
+            assert_eq!(truncate(text, max_length, markdown), truncated, "{text:?}");
+        }
+
+        // INFO: the fence and the ellipsis are within the length, whatever it is
+        for text in [code, "ééééé", "a\n````\nb c d e f g\n````"] {
+            for max_length in 0..=text.len() {
+                let truncated = truncate(text, max_length, true);
+                assert!(
+                    truncated.len() <= max_length,
+                    "{truncated:?} > {max_length}"
+                );
+            }
+        }
+    }
+}
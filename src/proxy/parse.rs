@@ -0,0 +1,332 @@
+mod diff;
+mod errorformat;
+mod query;
+mod sarif;
+mod xml;
+pub use errorformat::{ErrorFormat, Preset};
+pub use query::{completion_kind, symbol_kind, Query};
+
+use crate::Content;
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::{
+    borrow::Cow,
+    path::{Component, Path, PathBuf},
+};
+
+/// How the stdout of a tool is turned into the LSP response.
+pub enum Parser {
+    Json,            // stdout is already the LSP response
+    Text,            // stdout as a JSON string, e.g. the plain documentation of a hover
+    Ndjson(Mapping), // one JSON object per line
+    Columns {
+        separator: String,    // e.g. "\t" for TSV
+        columns: Vec<String>, // name of each column, the last one keep the rest of the line
+        mapping: Mapping,
+    },
+    Words(Mapping), // each whitespace separated word become a record with a `word` field
+    Regex {
+        pattern: Regex, // each match become a record of its named captures
+        mapping: Mapping,
+    },
+    ErrorFormat(ErrorFormat), // into `lsp::Diagnostic`s of the document
+    Sarif,         // a SARIF log into `lsp::Diagnostic`s of the document, with their fixes
+    Checkstyle,    // a Checkstyle XML report into `lsp::Diagnostic`s of the document
+    Junit,         // the failed test cases of a JUnit XML report into `lsp::Diagnostic`s
+    Diff,          // a unified diff into the `lsp::TextEdit`s of the document, e.g. for formatting
+    DiffWorkspace, // a unified diff into an `lsp::WorkspaceEdit`, e.g. for rename and code actions
+    Query {
+        query: Query, // each output become an item, e.g. a `CompletionItem` or a `Location`
+        first: bool,  // the response is a single item, e.g. a `Hover`
+    },
+}
+
+/// Declarative mapping of a record into the JSON of an LSP type, by target path (e.g. `range.start.line`).
+/// An empty mapping keep the record as it is.
+#[derive(Default)]
+pub struct Mapping {
+    pub fields: Vec<(String, Field)>,
+}
+
+/// Where a field of the LSP type come from.
+pub enum Field {
+    Raw(String),         // a record field as it is, e.g. a nested object of NDJSON
+    Text(String), // a template where every `{key}` is replaced by a record field, e.g. "{name}: {type}"
+    Number(String, i64), // a record field as a number plus an offset, e.g. 1-based line with -1
+    Const(Value), // e.g. a `CompletionItemKind`
+    Query(Query), // the first output of a query on the record, e.g. `.type | completion_kind`
+}
+
+type Record = Map<String, Value>;
+
+impl Parser {
+    pub fn parse<T: DeserializeOwned>(&self, stdout: &str, content: &Content) -> Result<T, String> {
+        let (records, mapping) = match self {
+            Parser::Json => return serde_json::from_str(stdout).map_err(|err| err.to_string()),
+            Parser::Text => {
+                let text = Value::String(stdout.into());
+                return serde_json::from_value(text).map_err(|err| err.to_string());
+            }
+            Parser::Ndjson(mapping) => (ndjson(stdout)?, mapping),
+            Parser::Columns {
+                separator,
+                columns,
+                mapping,
+            } => (self::columns(stdout, separator, columns), mapping),
+            Parser::Words(mapping) => (words(stdout), mapping),
+            Parser::Regex { pattern, mapping } => (captures(stdout, pattern), mapping),
+            Parser::ErrorFormat(errorformat) => {
+                let diagnostics = errorformat.parse(stdout, &content.path, &content.root);
+                return serde_json::from_value(diagnostics).map_err(|err| err.to_string());
+            }
+            Parser::Sarif => {
+                let diagnostics = sarif::parse(stdout, &content.path, &content.root)?;
+                return serde_json::from_value(diagnostics).map_err(|err| err.to_string());
+            }
+            Parser::Checkstyle => {
+                let diagnostics = xml::checkstyle(stdout, &content.path, &content.root)?;
+                return serde_json::from_value(diagnostics).map_err(|err| err.to_string());
+            }
+            Parser::Junit => {
+                let diagnostics = xml::junit(stdout, &content.path, &content.root)?;
+                return serde_json::from_value(diagnostics).map_err(|err| err.to_string());
+            }
+            Parser::Query { query, first } => {
+                let mut items = query.run(stdout)?;
+                let response = match first {
+                    true => items.drain(..).next().unwrap_or_default(),
+                    false => Value::Array(items),
+                };
+                return serde_json::from_value(response).map_err(|err| err.to_string());
+            }
+            Parser::Diff => {
+                let edits = diff::text_edits(stdout, &content.path, &content.text, &content.root)?;
+                return serde_json::to_value(edits)
+                    .and_then(serde_json::from_value)
+                    .map_err(|err| err.to_string());
+            }
+            Parser::DiffWorkspace => {
+                let edit =
+                    diff::workspace_edit(stdout, &content.path, &content.text, &content.root)?;
+                return serde_json::to_value(edit)
+                    .and_then(serde_json::from_value)
+                    .map_err(|err| err.to_string());
+            }
+        };
+        let items = records.iter().map(|record| mapping.apply(record)).collect();
+        serde_json::from_value(Value::Array(items)).map_err(|err| err.to_string())
+    }
+
+    /// The item of a single line of NDJSON, so it can be used before the whole output is printed.
+    /// Other parsers need the whole output.
+    pub fn parse_line(&self, line: &str) -> Option<Value> {
+        match self {
+            Parser::Ndjson(mapping) => Some(mapping.apply(&record(line)?.ok()?)),
+            _ => None,
+        }
+    }
+}
+
+impl Mapping {
+    pub fn apply(&self, record: &Record) -> Value {
+        if self.fields.is_empty() {
+            return Value::Object(record.clone());
+        }
+        let mut item = Value::Object(Map::new());
+        for (path, field) in &self.fields {
+            let value = match field {
+                Field::Raw(key) => record.get(key).cloned(),
+                Field::Text(template) => Some(interpolate(template, record).into()),
+                Field::Number(key, offset) => (record.get(key))
+                    .and_then(|value| match value {
+                        Value::Number(number) => number.as_i64(),
+                        Value::String(text) => text.trim().parse().ok(),
+                        _ => None,
+                    })
+                    .map(|number| (number + offset).max(0).into()),
+                Field::Const(value) => Some(value.clone()),
+                Field::Query(query) => (query.eval(&Value::Object(record.clone())).ok())
+                    .and_then(|outputs| outputs.into_iter().next()),
+            };
+            if let Some(value) = value {
+                insert(&mut item, path, value);
+            }
+        }
+        item
+    }
+}
+
+/// Replace every `{key}` in `template` by the field of `record`, strings are inserted without quotes.
+pub fn interpolate(template: &str, record: &Record) -> String {
+    super::substitute_with(template, |key| {
+        record.get(key).map(|value| match value {
+            Value::String(value) => Cow::Borrowed(value.as_str()),
+            value => Cow::Owned(value.to_string()),
+        })
+    })
+}
+
+/// Set `value` at a dotted `path`, creating the objects along the way.
+pub fn insert(item: &mut Value, path: &str, value: Value) {
+    let mut target = item;
+    for key in path.split('.') {
+        if !target.is_object() {
+            *target = Value::Object(Map::new());
+        }
+        target = &mut target[key];
+    }
+    *target = value;
+}
+
+/// Whether a `file` reported by a tool, absolute or relative to the `root` where it ran, is the `document`.
+pub fn is_document(file: &str, document: &Path, root: &Path) -> bool {
+    document == normalize(&root.join(file.trim()))
+}
+
+/// The path without its `.` and `..` components, e.g. `./src/../main.c`, without reading the disk.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => _ = normal.pop(),
+            component => normal.push(component),
+        }
+    }
+    normal
+}
+
+fn ndjson(stdout: &str) -> Result<Vec<Record>, String> {
+    stdout.lines().filter_map(record).collect()
+}
+
+/// The record of a line of NDJSON, a value which isn't an object is kept in its `value` field.
+fn record(line: &str) -> Option<Result<Record, String>> {
+    if line.trim().is_empty() {
+        return None;
+    }
+    Some(match serde_json::from_str(line) {
+        Ok(Value::Object(record)) => Ok(record),
+        Ok(value) => Ok(Map::from_iter([("value".to_owned(), value)])),
+        Err(err) => Err(format!("{err} in {line}")),
+    })
+}
+
+fn columns(stdout: &str, separator: &str, columns: &[String]) -> Vec<Record> {
+    (stdout.lines())
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let cells = line.splitn(columns.len(), separator);
+            (columns.iter().cloned())
+                .zip(cells.map(|cell| Value::String(cell.into())))
+                .collect()
+        })
+        .collect()
+}
+
+fn words(stdout: &str) -> Vec<Record> {
+    (stdout.split_whitespace())
+        .map(|word| Map::from_iter([("word".to_owned(), word.into())]))
+        .collect()
+}
+
+fn captures(stdout: &str, pattern: &Regex) -> Vec<Record> {
+    (pattern.captures_iter(stdout))
+        .map(|captures| {
+            (pattern.capture_names().flatten())
+                .filter_map(|name| Some((name.to_owned(), captures.name(name)?.as_str().into())))
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::mapping;
+    use serde_json::json;
+
+    fn content() -> Content {
+        Content::test("python", "/mirror/src/main.py", "")
+    }
+
+    fn parse(parser: &Parser, stdout: &str) -> Value {
+        parser.parse(stdout, &content()).unwrap()
+    }
+
+    #[test]
+    fn ndjson_fields() {
+        let parser = Parser::Ndjson(mapping([
+            ("range.start.line", Field::Number("line".into(), -1)),
+            ("range.start.character", Field::Number("column".into(), -1)),
+            ("message", Field::Text("{message} ({code})".into())),
+            ("data", Field::Raw("fix".into())),
+            ("source", Field::Const(json!("lint"))),
+        ]));
+        let stdout = concat!(
+            r#"{"line": 3, "column": "1", "message": "unused", "code": 401, "fix": {"a": 1}}"#,
+            "\n\n",
+            r#"{"line": 0, "column": 2, "message": "b"}"#,
+        );
+        assert_eq!(
+            parse(&parser, stdout),
+            json!([
+                {
+                    "range": {"start": {"line": 2, "character": 0}},
+                    "message": "unused (401)",
+                    "data": {"a": 1},
+                    "source": "lint",
+                },
+                {
+                    "range": {"start": {"line": 0, "character": 1}},
+                    "message": "b ({code})",
+                    "source": "lint",
+                },
+            ])
+        );
+        assert_eq!(
+            parser
+                .parse_line(r#"{"line": 1, "column": 1, "message": "c"}"#)
+                .unwrap()["range"],
+            json!({"start": {"line": 0, "character": 0}})
+        );
+        let err = parser.parse::<Value>("{\"line\": 1}\nnot json", &content());
+        assert!(err.unwrap_err().ends_with("in not json"));
+    }
+
+    #[test]
+    fn columns_keep_the_rest_in_the_last_one() {
+        let parser = Parser::Columns {
+            separator: ":".into(),
+            columns: ["line", "text"].map(String::from).to_vec(),
+            mapping: Mapping::default(),
+        };
+        assert_eq!(
+            parse(&parser, "3:a: b\n\n4:c\n"),
+            json!([{"line": "3", "text": "a: b"}, {"line": "4", "text": "c"}])
+        );
+    }
+
+    #[test]
+    fn words_and_regex() {
+        let parser = Parser::Words(mapping([
+            ("label", Field::Raw("word".into())),
+            ("kind", Field::Const(json!(1))),
+        ]));
+        assert_eq!(
+            parse(&parser, " foo\tbar\n"),
+            json!([{"label": "foo", "kind": 1}, {"label": "bar", "kind": 1}])
+        );
+
+        let parser = Parser::Regex {
+            pattern: Regex::new(r"(?m)^(?P<line>\d+): (?P<typo>\w+)(?: ==> (?P<fix>\w+))?$")
+                .unwrap(),
+            mapping: Mapping::default(),
+        };
+        assert_eq!(
+            parse(&parser, "1: teh ==> the\nnoise\n2: adn\n"),
+            json!([{"line": "1", "typo": "teh", "fix": "the"}, {"line": "2", "typo": "adn"}])
+        );
+    }
+}
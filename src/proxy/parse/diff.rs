@@ -0,0 +1,430 @@
+use super::is_document;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use tower_lsp::lsp_types as lsp;
+
+/// Changes to one file, in the order of the diff.
+struct File<'a> {
+    path: &'a str,
+    hunks: Vec<Hunk<'a>>,
+}
+
+/// Lines of a hunk starting at the 0-based line `start` of the old file, each with its ` `, `-`, `+` or `\` prefix.
+struct Hunk<'a> {
+    start: usize,
+    lines: Vec<(char, &'a str)>,
+    remaining: Option<(usize, usize)>, // old and new lines left to read, `None` until the next header
+}
+
+/// The edits of a unified diff (or `rustfmt --check`) on the `document`, whose context must match its `text`.
+/// Relative paths are in `root`, where the tool ran.
+pub fn text_edits(
+    stdout: &str,
+    document: &Path,
+    text: &str,
+    root: &Path,
+) -> Result<Vec<lsp::TextEdit>, String> {
+    (files(stdout)?.iter())
+        .filter(|file| {
+            is_document(file.path, document, root) || is_document(strip(file.path), document, root)
+        })
+        .map(|file| edits(file, text))
+        .try_fold(vec![], |mut all, edits| {
+            all.extend(edits?);
+            Ok(all)
+        })
+}
+
+/// The edits of a unified diff on every file, the ones other than the `document` are checked against the disk.
+/// Relative paths are in `root`, e.g. the mirror of the workspace folder where the tool ran.
+pub fn workspace_edit(
+    stdout: &str,
+    document: &Path,
+    text: &str,
+    root: &Path,
+) -> Result<lsp::WorkspaceEdit, String> {
+    let mut changes: HashMap<lsp::Url, Vec<lsp::TextEdit>> = HashMap::new();
+    for file in files(stdout)? {
+        let path = resolve(file.path, document, root)?;
+        let edits = match path == document {
+            true => edits(&file, text)?,
+            false => {
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("{}: {err}", file.path))?;
+                edits(&file, &text)?
+            }
+        };
+        let uri = lsp::Url::from_file_path(&path)
+            .map_err(|_| format!("{}: not a file path", file.path))?;
+        changes.entry(uri).or_default().extend(edits);
+    }
+    Ok(lsp::WorkspaceEdit {
+        changes: Some(changes),
+        ..lsp::WorkspaceEdit::default()
+    })
+}
+
+fn files(stdout: &str) -> Result<Vec<File<'_>>, String> {
+    let mut files: Vec<File> = vec![];
+    let mut old_path = None;
+    for line in stdout.split_inclusive('\n') {
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        let hunk = files.last_mut().and_then(|file| file.hunks.last_mut());
+
+        // INFO: inside a hunk, `--- x` is the removed line `-- x`
+        let marker = line.starts_with('\\');
+        if let Some(hunk) = hunk.filter(|hunk| marker || hunk.remaining != Some((0, 0))) {
+            if hunk.push(line) {
+                continue;
+            }
+        }
+        if let Some(path) = line.strip_prefix("--- ") {
+            old_path = Some(header_path(path));
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            let path = header_path(path);
+            if path == "/dev/null" || old_path == Some("/dev/null") {
+                return Err(format!("creating or deleting {path} is not supported"));
+            }
+            files.push(File {
+                path,
+                hunks: vec![],
+            });
+        } else if let Some(header) = line.strip_prefix("@@ -") {
+            let file = files.last_mut().ok_or("hunk without file header")?;
+            file.hunks
+                .push(Hunk::unified(header).ok_or_else(|| format!("invalid hunk {line}"))?);
+        } else if let Some((path, start)) = rustfmt_header(line) {
+            if files.last().is_none_or(|file| file.path != path) {
+                files.push(File {
+                    path,
+                    hunks: vec![],
+                });
+            }
+            if let Some(file) = files.last_mut() {
+                file.hunks.push(Hunk {
+                    start: start.saturating_sub(1),
+                    lines: vec![],
+                    remaining: None,
+                });
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// The path of a `---`/`+++` header, without the timestamp some tools add after a tab.
+fn header_path(header: &str) -> &str {
+    header.split('\t').next().unwrap_or(header).trim_end()
+}
+
+/// `Diff in {path} at line {line}:` or `Diff in {path}:{line}:` of `rustfmt --check`.
+fn rustfmt_header(line: &str) -> Option<(&str, usize)> {
+    let rest = line.strip_prefix("Diff in ")?.strip_suffix(':')?;
+    let (path, start) = (rest.rsplit_once(" at line ")).or_else(|| rest.rsplit_once(':'))?;
+    Some((path, start.parse().ok()?))
+}
+
+/// The `a/` or `b/` prefix of git diffs.
+fn strip(path: &str) -> &str {
+    (path.strip_prefix("a/").or_else(|| path.strip_prefix("b/"))).unwrap_or(path)
+}
+
+fn resolve(path: &str, document: &Path, root: &Path) -> Result<PathBuf, String> {
+    if is_document(path, document, root) || is_document(strip(path), document, root) {
+        return Ok(document.to_path_buf());
+    }
+    ([path, strip(path)].into_iter())
+        .map(|path| root.join(path))
+        .find(|path| path.is_file())
+        .ok_or_else(|| format!("{path}: no such file"))
+}
+
+impl<'a> Hunk<'a> {
+    /// From the rest of `@@ -start,count +start,count @@`.
+    fn unified(header: &str) -> Option<Self> {
+        let (ranges, _) = header.split_once(" @@")?;
+        let (old, new) = ranges.split_once(" +")?;
+        let range = |range: &str| -> Option<(usize, usize)> {
+            match range.split_once(',') {
+                Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+                None => Some((range.parse().ok()?, 1)),
+            }
+        };
+        let ((start, old), (_, new)) = (range(old)?, range(new)?);
+        Some(Self {
+            // INFO: an insertion without old lines (`-3,0`) is after the line `start`
+            start: if old == 0 {
+                start
+            } else {
+                start.saturating_sub(1)
+            },
+            lines: vec![],
+            remaining: Some((old, new)),
+        })
+    }
+
+    /// Add a line of the hunk body, returning whether it was one.
+    fn push(&mut self, line: &'a str) -> bool {
+        let (kind, text) = match line.chars().next() {
+            Some(kind @ (' ' | '-' | '+' | '\\')) => (kind, &line[1..]),
+            None => (' ', ""), // INFO: some tools trim the space of an empty context line
+            _ => return false,
+        };
+        if let Some((old, new)) = &mut self.remaining {
+            let (old_line, new_line) = (matches!(kind, ' ' | '-'), matches!(kind, ' ' | '+'));
+            if (old_line && *old == 0) || (new_line && *new == 0) {
+                return false;
+            }
+            *old -= old_line as usize;
+            *new -= new_line as usize;
+        }
+        self.lines.push((kind, text));
+        true
+    }
+}
+
+/// One edit per run of changed lines, after checking that the context and removed lines match `text`.
+fn edits(file: &File, text: &str) -> Result<Vec<lsp::TextEdit>, String> {
+    let mut edits = vec![];
+    for hunk in &file.hunks {
+        // INFO: `rustfmt --check` diffs the lines *separated* by newlines, so a final newline is an empty last line
+        let separated = hunk.remaining.is_none();
+        let lines: Vec<_> = match separated {
+            true => text.split('\n').collect(),
+            false => text.split_inclusive('\n').collect(),
+        };
+        let mut line = hunk.start;
+        let mut block: Option<Block> = None;
+        let mut previous = ' ';
+        for &(kind, content) in &hunk.lines {
+            if matches!(kind, ' ' | '-') {
+                let actual = (lines.get(line)).map(|line| line.strip_suffix('\n').unwrap_or(line));
+                if actual != Some(content) {
+                    return Err(format!(
+                        "{}:{}: the diff doesn't match the document",
+                        file.path,
+                        line + 1
+                    ));
+                }
+            }
+            let new_block = || Block {
+                start: line,
+                end: line,
+                lines: vec![],
+                final_newline: true,
+            };
+            match kind {
+                ' ' => {
+                    edits.extend(block.take().map(|block| block.edit(&lines, separated)));
+                    line += 1;
+                }
+                '-' => {
+                    block.get_or_insert_with(new_block).end = line + 1;
+                    line += 1;
+                }
+                '+' => block.get_or_insert_with(new_block).lines.push(content),
+                // INFO: `\ No newline at end of file` after an added line
+                _ if previous == '+' => {
+                    if let Some(block) = &mut block {
+                        block.final_newline = false;
+                    }
+                }
+                _ => {}
+            }
+            previous = kind;
+        }
+        edits.extend(block.map(|block| block.edit(&lines, separated)));
+    }
+    Ok(edits)
+}
+
+/// The old lines `start..end` replaced by the new `lines`.
+struct Block<'a> {
+    start: usize,
+    end: usize,
+    lines: Vec<&'a str>,
+    final_newline: bool,
+}
+
+impl Block<'_> {
+    fn edit(self, lines: &[&str], separated: bool) -> lsp::TextEdit {
+        let start = lsp::Position::new(self.start as u32, 0);
+        let eof = match lines.last() {
+            Some(last) if !last.ends_with('\n') => {
+                lsp::Position::new(lines.len() as u32 - 1, last.encode_utf16().count() as u32)
+            }
+            _ => lsp::Position::new(lines.len() as u32, 0),
+        };
+        let (range, new_text) = match separated {
+            false => {
+                let end = match self.end < lines.len() {
+                    true => lsp::Position::new(self.end as u32, 0),
+                    false => eof,
+                };
+                let mut new_text: String =
+                    self.lines.iter().map(|line| format!("{line}\n")).collect();
+                if !self.final_newline {
+                    new_text.pop();
+                }
+                (lsp::Range::new(start, end), new_text)
+            }
+            true if self.end < lines.len() => {
+                let end = lsp::Position::new(self.end as u32, 0);
+                let new_text = self.lines.iter().map(|line| format!("{line}\n")).collect();
+                (lsp::Range::new(start, end), new_text)
+            }
+            // INFO: appended after the last line, which has no newline to keep
+            true if self.start == lines.len() => {
+                let new_text = self.lines.iter().map(|line| format!("\n{line}")).collect();
+                (lsp::Range::new(eof, eof), new_text)
+            }
+            // INFO: the last line removed with nothing to replace it also remove the newline before it
+            true if self.lines.is_empty() && self.start > 0 => {
+                let previous = lines[self.start - 1].encode_utf16().count() as u32;
+                let start = lsp::Position::new(self.start as u32 - 1, previous);
+                (lsp::Range::new(start, eof), String::new())
+            }
+            true => (lsp::Range::new(start, eof), self.lines.join("\n")),
+        };
+        lsp::TextEdit { range, new_text }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = "/mirror";
+    const DOCUMENT: &str = "/mirror/src/app.py";
+
+    fn edit(range: ((u32, u32), (u32, u32)), new_text: &str) -> lsp::TextEdit {
+        let ((line, character), (end_line, end_character)) = range;
+        lsp::TextEdit {
+            range: lsp::Range::new(
+                lsp::Position::new(line, character),
+                lsp::Position::new(end_line, end_character),
+            ),
+            new_text: new_text.into(),
+        }
+    }
+
+    #[test]
+    fn multiple_hunks() {
+        let text = "import os\nimport sys\n\n\ndef f( x ):\n    return x\n\n\n\nprint(f(1))\n";
+        let diff = "\
+--- a/src/app.py
++++ b/src/app.py
+@@ -1,3 +1,2 @@
+-import os
+ import sys
+ 
+@@ -4,6 +3,5 @@
+ 
+-def f( x ):
++def f(x):
+     return x
+ 
+ 
+-
+ print(f(1))
+";
+        assert_eq!(
+            text_edits(diff, Path::new(DOCUMENT), text, Path::new(ROOT)).unwrap(),
+            [
+                edit(((0, 0), (1, 0)), ""),
+                edit(((4, 0), (5, 0)), "def f(x):\n"),
+                edit(((8, 0), (9, 0)), ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_newline_at_end_of_file() {
+        let text = "a\nb";
+        let diff = "\
+--- src/app.py
++++ src/app.py
+@@ -1,2 +1,2 @@
+ a
+-b
+\\ No newline at end of file
++c
+\\ No newline at end of file
+";
+        let edits = text_edits(diff, Path::new(DOCUMENT), text, Path::new(ROOT)).unwrap();
+        assert_eq!(edits, [edit(((1, 0), (1, 1)), "c")]);
+
+        let diff =
+            "--- src/app.py\n+++ src/app.py\n@@ -2 +2 @@\n-b\n\\ No newline at end of file\n+b\n";
+        let edits = text_edits(diff, Path::new(DOCUMENT), text, Path::new(ROOT)).unwrap();
+        assert_eq!(edits, [edit(((1, 0), (1, 1)), "b\n")]);
+    }
+
+    #[test]
+    fn other_files_are_filtered_out() {
+        let diff = "\
+--- src/other.py
++++ src/other.py
+@@ -1 +1 @@
+-x
++y
+--- /mirror/src/app.py
++++ /mirror/src/app.py
+@@ -1 +1 @@
+-a
++b
+";
+        let edits = text_edits(diff, Path::new(DOCUMENT), "a\n", Path::new(ROOT)).unwrap();
+        assert_eq!(edits, [edit(((0, 0), (1, 0)), "b\n")]);
+
+        let err = text_edits(diff, Path::new(DOCUMENT), "c\n", Path::new(ROOT)).unwrap_err();
+        assert_eq!(
+            err,
+            "/mirror/src/app.py:1: the diff doesn't match the document"
+        );
+    }
+
+    #[test]
+    fn rustfmt_check() {
+        let diff = "Diff in /mirror/src/app.py at line 1:\n-fn f(){}\n+fn f() {}\n";
+        let edits = text_edits(diff, Path::new(DOCUMENT), "fn f(){}\n", Path::new(ROOT)).unwrap();
+        assert_eq!(edits, [edit(((0, 0), (1, 0)), "fn f() {}\n")]);
+    }
+
+    #[test]
+    fn workspace_edit_in_root() {
+        let root = std::env::temp_dir().join(format!("lspcat-diff-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/util.py"), "x = 1\n").unwrap();
+        let document = root.join("src/app.py");
+        let diff = "\
+--- a/src/util.py
++++ b/src/util.py
+@@ -1 +1 @@
+-x = 1
++x = 2
+--- a/src/app.py
++++ b/src/app.py
+@@ -1 +1 @@
+-import util
++from util import x
+";
+        let edited = workspace_edit(diff, &document, "import util\n", &root);
+        let missing = workspace_edit(diff, &document, "import util\n", Path::new("/nonexistent"));
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let changes = edited.unwrap().changes.unwrap();
+        let uri = |path: &Path| lsp::Url::from_file_path(path).unwrap();
+        assert_eq!(
+            changes[&uri(&root.join("src/util.py"))],
+            [edit(((0, 0), (1, 0)), "x = 2\n")]
+        );
+        assert_eq!(
+            changes[&uri(&document)],
+            [edit(((0, 0), (1, 0)), "from util import x\n")]
+        );
+        assert_eq!(missing.unwrap_err(), "b/src/util.py: no such file");
+    }
+}
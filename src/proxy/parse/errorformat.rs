@@ -0,0 +1,446 @@
+use regex::Regex;
+use serde_json::{json, Value};
+use std::path::Path;
+use tower_lsp::lsp_types as lsp;
+
+/// Vim `errorformat`, a list of line patterns (see `:help errorformat`) which produce `lsp::Diagnostic`s.
+///
+/// Supported: `%f %l %c %v %e %k %t %n %m %r %p %s %o`, `%*[...]`, `%*\X`, `%. %# %[ %\ %%`,
+/// the prefixes `%A %E %W %I %N %C %Z %G` with `%-` (ignore) and `%+` (whole line as message).
+/// The directory stack (`%D %X %O %P %Q`) is not supported, such lines are ignored.
+pub struct ErrorFormat {
+    formats: Vec<Format>,
+}
+
+/// Known `errorformat` of common tools.
+pub enum Preset {
+    Gcc,     // also Clang
+    Rustc,   // `--error-format=short`
+    Python,  // tracebacks, whose innermost frame is the location of the exception
+    Generic, // `file:line:col: message`
+}
+
+struct Format {
+    pattern: Regex,
+    kind: Kind,
+    ignore: bool, // `%-`
+    whole: bool,  // `%+`
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Single,
+    Start(Option<char>), // `%A` or `%E %W %I %N` with their type
+    Continue,
+    End,
+    General,
+}
+
+#[derive(Default)]
+struct Entry {
+    file: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+    end_line: Option<u32>,
+    end_column: Option<u32>,
+    kind: Option<char>,
+    code: Option<String>,
+    message: Vec<String>,
+}
+
+impl ErrorFormat {
+    pub fn new<'a>(formats: impl IntoIterator<Item = &'a str>) -> Result<Self, regex::Error> {
+        let formats = formats
+            .into_iter()
+            .map(Format::new)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { formats })
+    }
+
+    pub fn preset(preset: Preset) -> Self {
+        let formats: &[&str] = match preset {
+            Preset::Gcc => &[
+                "%f:%l:%c: fatal %trror: %m",
+                "%f:%l:%c: %trror: %m",
+                "%f:%l:%c: %tarning: %m",
+                "%f:%l:%c: %tote: %m",
+                "%f:%l: %trror: %m",
+                "%f:%l: %tarning: %m",
+                "%f:%l: %tote: %m",
+            ],
+            Preset::Rustc => &[
+                "%f:%l:%c: %trror[%*[^]]]: %m",
+                "%f:%l:%c: %trror: %m",
+                "%f:%l:%c: %tarning: %m",
+            ],
+            // INFO: a `SyntaxError` frame has no `, in <function>` after its line
+            Preset::Python => &[
+                "%A  File \"%f\", line %l%.%#",
+                "%-C    %.%#",
+                "%+Z%*[A-Za-z0-9_.]: %m",
+            ],
+            Preset::Generic => &["%f:%l:%c: %m", "%f:%l: %m"],
+        };
+        Self::new(formats.iter().copied()).expect("presets are valid")
+    }
+
+    /// Diagnostics of the `document` in the output of a tool, whose relative paths are in `root`.
+    pub fn parse(&self, stdout: &str, document: &Path, root: &Path) -> Value {
+        let mut diagnostics = vec![];
+        let mut open: Option<Entry> = None;
+        let mut emit = |entry: Entry| {
+            if let Some(diagnostic) = entry.diagnostic(document, root) {
+                diagnostics.push(diagnostic);
+            }
+        };
+
+        for line in stdout.lines() {
+            let Some((format, captures)) = (self.formats.iter())
+                .find_map(|format| Some((format, format.pattern.captures(line)?)))
+            else {
+                continue;
+            };
+            let mut entry = match format.kind {
+                Kind::General => continue,
+                Kind::Single | Kind::Start(_) => {
+                    if let Some(entry) = open.take() {
+                        emit(entry);
+                    }
+                    let kind = match format.kind {
+                        Kind::Start(kind) => kind,
+                        _ => None,
+                    };
+                    Entry {
+                        kind,
+                        ..Entry::default()
+                    }
+                }
+                Kind::Continue | Kind::End => match open.take() {
+                    Some(entry) => entry,
+                    None => continue, // INFO: a continuation without a start
+                },
+            };
+            if format.whole {
+                entry.message.push(line.to_owned());
+            } else if !format.ignore {
+                entry.merge(&captures);
+            }
+            match format.kind {
+                Kind::Single | Kind::End => emit(entry),
+                _ => open = Some(entry),
+            }
+        }
+        if let Some(entry) = open {
+            emit(entry);
+        }
+        Value::Array(diagnostics)
+    }
+}
+
+impl Format {
+    fn new(format: &str) -> Result<Self, regex::Error> {
+        let (mut ignore, mut whole, mut kind) = (false, false, Kind::Single);
+        let mut rest = format;
+        if let Some(prefix) = rest.strip_prefix('%') {
+            let mut chars = prefix.chars();
+            let mut next = chars.next();
+            if let Some(sign @ ('-' | '+')) = next {
+                (ignore, whole) = (sign == '-', sign == '+');
+                next = chars.next();
+            }
+            let prefix = match next {
+                Some('A') => Some(Kind::Start(None)),
+                Some(kind @ ('E' | 'W' | 'I' | 'N')) => Some(Kind::Start(Some(kind))),
+                Some('C') => Some(Kind::Continue),
+                Some('Z') => Some(Kind::End),
+                Some('G' | 'D' | 'X' | 'O' | 'P' | 'Q') => Some(Kind::General),
+                _ => None,
+            };
+            if let Some(prefix) = prefix {
+                kind = prefix;
+                rest = chars.as_str();
+            } else {
+                (ignore, whole) = (false, false);
+            }
+        }
+        let pattern = Regex::new(&format!("^{}$", translate(rest)))?;
+        Ok(Self {
+            pattern,
+            kind,
+            ignore,
+            whole,
+        })
+    }
+}
+
+/// Translate an `errorformat` pattern into a regex.
+fn translate(format: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(char) = chars.next() {
+        if char != '%' {
+            regex.push_str(&regex::escape(&char.to_string()));
+            continue;
+        }
+        match chars.next() {
+            Some('f') => regex.push_str(r"(?P<f>(?:[A-Za-z]:)?.+?)"),
+            Some('o') => regex.push_str(r"(?P<o>.+?)"),
+            Some(key @ ('l' | 'c' | 'v' | 'e' | 'k' | 'n')) => {
+                regex.push_str(&format!(r"(?P<{key}>\d+)"))
+            }
+            Some('t') => regex.push_str(r"(?P<t>.)"),
+            Some('m') => regex.push_str(r"(?P<m>.+)"),
+            Some('r') => regex.push_str(r"(?P<r>.*)"),
+            Some('s') => regex.push_str(r"(?P<s>.+)"),
+            Some('p') => regex.push_str(r"(?P<p>[-\t .]*)"),
+            Some('*') => match chars.next() {
+                Some('[') => {
+                    regex.push_str(&class(&mut chars));
+                    regex.push('+');
+                }
+                Some('\\') => {
+                    regex.push_str(&vim_escape(chars.next()));
+                    regex.push('+');
+                }
+                _ => {}
+            },
+            Some('[') => regex.push_str(&class(&mut chars)),
+            Some('\\') => regex.push_str(&vim_escape(chars.next())),
+            Some('.') => regex.push('.'),
+            Some('#') => regex.push('*'),
+            Some(char) => regex.push_str(&regex::escape(&char.to_string())), // e.g. `%%`
+            None => regex.push('%'),
+        }
+    }
+    regex
+}
+
+/// Copy a character range until its closing `]`, which is literal right after `[` or `[^`.
+fn class(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut class = String::from("[");
+    if chars.peek() == Some(&'^') {
+        class.push(chars.next().unwrap_or('^'));
+    }
+    if chars.peek() == Some(&']') {
+        chars.next();
+        class.push_str(r"\]");
+    }
+    for char in chars.by_ref() {
+        match char {
+            ']' => break,
+            '[' => class.push_str(r"\["),
+            char => class.push(char),
+        }
+    }
+    class.push(']');
+    class
+}
+
+/// Translate a Vim regex atom after a backslash, e.g. `%\d%\+`.
+fn vim_escape(char: Option<char>) -> String {
+    match char {
+        Some('+') => "+".into(),
+        Some('=' | '?') => "?".into(),
+        Some('(') => "(?:".into(),
+        Some(char @ (')' | '|')) => char.into(),
+        Some('a') => "[A-Za-z]".into(),
+        Some(char @ ('d' | 'D' | 's' | 'S' | 'w' | 'W')) => format!(r"\{char}"),
+        Some(char) => regex::escape(&char.to_string()),
+        None => r"\\".into(),
+    }
+}
+
+impl Entry {
+    fn merge(&mut self, captures: &regex::Captures) {
+        let number = |key| captures.name(key)?.as_str().parse::<u32>().ok();
+        let text = |key| Some(captures.name(key)?.as_str().to_owned());
+        self.file = text("f").or(self.file.take());
+        self.line = number("l").or(self.line);
+        self.column = (number("c").or(number("v")))
+            .or_else(|| Some(captures.name("p")?.as_str().len() as u32 + 1))
+            .or(self.column);
+        self.end_line = number("e").or(self.end_line);
+        self.end_column = number("k").or(self.end_column);
+        self.kind = (text("t").and_then(|kind| kind.chars().next())).or(self.kind);
+        self.code = text("n").or(self.code.take());
+        if let Some(message) = text("m").or_else(|| text("r")) {
+            self.message.push(message);
+        }
+    }
+
+    fn diagnostic(self, document: &Path, root: &Path) -> Option<Value> {
+        if (self.file.as_ref()).is_some_and(|file| !super::is_document(file, document, root)) {
+            return None;
+        }
+        let message = self.message.join("\n").trim().to_owned();
+        if message.is_empty() {
+            return None;
+        }
+
+        let line = self.line.unwrap_or(1).saturating_sub(1);
+        let range = match self.column {
+            Some(column) => {
+                let start = lsp::Position::new(line, column.saturating_sub(1));
+                let end = lsp::Position::new(
+                    self.end_line.map_or(line, |line| line.saturating_sub(1)),
+                    self.end_column
+                        .map_or(start.character, |column| column.saturating_sub(1)),
+                );
+                lsp::Range::new(start, end)
+            }
+            None => lsp::Range::new(lsp::Position::new(line, 0), lsp::Position::new(line + 1, 0)),
+        };
+        let severity = match self.kind.map(|kind| kind.to_ascii_uppercase()) {
+            Some('W') => lsp::DiagnosticSeverity::WARNING,
+            Some('I') => lsp::DiagnosticSeverity::INFORMATION,
+            Some('N' | 'H') => lsp::DiagnosticSeverity::HINT,
+            _ => lsp::DiagnosticSeverity::ERROR, // INFO: like Vim, an entry is an error by default
+        };
+        Some(json!(lsp::Diagnostic {
+            range,
+            severity: Some(severity),
+            code: self.code.map(lsp::NumberOrString::String),
+            message,
+            ..lsp::Diagnostic::default()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = "/mirror";
+    const DOCUMENT: &str = "/mirror/src/main.c";
+
+    /// `(line, character, severity, message)` of each diagnostic
+    fn parse(errorformat: &ErrorFormat, stdout: &str) -> Vec<(u32, u32, u64, String)> {
+        let Value::Array(diagnostics) =
+            errorformat.parse(stdout, Path::new(DOCUMENT), Path::new(ROOT))
+        else {
+            unreachable!()
+        };
+        (diagnostics.iter())
+            .map(|diagnostic| {
+                let start = &diagnostic["range"]["start"];
+                (
+                    start["line"].as_u64().unwrap() as u32,
+                    start["character"].as_u64().unwrap() as u32,
+                    diagnostic["severity"].as_u64().unwrap(),
+                    diagnostic["message"].as_str().unwrap().to_owned(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_line() {
+        let errorformat = ErrorFormat::new(["%f:%l:%c:%m"]).unwrap();
+        let stdout = "src/main.c:3:5:expected ';'\nother.c:1:1:elsewhere\nnoise\n/mirror/src/main.c:1:1:absolute\nmain.c:2:2:another main.c\n./lib/../src/main.c:4:1:normalized\n";
+        assert_eq!(
+            parse(&errorformat, stdout),
+            [
+                (2, 4, 1, "expected ';'".into()),
+                (0, 0, 1, "absolute".into()),
+                (3, 0, 1, "normalized".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn multi_line() {
+        let errorformat = ErrorFormat::new(["%Eerror in %f:%l", "%C  %m", "%Z--"]).unwrap();
+        let stdout = "error in src/main.c:7\n  first\n  second\n--\n  orphan\nerror in src/main.c:9\n  unterminated\n";
+        let diagnostics = errorformat.parse(stdout, Path::new(DOCUMENT), Path::new(ROOT));
+        assert_eq!(diagnostics[0]["message"], "first\nsecond");
+        assert_eq!(
+            diagnostics[0]["range"],
+            json!({"start": {"line": 6, "character": 0}, "end": {"line": 7, "character": 0}})
+        );
+        assert_eq!(diagnostics[1]["message"], "unterminated");
+        assert_eq!(diagnostics.as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn ignored_general_lines() {
+        let errorformat =
+            ErrorFormat::new(["%-GIn file included from %.%#", "%f:%l:%c: %m"]).unwrap();
+        let stdout = "In file included from src/main.c:1:10: here\nsrc/main.c:2:3: real\n";
+        assert_eq!(parse(&errorformat, stdout), [(1, 2, 1, "real".into())]);
+    }
+
+    #[test]
+    fn type_and_number() {
+        let errorformat = ErrorFormat::new(["%f:%l:%c: (%t%n) %m"]).unwrap();
+        let diagnostics = errorformat.parse(
+            "src/main.c:1:7: (W211) unused variable 'a'",
+            Path::new(DOCUMENT),
+            Path::new(ROOT),
+        );
+        assert_eq!(diagnostics[0]["severity"], 2);
+        assert_eq!(diagnostics[0]["code"], "211");
+        assert_eq!(diagnostics[0]["message"], "unused variable 'a'");
+
+        assert!(ErrorFormat::new(["%f:%\\(%m"]).is_err());
+    }
+
+    #[test]
+    fn presets() {
+        let gcc = ErrorFormat::preset(Preset::Gcc);
+        let stdout = "src/main.c:4:1: fatal error: x.h: No such file or directory\n\
+                      src/main.c:3:5: warning: unused variable 'y' [-Wunused-variable]\n\
+                      src/main.c:2: note: declared here\n";
+        assert_eq!(
+            parse(&gcc, stdout),
+            [
+                (3, 0, 1, "x.h: No such file or directory".into()),
+                (2, 4, 2, "unused variable 'y' [-Wunused-variable]".into()),
+                (1, 0, 4, "declared here".into()),
+            ]
+        );
+
+        let rustc = ErrorFormat::preset(Preset::Rustc);
+        let stdout = "src/main.c:1:5: error[E0425]: cannot find value `x` in this scope\n";
+        assert_eq!(
+            parse(&rustc, stdout),
+            [(0, 4, 1, "cannot find value `x` in this scope".into())]
+        );
+
+        let python = ErrorFormat::preset(Preset::Python);
+        let stdout = r#"Traceback (most recent call last):
+  File "/mirror/src/main.c", line 3, in <module>
+    main()
+  File "/mirror/src/main.c", line 2, in main
+    raise ValueError("bad")
+ValueError: bad
+Traceback (most recent call last):
+  File "<string>", line 1, in <module>
+  File "src/main.c", line 7
+    foo(
+       ^
+SyntaxError: '(' was never closed
+"#;
+        assert_eq!(
+            parse(&python, stdout),
+            [
+                (1, 0, 1, "ValueError: bad".into()),
+                (6, 0, 1, "SyntaxError: '(' was never closed".into()),
+            ]
+        );
+
+        let generic = ErrorFormat::preset(Preset::Generic);
+        let stdout = "src/main.c:2:1: [warning] too many blank lines (empty-lines)\nsrc/main.c:3: no column\n";
+        assert_eq!(
+            parse(&generic, stdout),
+            [
+                (
+                    1,
+                    0,
+                    1,
+                    "[warning] too many blank lines (empty-lines)".into()
+                ),
+                (2, 0, 1, "no column".into()),
+            ]
+        );
+    }
+}
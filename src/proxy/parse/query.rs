@@ -0,0 +1,823 @@
+use serde_json::{Map, Value};
+use tower_lsp::lsp_types as lsp;
+
+/// A jq-like query which select and reshape the JSON printed by a tool, e.g.
+/// `.items[] | select(.type != "module") | {label: .name, detail: "\(.type)", kind: (.type | completion_kind)}`
+///
+/// Supported: `.`, `.key`, `."key"`, `.[N]`, `.[]`, `?`, `|`, `,`, `//`, `+`, `-`, `==`, `!=`,
+/// literals, `"\(interpolation)"`, `{key: value, key}`, `[collect]`, `(group)`,
+/// `select(f)`, `map(f)`, `not`, `length`, `tostring`, `tonumber`, `ascii_downcase`, `ascii_upcase`
+/// and the enum mappings `completion_kind`, `symbol_kind`, `severity` from names (e.g. "function", "warning").
+/// Unlike jq, an interpolation or an object value only use the first output of its expression.
+pub struct Query {
+    expr: Expr,
+}
+
+enum Expr {
+    Identity,
+    Literal(Value),
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Iterate(Box<Expr>),
+    Try(Box<Expr>),
+    Pipe(Box<Expr>, Box<Expr>),
+    Comma(Box<Expr>, Box<Expr>),
+    Alternative(Box<Expr>, Box<Expr>),
+    Binary(Box<Expr>, Op, Box<Expr>),
+    Text(Vec<Part>),
+    Object(Vec<(String, Expr)>),
+    Array(Option<Box<Expr>>),
+    Select(Box<Expr>),
+    Builtin(Builtin),
+}
+
+enum Part {
+    Text(String),
+    Expr(Expr),
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Add,
+    Sub,
+    Eq,
+    Ne,
+}
+
+#[derive(Clone, Copy)]
+enum Builtin {
+    Not,
+    Length,
+    ToString,
+    ToNumber,
+    Downcase,
+    Upcase,
+    CompletionKind,
+    SymbolKind,
+    Severity,
+}
+
+impl Query {
+    pub fn new(source: &str) -> Result<Self, String> {
+        let mut reader = Reader { source, at: 0 };
+        let expr = reader.pipe()?;
+        reader.skip_whitespace();
+        match reader.at == source.len() {
+            true => Ok(Self { expr }),
+            false => Err(reader.error("unexpected character")),
+        }
+    }
+
+    /// Every output of the query on each JSON value of the `stdout` stream.
+    pub fn run(&self, stdout: &str) -> Result<Vec<Value>, String> {
+        let mut outputs = vec![];
+        for input in serde_json::Deserializer::from_str(stdout).into_iter::<Value>() {
+            outputs.extend(self.eval(&input.map_err(|err| err.to_string())?)?);
+        }
+        Ok(outputs)
+    }
+
+    pub fn eval(&self, input: &Value) -> Result<Vec<Value>, String> {
+        self.expr.eval(input)
+    }
+}
+
+impl Expr {
+    fn eval(&self, input: &Value) -> Result<Vec<Value>, String> {
+        Ok(match self {
+            Expr::Identity => vec![input.clone()],
+            Expr::Literal(value) => vec![value.clone()],
+            Expr::Field(target, key) => (target.eval(input)?.into_iter())
+                .map(|value| index(value, &Value::String(key.clone())))
+                .collect::<Result<_, _>>()?,
+            Expr::Index(target, key) => {
+                let keys = key.eval(input)?;
+                let mut outputs = vec![];
+                for value in target.eval(input)? {
+                    for key in &keys {
+                        outputs.push(index(value.clone(), key)?);
+                    }
+                }
+                outputs
+            }
+            Expr::Iterate(target) => {
+                let mut outputs = vec![];
+                for value in target.eval(input)? {
+                    match value {
+                        Value::Array(items) => outputs.extend(items),
+                        Value::Object(fields) => {
+                            outputs.extend(fields.into_iter().map(|(_, value)| value))
+                        }
+                        value => return Err(format!("cannot iterate over {}", kind(&value))),
+                    }
+                }
+                outputs
+            }
+            Expr::Try(expr) => expr.eval(input).unwrap_or_default(),
+            Expr::Pipe(left, right) => {
+                let mut outputs = vec![];
+                for value in left.eval(input)? {
+                    outputs.extend(right.eval(&value)?);
+                }
+                outputs
+            }
+            Expr::Comma(left, right) => [left.eval(input)?, right.eval(input)?].concat(),
+            Expr::Alternative(left, right) => {
+                let outputs: Vec<_> = (left.eval(input).unwrap_or_default().into_iter())
+                    .filter(truthy)
+                    .collect();
+                match outputs.is_empty() {
+                    true => right.eval(input)?,
+                    false => outputs,
+                }
+            }
+            Expr::Binary(left, op, right) => {
+                let lefts = left.eval(input)?;
+                let mut outputs = vec![];
+                for right in right.eval(input)? {
+                    for left in &lefts {
+                        outputs.push(binary(left, *op, &right)?);
+                    }
+                }
+                outputs
+            }
+            Expr::Text(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    match part {
+                        Part::Text(literal) => text.push_str(literal),
+                        Part::Expr(expr) => match expr.eval(input)?.into_iter().next() {
+                            Some(Value::String(value)) => text.push_str(&value),
+                            Some(value) => text.push_str(&value.to_string()),
+                            None => {}
+                        },
+                    }
+                }
+                vec![Value::String(text)]
+            }
+            Expr::Object(fields) => {
+                let mut object = Map::new();
+                for (key, value) in fields {
+                    let value = value.eval(input)?.into_iter().next().unwrap_or_default();
+                    object.insert(key.clone(), value);
+                }
+                vec![Value::Object(object)]
+            }
+            Expr::Array(items) => vec![Value::Array(match items {
+                Some(items) => items.eval(input)?,
+                None => vec![],
+            })],
+            Expr::Select(condition) => match condition.eval(input)?.iter().any(truthy) {
+                true => vec![input.clone()],
+                false => vec![],
+            },
+            Expr::Builtin(builtin) => vec![builtin.call(input)?],
+        })
+    }
+}
+
+impl Builtin {
+    fn new(name: &str) -> Option<Self> {
+        Some(match name {
+            "not" => Builtin::Not,
+            "length" => Builtin::Length,
+            "tostring" => Builtin::ToString,
+            "tonumber" => Builtin::ToNumber,
+            "ascii_downcase" => Builtin::Downcase,
+            "ascii_upcase" => Builtin::Upcase,
+            "completion_kind" => Builtin::CompletionKind,
+            "symbol_kind" => Builtin::SymbolKind,
+            "severity" => Builtin::Severity,
+            _ => return None,
+        })
+    }
+
+    fn call(self, input: &Value) -> Result<Value, String> {
+        let text = || match input {
+            Value::String(text) => Ok(text.as_str()),
+            value => Err(format!("{} is not a string", kind(value))),
+        };
+        // INFO: a number is already the value of the enum
+        let name = |map: fn(&str) -> Option<Value>| match input {
+            Value::Number(_) => Ok(input.clone()),
+            _ => Ok(map(text()?).unwrap_or_default()),
+        };
+        match self {
+            Builtin::Not => Ok((!truthy(input)).into()),
+            Builtin::Length => Ok(match input {
+                Value::Null => 0.into(),
+                Value::String(text) => text.chars().count().into(),
+                Value::Array(items) => items.len().into(),
+                Value::Object(fields) => fields.len().into(),
+                value => return Err(format!("{} has no length", kind(value))),
+            }),
+            Builtin::ToString => Ok(match input {
+                Value::String(_) => input.clone(),
+                value => value.to_string().into(),
+            }),
+            Builtin::ToNumber => match input {
+                Value::Number(_) => Ok(input.clone()),
+                _ => (text()?.trim().parse::<f64>().ok())
+                    .and_then(number)
+                    .ok_or_else(|| format!("cannot parse {input} as a number")),
+            },
+            Builtin::Downcase => Ok(text()?.to_ascii_lowercase().into()),
+            Builtin::Upcase => Ok(text()?.to_ascii_uppercase().into()),
+            Builtin::CompletionKind => {
+                name(|name| serde_json::to_value(completion_kind(name)?).ok())
+            }
+            Builtin::SymbolKind => name(|name| serde_json::to_value(symbol_kind(name)?).ok()),
+            Builtin::Severity => name(|name| serde_json::to_value(severity(name)?).ok()),
+        }
+    }
+}
+
+/// A `CompletionItemKind` by its name in the LSP specification or a common alias (e.g. `fn`, `var`), ignoring case.
+pub fn completion_kind(name: &str) -> Option<lsp::CompletionItemKind> {
+    use lsp::CompletionItemKind as Kind;
+    Some(
+        match name
+            .to_ascii_lowercase()
+            .replace(['_', '-', ' '], "")
+            .as_str()
+        {
+            "text" => Kind::TEXT,
+            "method" => Kind::METHOD,
+            "function" | "func" | "fn" | "procedure" | "subroutine" => Kind::FUNCTION,
+            "constructor" | "ctor" => Kind::CONSTRUCTOR,
+            "field" | "member" => Kind::FIELD,
+            "variable" | "var" | "let" | "local" | "parameter" | "param" => Kind::VARIABLE,
+            "class" => Kind::CLASS,
+            "interface" | "trait" | "protocol" => Kind::INTERFACE,
+            "module" | "namespace" | "package" | "mod" => Kind::MODULE,
+            "property" | "prop" | "attribute" => Kind::PROPERTY,
+            "unit" => Kind::UNIT,
+            "value" => Kind::VALUE,
+            "enum" => Kind::ENUM,
+            "keyword" => Kind::KEYWORD,
+            "snippet" => Kind::SNIPPET,
+            "color" => Kind::COLOR,
+            "file" => Kind::FILE,
+            "reference" => Kind::REFERENCE,
+            "folder" | "directory" => Kind::FOLDER,
+            "enummember" | "enumerator" | "variant" => Kind::ENUM_MEMBER,
+            "constant" | "const" | "macro" | "define" => Kind::CONSTANT,
+            "struct" | "record" => Kind::STRUCT,
+            "event" => Kind::EVENT,
+            "operator" => Kind::OPERATOR,
+            "typeparameter" | "generic" | "type" | "typedef" | "alias" => Kind::TYPE_PARAMETER,
+            _ => return None,
+        },
+    )
+}
+
+/// A `SymbolKind` by its name in the LSP specification or a common alias (e.g. `fn`, `var`), ignoring case.
+pub fn symbol_kind(name: &str) -> Option<lsp::SymbolKind> {
+    use lsp::SymbolKind as Kind;
+    Some(
+        match name
+            .to_ascii_lowercase()
+            .replace(['_', '-', ' '], "")
+            .as_str()
+        {
+            "file" => Kind::FILE,
+            "module" | "mod" => Kind::MODULE,
+            "namespace" => Kind::NAMESPACE,
+            "package" => Kind::PACKAGE,
+            "class" => Kind::CLASS,
+            "method" => Kind::METHOD,
+            "property" | "prop" | "attribute" => Kind::PROPERTY,
+            "field" | "member" => Kind::FIELD,
+            "constructor" | "ctor" => Kind::CONSTRUCTOR,
+            "enum" => Kind::ENUM,
+            "interface" | "trait" | "protocol" => Kind::INTERFACE,
+            "function" | "func" | "fn" | "procedure" | "subroutine" => Kind::FUNCTION,
+            "variable" | "var" | "let" | "local" | "parameter" | "param" => Kind::VARIABLE,
+            "constant" | "const" | "macro" | "define" => Kind::CONSTANT,
+            "string" => Kind::STRING,
+            "number" => Kind::NUMBER,
+            "boolean" | "bool" => Kind::BOOLEAN,
+            "array" => Kind::ARRAY,
+            "object" => Kind::OBJECT,
+            "key" => Kind::KEY,
+            "null" => Kind::NULL,
+            "enummember" | "enumerator" | "variant" => Kind::ENUM_MEMBER,
+            "struct" | "record" | "union" => Kind::STRUCT,
+            "event" => Kind::EVENT,
+            "operator" => Kind::OPERATOR,
+            "typeparameter" | "generic" | "type" | "typedef" | "alias" => Kind::TYPE_PARAMETER,
+            _ => return None,
+        },
+    )
+}
+
+/// A `DiagnosticSeverity` by its name or a common alias (e.g. `warn`, `note`), ignoring case.
+pub fn severity(name: &str) -> Option<lsp::DiagnosticSeverity> {
+    use lsp::DiagnosticSeverity as Severity;
+    Some(match name.to_ascii_lowercase().as_str() {
+        "error" | "err" | "fatal" | "critical" => Severity::ERROR,
+        "warning" | "warn" => Severity::WARNING,
+        "information" | "info" | "note" => Severity::INFORMATION,
+        "hint" | "help" | "style" | "suggestion" => Severity::HINT,
+        _ => return None,
+    })
+}
+
+fn index(value: Value, key: &Value) -> Result<Value, String> {
+    match (value, key) {
+        (Value::Null, _) => Ok(Value::Null),
+        (Value::Object(mut fields), Value::String(key)) => {
+            Ok(fields.remove(key).unwrap_or_default())
+        }
+        (Value::Array(items), Value::Number(index)) => {
+            let index = index.as_i64().unwrap_or_default();
+            let index = match index < 0 {
+                true => items.len() as i64 + index,
+                false => index,
+            };
+            Ok((usize::try_from(index).ok())
+                .and_then(|index| items.into_iter().nth(index))
+                .unwrap_or_default())
+        }
+        (value, key) => Err(format!("cannot index {} with {key}", kind(&value))),
+    }
+}
+
+fn binary(left: &Value, op: Op, right: &Value) -> Result<Value, String> {
+    Ok(match (op, left, right) {
+        (Op::Eq, _, _) => (left == right).into(),
+        (Op::Ne, _, _) => (left != right).into(),
+        (Op::Add, Value::Null, value) | (Op::Add, value, Value::Null) => value.clone(),
+        (Op::Add, Value::String(left), Value::String(right)) => format!("{left}{right}").into(),
+        (Op::Add, Value::Array(left), Value::Array(right)) => [&left[..], right].concat().into(),
+        (Op::Add | Op::Sub, Value::Number(left), Value::Number(right)) => {
+            let (left, right) = (
+                left.as_f64().unwrap_or_default(),
+                right.as_f64().unwrap_or_default(),
+            );
+            let result = match op {
+                Op::Add => left + right,
+                _ => left - right,
+            };
+            number(result).unwrap_or_default()
+        }
+        (_, left, right) => {
+            return Err(format!("cannot combine {} and {}", kind(left), kind(right)))
+        }
+    })
+}
+
+/// Integers stay integers, so they deserialize into `u32` fields like `line`.
+fn number(number: f64) -> Option<Value> {
+    match number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+        true => Some((number as i64).into()),
+        false => serde_json::Number::from_f64(number).map(Value::Number),
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Recursive descent, from the lowest precedence `|` to the terms.
+struct Reader<'a> {
+    source: &'a str,
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn pipe(&mut self) -> Result<Expr, String> {
+        let mut expr = self.comma()?;
+        while self.eat("|") {
+            expr = Expr::Pipe(Box::new(expr), Box::new(self.comma()?));
+        }
+        Ok(expr)
+    }
+
+    fn comma(&mut self) -> Result<Expr, String> {
+        let mut expr = self.alternative()?;
+        while self.eat(",") {
+            expr = Expr::Comma(Box::new(expr), Box::new(self.alternative()?));
+        }
+        Ok(expr)
+    }
+
+    fn alternative(&mut self) -> Result<Expr, String> {
+        let mut expr = self.comparison()?;
+        while self.eat("//") {
+            expr = Expr::Alternative(Box::new(expr), Box::new(self.comparison()?));
+        }
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let expr = self.additive()?;
+        let op = match () {
+            _ if self.eat("==") => Op::Eq,
+            _ if self.eat("!=") => Op::Ne,
+            _ => return Ok(expr),
+        };
+        Ok(Expr::Binary(Box::new(expr), op, Box::new(self.additive()?)))
+    }
+
+    fn additive(&mut self) -> Result<Expr, String> {
+        let mut expr = self.postfix()?;
+        loop {
+            let op = match () {
+                _ if self.eat("+") => Op::Add,
+                _ if self.eat("-") => Op::Sub,
+                _ => return Ok(expr),
+            };
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.postfix()?));
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.term()?;
+        loop {
+            expr = if self.eat("?") {
+                Expr::Try(Box::new(expr))
+            } else if self.eat("[") {
+                self.subscript(expr)?
+            } else if self.peek() == Some('.') && !self.rest().starts_with("..") {
+                self.at += 1;
+                match self.rest().starts_with('[') {
+                    true => {
+                        self.at += 1;
+                        self.subscript(expr)?
+                    }
+                    false => Expr::Field(Box::new(expr), self.key()?),
+                }
+            } else {
+                return Ok(expr);
+            };
+        }
+    }
+
+    /// After the `[` of `.[]` or `.[N]`.
+    fn subscript(&mut self, target: Expr) -> Result<Expr, String> {
+        if self.eat("]") {
+            return Ok(Expr::Iterate(Box::new(target)));
+        }
+        let key = self.pipe()?;
+        self.expect("]")?;
+        Ok(Expr::Index(Box::new(target), Box::new(key)))
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        self.skip_whitespace();
+        let Some(char) = self.rest().chars().next() else {
+            return Err(self.error("unexpected end"));
+        };
+        match char {
+            '.' => {
+                self.at += 1;
+                if self
+                    .rest()
+                    .starts_with(|char: char| char == '"' || is_identifier(char))
+                {
+                    Ok(Expr::Field(Box::new(Expr::Identity), self.key()?))
+                } else if self.rest().starts_with('[') {
+                    self.at += 1;
+                    self.subscript(Expr::Identity)
+                } else {
+                    Ok(Expr::Identity)
+                }
+            }
+            '"' => self.string(),
+            '(' => {
+                self.at += 1;
+                let expr = self.pipe()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            '[' => {
+                self.at += 1;
+                if self.eat("]") {
+                    return Ok(Expr::Array(None));
+                }
+                let items = self.pipe()?;
+                self.expect("]")?;
+                Ok(Expr::Array(Some(Box::new(items))))
+            }
+            '{' => self.object(),
+            '-' | '0'..='9' => self.number(),
+            char if is_identifier(char) => self.call(),
+            _ => Err(self.error("unexpected character")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Expr, String> {
+        self.at += 1;
+        let mut fields = vec![];
+        while !self.eat("}") {
+            if !fields.is_empty() {
+                self.expect(",")?;
+            }
+            self.skip_whitespace();
+            let key = self.key()?;
+            // INFO: `{name}` is a shorthand of `{name: .name}`
+            let value = match self.eat(":") {
+                true => self.alternative()?,
+                false => Expr::Field(Box::new(Expr::Identity), key.clone()),
+            };
+            fields.push((key, value));
+        }
+        Ok(Expr::Object(fields))
+    }
+
+    fn call(&mut self) -> Result<Expr, String> {
+        let name = self.identifier();
+        let argument = |reader: &mut Self| -> Result<Expr, String> {
+            reader.expect("(")?;
+            let argument = reader.pipe()?;
+            reader.expect(")")?;
+            Ok(argument)
+        };
+        Ok(match name {
+            "true" => Expr::Literal(true.into()),
+            "false" => Expr::Literal(false.into()),
+            "null" => Expr::Literal(Value::Null),
+            "select" => Expr::Select(Box::new(argument(self)?)),
+            // INFO: `map(f)` is `[.[] | f]`
+            "map" => Expr::Array(Some(Box::new(Expr::Pipe(
+                Box::new(Expr::Iterate(Box::new(Expr::Identity))),
+                Box::new(argument(self)?),
+            )))),
+            name => match Builtin::new(name) {
+                Some(builtin) => Expr::Builtin(builtin),
+                None => return Err(self.error(&format!("unknown function {name}"))),
+            },
+        })
+    }
+
+    /// A field name, bare or quoted.
+    fn key(&mut self) -> Result<String, String> {
+        match self.rest().starts_with('"') {
+            true => match self.string()? {
+                Expr::Text(parts) if parts.iter().all(|part| matches!(part, Part::Text(_))) => {
+                    Ok((parts.into_iter())
+                        .map(|part| match part {
+                            Part::Text(text) => text,
+                            Part::Expr(_) => String::new(),
+                        })
+                        .collect())
+                }
+                _ => Err(self.error("a key can't be interpolated")),
+            },
+            false => match self.identifier() {
+                "" => Err(self.error("expected a key")),
+                key => Ok(key.into()),
+            },
+        }
+    }
+
+    fn identifier(&mut self) -> &'a str {
+        let rest = &self.source[self.at..];
+        let end = (rest.find(|char: char| !is_identifier(char) && !char.is_ascii_digit()))
+            .unwrap_or(rest.len());
+        self.at += end;
+        &rest[..end]
+    }
+
+    fn number(&mut self) -> Result<Expr, String> {
+        let rest = self.rest();
+        let end = (rest.char_indices().skip(1))
+            .find(|(_, char)| !(char.is_ascii_digit() || *char == '.'))
+            .map_or(rest.len(), |(end, _)| end);
+        let number = (rest[..end].parse::<f64>().ok())
+            .and_then(number)
+            .ok_or_else(|| self.error("invalid number"))?;
+        self.at += end;
+        Ok(Expr::Literal(number))
+    }
+
+    /// A string literal with `\(expr)` interpolations.
+    fn string(&mut self) -> Result<Expr, String> {
+        self.at += 1; // INFO: the opening quote
+        let (mut parts, mut text) = (vec![], String::new());
+        loop {
+            let Some(char) = self.rest().chars().next() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.at += char.len_utf8();
+            match char {
+                '"' => break,
+                '\\' => {
+                    let Some(escaped) = self.rest().chars().next() else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.at += escaped.len_utf8();
+                    match escaped {
+                        '(' => {
+                            parts.push(Part::Text(std::mem::take(&mut text)));
+                            parts.push(Part::Expr(self.pipe()?));
+                            self.expect(")")?;
+                        }
+                        'n' => text.push('\n'),
+                        't' => text.push('\t'),
+                        'r' => text.push('\r'),
+                        escaped => text.push(escaped),
+                    }
+                }
+                char => text.push(char),
+            }
+        }
+        parts.push(Part::Text(text));
+        Ok(Expr::Text(parts))
+    }
+
+    fn rest(&self) -> &str {
+        &self.source[self.at..]
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let eaten = self.rest().starts_with(token);
+        if eaten {
+            self.at += token.len();
+        }
+        eaten
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        match self.eat(token) {
+            true => Ok(()),
+            false => Err(self.error(&format!("expected `{token}`"))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.at = self.source.len() - self.rest().trim_start().len();
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{message} at {} in `{}`", self.at, self.source)
+    }
+}
+
+fn is_identifier(char: char) -> bool {
+    char.is_ascii_alphabetic() || char == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn eval(source: &str, input: Value) -> Vec<Value> {
+        let query = Query::new(source).unwrap_or_else(|err| panic!("{err}"));
+        query.eval(&input).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    #[test]
+    fn paths() {
+        let input = json!({"a": {"b": [1, 2, 3]}, "with space": true});
+        assert_eq!(eval(".", json!(1)), [json!(1)]);
+        assert_eq!(eval(".a.b", input.clone()), [json!([1, 2, 3])]);
+        assert_eq!(eval(".a.b[0]", input.clone()), [json!(1)]);
+        assert_eq!(eval(".a.b[-1]", input.clone()), [json!(3)]);
+        assert_eq!(eval(".a.b[5]", input.clone()), [Value::Null]);
+        assert_eq!(eval(r#"."with space""#, input.clone()), [json!(true)]);
+        assert_eq!(eval(r#".["a"].b | length"#, input.clone()), [json!(3)]);
+        assert_eq!(eval(".missing.deeper", input), [Value::Null]);
+    }
+
+    #[test]
+    fn iterate() {
+        let input = json!({"items": [{"name": "a"}, {"name": "b"}]});
+        assert_eq!(
+            eval(".items[].name", input.clone()),
+            [json!("a"), json!("b")]
+        );
+        assert_eq!(eval(".items[0][]", input), [json!("a")]);
+        assert_eq!(eval(".[]?", json!(1)), Vec::<Value>::new());
+        assert_eq!(
+            eval(".a, .b", json!({"a": 1, "b": 2})),
+            [json!(1), json!(2)]
+        );
+    }
+
+    #[test]
+    fn map_and_select() {
+        let input = json!([{"n": 1, "ok": true}, {"n": 2, "ok": false}, {"n": 3}]);
+        assert_eq!(eval("map(.n + 1)", input.clone()), [json!([2, 3, 4])]);
+        assert_eq!(eval(".[] | select(.ok) | .n", input.clone()), [json!(1)]);
+        assert_eq!(
+            eval("map(select(.ok | not) | .n)", input.clone()),
+            [json!([2, 3])]
+        );
+        assert_eq!(eval("[.[] | select(.n != 2)] | length", input), [json!(2)]);
+    }
+
+    #[test]
+    fn construct() {
+        let input = json!({"line": 3, "name": "x"});
+        assert_eq!(
+            eval("{line: (.line - 1), name, kind: \"var\"}", input),
+            [json!({"line": 2, "name": "x", "kind": "var"})]
+        );
+        assert_eq!(eval("[]", Value::Null), [json!([])]);
+        assert_eq!(eval("[1, 2] + [3]", Value::Null), [json!([1, 2, 3])]);
+        assert_eq!(eval(".a + 1", json!({})), [json!(1)]);
+        assert_eq!(eval(".x == 1.5", json!({"x": 1.5})), [json!(true)]);
+    }
+
+    #[test]
+    fn interpolation() {
+        let input = json!({"code": 2034, "file": "a.sh", "tags": ["x"]});
+        assert_eq!(eval(r#""SC\(.code)""#, input.clone()), [json!("SC2034")]);
+        assert_eq!(
+            eval(r#""\(.file):\(.code + 1) \(.tags)""#, input),
+            [json!("a.sh:2035 [\"x\"]")]
+        );
+        assert_eq!(
+            eval(r#""tab\tquote\"""#, Value::Null),
+            [json!("tab\tquote\"")]
+        );
+    }
+
+    #[test]
+    fn alternative() {
+        assert_eq!(eval(r#".a // "default""#, json!({})), [json!("default")]);
+        assert_eq!(
+            eval(r#".a // "default""#, json!({"a": false})),
+            [json!("default")]
+        );
+        assert_eq!(eval(r#".a // "default""#, json!({"a": 0})), [json!(0)]);
+        // INFO: errors on the left are alternatives too
+        assert_eq!(eval(".a[] // 1", json!({"a": 2})), [json!(1)]);
+    }
+
+    #[test]
+    fn builtins() {
+        assert_eq!(
+            eval(r#""Function" | completion_kind"#, Value::Null),
+            [json!(3)]
+        );
+        assert_eq!(
+            eval(r#""enum-member" | completion_kind"#, Value::Null),
+            [json!(20)]
+        );
+        assert_eq!(eval(r#""struct" | symbol_kind"#, Value::Null), [json!(23)]);
+        assert_eq!(eval(r#""warning" | severity"#, Value::Null), [json!(2)]);
+        assert_eq!(eval(r#""style" | severity"#, Value::Null), [json!(4)]);
+        assert_eq!(eval("4 | severity", Value::Null), [json!(4)]);
+        assert_eq!(eval(r#""bogus" | severity"#, Value::Null), [Value::Null]);
+        assert_eq!(
+            eval(r#"" 12 " | tonumber | tostring"#, Value::Null),
+            [json!("12")]
+        );
+        assert_eq!(
+            eval(r#""Ab" | ascii_downcase, ascii_upcase"#, Value::Null),
+            [json!("ab"), json!("AB")]
+        );
+        assert_eq!(eval(r#""é" | length"#, Value::Null), [json!(1)]);
+    }
+
+    #[test]
+    fn run_a_stream() {
+        let query = Query::new(".n").unwrap();
+        assert_eq!(
+            query.run("{\"n\": 1}\n{\"n\": 2}").unwrap(),
+            [json!(1), json!(2)]
+        );
+        assert!(query.run("{\"n\": ").is_err());
+    }
+
+    #[test]
+    fn errors() {
+        let error = |source| Query::new(source).err().unwrap();
+        assert_eq!(error(".a ]"), "unexpected character at 3 in `.a ]`");
+        assert_eq!(error("nope"), "unknown function nope at 4 in `nope`");
+        assert_eq!(error("select(.a"), "expected `)` at 9 in `select(.a`");
+        assert_eq!(error("\"abc"), "unterminated string at 4 in `\"abc`");
+        let failure = |source, input| Query::new(source).unwrap().eval(&input).err().unwrap();
+        assert_eq!(failure(".[]", json!(1)), "cannot iterate over number");
+        assert_eq!(
+            failure(".a", json!("text")),
+            "cannot index string with \"a\""
+        );
+        assert_eq!(
+            failure(".a - \"b\"", json!({"a": 1})),
+            "cannot combine number and string"
+        );
+        assert_eq!(failure("ascii_upcase", json!(1)), "number is not a string");
+    }
+}
@@ -0,0 +1,298 @@
+use serde_json::{json, Value};
+use std::{collections::HashMap, path::Path};
+use tower_lsp::lsp_types as lsp;
+
+/// Diagnostics of the `document` in a SARIF 2.1.0 log, where relative uris without a known base are in `root`.
+/// The `fixes` of a result are kept in the `data` of its diagnostic, to be offered as code actions.
+pub fn parse(stdout: &str, document: &Path, root: &Path) -> Result<Value, String> {
+    let log: Value = serde_json::from_str(stdout).map_err(|err| err.to_string())?;
+    let diagnostics = (log["runs"].as_array().into_iter().flatten())
+        .flat_map(|run| {
+            let run = Run {
+                run,
+                document,
+                root,
+            };
+            (run.run["results"].as_array().into_iter().flatten())
+                .filter_map(move |result| run.diagnostic(result))
+        })
+        .collect();
+    Ok(Value::Array(diagnostics))
+}
+
+struct Run<'a> {
+    run: &'a Value,
+    document: &'a Path,
+    root: &'a Path, // e.g. the mirror of the workspace folder, where the tool ran
+}
+
+impl Run<'_> {
+    fn diagnostic(&self, result: &Value) -> Option<Value> {
+        if matches!(result["kind"].as_str(), Some("pass" | "notApplicable")) {
+            return None;
+        }
+        let physical = &result["locations"][0]["physicalLocation"];
+        if self.uri(&physical["artifactLocation"])? != self.document_uri()? {
+            return None;
+        }
+
+        let rule = self.rule(result);
+        let level = (result["level"].as_str())
+            .or_else(|| rule?["defaultConfiguration"]["level"].as_str())
+            .unwrap_or("warning"); // INFO: the default level of SARIF
+        let severity = match level {
+            "error" => lsp::DiagnosticSeverity::ERROR,
+            "warning" => lsp::DiagnosticSeverity::WARNING,
+            "note" => lsp::DiagnosticSeverity::INFORMATION,
+            _ => lsp::DiagnosticSeverity::HINT,
+        };
+        let code = (result["ruleId"].as_str())
+            .or_else(|| rule?["id"].as_str())
+            .map(|id| lsp::NumberOrString::String(id.into()));
+        let code_description = (rule.and_then(|rule| rule["helpUri"].as_str()))
+            .and_then(|href| lsp::Url::parse(href).ok())
+            .map(|href| lsp::CodeDescription { href });
+        let related_information = (result["relatedLocations"].as_array())
+            .map(|locations| {
+                (locations.iter())
+                    .filter_map(|location| {
+                        Some(lsp::DiagnosticRelatedInformation {
+                            location: self.location(&location["physicalLocation"])?,
+                            message: self.message(&location["message"], None),
+                        })
+                    })
+                    .collect()
+            })
+            .filter(|related: &Vec<_>| !related.is_empty());
+        let fixes: Vec<_> = (result["fixes"].as_array().into_iter().flatten())
+            .filter_map(|fix| {
+                let title = match self.message(&fix["description"], None) {
+                    title if title.is_empty() => format!("Fix {}", result["ruleId"].as_str()?),
+                    title => title,
+                };
+                Some(json!({ "title": title, "edit": self.edit(fix)? }))
+            })
+            .collect();
+
+        Some(json!(lsp::Diagnostic {
+            range: range(&physical["region"]).unwrap_or_default(),
+            severity: Some(severity),
+            code,
+            code_description,
+            source: self.run["tool"]["driver"]["name"].as_str().map(Into::into),
+            message: self.message(&result["message"], rule),
+            related_information,
+            data: (!fixes.is_empty()).then(|| json!({ "fixes": fixes })),
+            ..lsp::Diagnostic::default()
+        }))
+    }
+
+    /// The rule of a result, by index (faster) or by id.
+    fn rule(&self, result: &Value) -> Option<&Value> {
+        let rules = self.run["tool"]["driver"]["rules"].as_array()?;
+        let index = (result["ruleIndex"].as_u64()).or_else(|| result["rule"]["index"].as_u64());
+        match index {
+            Some(index) => rules.get(index as usize),
+            None => {
+                let id = (result["ruleId"].as_str()).or_else(|| result["rule"]["id"].as_str())?;
+                rules.iter().find(|rule| rule["id"] == id)
+            }
+        }
+    }
+
+    /// Text of a message, or its `id` looked up in the `rule`, with the `{N}` arguments replaced.
+    fn message(&self, message: &Value, rule: Option<&Value>) -> String {
+        let text = (message["text"].as_str())
+            .or_else(|| message["markdown"].as_str())
+            .or_else(|| {
+                let id = message["id"].as_str()?;
+                rule?["messageStrings"][id]["text"].as_str()
+            })
+            .unwrap_or_default();
+        (message["arguments"].as_array().into_iter().flatten())
+            .enumerate()
+            .fold(text.to_owned(), |text, (index, argument)| {
+                text.replace(
+                    &format!("{{{index}}}"),
+                    argument.as_str().unwrap_or_default(),
+                )
+            })
+    }
+
+    fn location(&self, physical: &Value) -> Option<lsp::Location> {
+        Some(lsp::Location {
+            uri: self.uri(&physical["artifactLocation"])?,
+            range: range(&physical["region"]).unwrap_or_default(),
+        })
+    }
+
+    /// All the `artifactChanges` of a fix in a single edit.
+    fn edit(&self, fix: &Value) -> Option<lsp::WorkspaceEdit> {
+        let mut changes: HashMap<_, Vec<_>> = HashMap::new();
+        for change in fix["artifactChanges"].as_array()? {
+            let uri = self.uri(&change["artifactLocation"])?;
+            for replacement in change["replacements"].as_array()? {
+                changes.entry(uri.clone()).or_default().push(lsp::TextEdit {
+                    range: range(&replacement["deletedRegion"])?,
+                    new_text: (replacement["insertedContent"]["text"].as_str())
+                        .unwrap_or_default()
+                        .into(),
+                });
+            }
+        }
+        Some(lsp::WorkspaceEdit {
+            changes: Some(changes),
+            ..lsp::WorkspaceEdit::default()
+        })
+    }
+
+    /// Resolve an `artifactLocation` through `artifacts` and `originalUriBaseIds`.
+    /// A relative uri without a known base is relative to the root.
+    fn uri(&self, artifact: &Value) -> Option<lsp::Url> {
+        let artifact = match artifact["index"].as_u64() {
+            Some(index) if artifact["uri"].is_null() => {
+                &self.run["artifacts"][index as usize]["location"]
+            }
+            _ => artifact,
+        };
+        let uri = artifact["uri"].as_str()?;
+        if let Ok(uri) = lsp::Url::parse(uri) {
+            return Some(uri);
+        }
+        // INFO: a base such as `%SRCROOT%` is often left for the viewer to define
+        let base = (artifact["uriBaseId"].as_str())
+            .and_then(|base| self.run["originalUriBaseIds"][base]["uri"].as_str());
+        if let Some(base) = base {
+            return lsp::Url::parse(base).ok()?.join(uri).ok();
+        }
+
+        let relative = lsp::Url::parse("file:///")
+            .ok()?
+            .join(uri)
+            .ok()?
+            .to_file_path()
+            .ok()?;
+        let relative = relative.strip_prefix("/").unwrap_or(&relative);
+        lsp::Url::from_file_path(super::normalize(&self.root.join(relative))).ok()
+    }
+
+    fn document_uri(&self) -> Option<lsp::Url> {
+        lsp::Url::from_file_path(self.document).ok()
+    }
+}
+
+/// SARIF regions are 1-based with an exclusive end column, which default to the end of the line.
+fn range(region: &Value) -> Option<lsp::Range> {
+    let line = region["startLine"].as_u64()? as u32;
+    let column = region["startColumn"].as_u64().unwrap_or(1) as u32;
+    let end_line = region["endLine"].as_u64().map_or(line, |line| line as u32);
+    // INFO: a character past the end of the line is clamped to it by the client
+    let end_column = region["endColumn"]
+        .as_u64()
+        .map_or(u32::MAX, |column| column as u32);
+    Some(lsp::Range::new(
+        lsp::Position::new(line.saturating_sub(1), column.saturating_sub(1)),
+        lsp::Position::new(end_line.saturating_sub(1), end_column.saturating_sub(1)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = r#"{
+      "version": "2.1.0",
+      "runs": [{
+        "tool": {"driver": {"name": "semgrep", "rules": [
+          {"id": "py.eval", "helpUri": "https://example.com/py.eval",
+           "defaultConfiguration": {"level": "error"},
+           "messageStrings": {"default": {"text": "eval of {0}"}}}
+        ]}},
+        "originalUriBaseIds": {"CHECKOUT": {"uri": "file:///checkout/"}},
+        "results": [
+          {"ruleId": "py.eval", "ruleIndex": 0,
+           "message": {"id": "default", "arguments": ["input"]},
+           "locations": [{"physicalLocation": {
+             "artifactLocation": {"uri": "src/app.py", "uriBaseId": "%SRCROOT%"},
+             "region": {"startLine": 3, "startColumn": 5, "endColumn": 9}}}],
+           "relatedLocations": [
+             {"message": {"text": "defined here"}, "physicalLocation": {
+               "artifactLocation": {"uri": "src/util.py"}, "region": {"startLine": 1}}},
+             {"message": {"text": "vendored"}, "physicalLocation": {
+               "artifactLocation": {"uri": "lib/x.py", "uriBaseId": "CHECKOUT"}, "region": {"startLine": 2}}}
+           ],
+           "fixes": [{"description": {"text": "Use literal_eval"}, "artifactChanges": [{
+             "artifactLocation": {"uri": "src/app.py"},
+             "replacements": [{"deletedRegion": {"startLine": 3, "startColumn": 5, "endColumn": 9},
+                               "insertedContent": {"text": "ast.literal_eval"}}]}]}]},
+          {"ruleId": "py.eval", "level": "note", "message": {"text": "elsewhere"},
+           "locations": [{"physicalLocation": {
+             "artifactLocation": {"uri": "src/other.py"}, "region": {"startLine": 1}}}]},
+          {"ruleId": "py.eval", "kind": "pass", "message": {"text": "passed"},
+           "locations": [{"physicalLocation": {
+             "artifactLocation": {"uri": "src/app.py"}, "region": {"startLine": 1}}}]}
+        ]
+      }]
+    }"#;
+
+    fn parse(log: &str) -> Vec<Value> {
+        let diagnostics = super::parse(log, Path::new("/mirror/src/app.py"), Path::new("/mirror"));
+        let Value::Array(diagnostics) = diagnostics.unwrap() else {
+            unreachable!()
+        };
+        diagnostics
+    }
+
+    #[test]
+    fn results_of_the_document() {
+        let diagnostics = parse(LOG);
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = &diagnostics[0];
+        assert_eq!(
+            diagnostic["range"],
+            json!({"start": {"line": 2, "character": 4}, "end": {"line": 2, "character": 8}})
+        );
+        assert_eq!(diagnostic["severity"], 1);
+        assert_eq!(diagnostic["code"], "py.eval");
+        assert_eq!(
+            diagnostic["codeDescription"]["href"],
+            "https://example.com/py.eval"
+        );
+        assert_eq!(diagnostic["source"], "semgrep");
+        assert_eq!(diagnostic["message"], "eval of input");
+    }
+
+    #[test]
+    fn relative_uris() {
+        let diagnostics = parse(LOG);
+        let related = &diagnostics[0]["relatedInformation"];
+        assert_eq!(related[0]["location"]["uri"], "file:///mirror/src/util.py");
+        assert_eq!(related[0]["message"], "defined here");
+        assert_eq!(related[1]["location"]["uri"], "file:///checkout/lib/x.py");
+        assert_eq!(
+            related[1]["location"]["range"]["end"],
+            json!({"line": 1, "character": u32::MAX - 1}) // INFO: up to the end of the line
+        );
+    }
+
+    #[test]
+    fn fixes() {
+        let diagnostics = parse(LOG);
+        let fix = &diagnostics[0]["data"]["fixes"][0];
+        assert_eq!(fix["title"], "Use literal_eval");
+        assert_eq!(
+            fix["edit"]["changes"]["file:///mirror/src/app.py"],
+            json!([{
+                "range": {"start": {"line": 2, "character": 4}, "end": {"line": 2, "character": 8}},
+                "newText": "ast.literal_eval",
+            }])
+        );
+    }
+
+    #[test]
+    fn invalid_log() {
+        let err = super::parse("{", Path::new("/mirror/src/app.py"), Path::new("/mirror"));
+        assert!(err.is_err());
+        assert!(parse(r#"{"version": "2.1.0", "runs": []}"#).is_empty());
+    }
+}
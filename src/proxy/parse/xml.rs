@@ -0,0 +1,206 @@
+use super::is_document;
+use roxmltree::{Document, Node};
+use serde_json::{json, Value};
+use std::path::Path;
+use tower_lsp::lsp_types as lsp;
+
+/// Diagnostics of the `document` in a Checkstyle report, which is also emitted by ESLint, PHP_CodeSniffer, etc.
+/// Relative file names are in `root`, where the tool ran.
+/// `<checkstyle><file name=".."><error line=".." column=".." severity=".." message=".." source=".."/>`
+pub fn checkstyle(stdout: &str, document: &Path, root: &Path) -> Result<Value, String> {
+    let xml = Document::parse(stdout).map_err(|err| err.to_string())?;
+    let diagnostics = (xml.descendants())
+        .filter(|node| node.has_tag_name("file"))
+        .filter(|file| {
+            (file.attribute("name")).is_some_and(|name| is_document(name, document, root))
+        })
+        .flat_map(|file| file.children().filter(|node| node.has_tag_name("error")))
+        .map(|error| {
+            let severity = match error.attribute("severity") {
+                Some("warning") => lsp::DiagnosticSeverity::WARNING,
+                Some("info") => lsp::DiagnosticSeverity::INFORMATION,
+                Some("ignore") => lsp::DiagnosticSeverity::HINT,
+                _ => lsp::DiagnosticSeverity::ERROR,
+            };
+            json!(lsp::Diagnostic {
+                range: range(number(error, "line"), number(error, "column")),
+                severity: Some(severity),
+                code: (error.attribute("source"))
+                    .map(|source| lsp::NumberOrString::String(source.into())),
+                message: error.attribute("message").unwrap_or_default().into(),
+                ..lsp::Diagnostic::default()
+            })
+        })
+        .collect();
+    Ok(Value::Array(diagnostics))
+}
+
+/// Diagnostics of the `document` for each `<failure>` or `<error>` of a JUnit report.
+/// A test case is located by its `file` and `line` attributes (or those of its suite),
+/// otherwise by the first `path:line` of the document in the failure output (e.g. a stack trace).
+pub fn junit(stdout: &str, document: &Path, root: &Path) -> Result<Value, String> {
+    let xml = Document::parse(stdout).map_err(|err| err.to_string())?;
+    let diagnostics = (xml.descendants())
+        .filter(|node| node.has_tag_name("testcase"))
+        .flat_map(|testcase| {
+            (testcase.children())
+                .filter(|node| node.has_tag_name("failure") || node.has_tag_name("error"))
+                .filter_map(move |failure| {
+                    let output = failure.text().unwrap_or_default();
+                    let file = (testcase.ancestors())
+                        .find_map(|node| node.attribute("file"))
+                        .filter(|file| is_document(file, document, root));
+                    let line = match file {
+                        Some(_) => {
+                            number(testcase, "line").or_else(|| trace_line(output, document, root))
+                        }
+                        None => Some(trace_line(output, document, root)?),
+                    };
+                    let message = match failure.attribute("message") {
+                        Some(message) => message,
+                        None => output.trim().lines().next().unwrap_or("failed"),
+                    };
+                    Some(json!(lsp::Diagnostic {
+                        range: range(line, None),
+                        severity: Some(lsp::DiagnosticSeverity::ERROR),
+                        code: (failure.attribute("type"))
+                            .map(|kind| lsp::NumberOrString::String(kind.into())),
+                        message: match testcase.attribute("name") {
+                            Some(name) => format!("{name}: {message}"),
+                            None => message.into(),
+                        },
+                        ..lsp::Diagnostic::default()
+                    }))
+                })
+        })
+        .collect();
+    Ok(Value::Array(diagnostics))
+}
+
+/// The line of the first `path:line` in `output` where `path` is the `document`.
+fn trace_line(output: &str, document: &Path, root: &Path) -> Option<u32> {
+    let name = document.file_name()?.to_str()?;
+    (output.match_indices(name)).find_map(|(index, _)| {
+        let prefix = (output[..index]
+            .rsplit(|char: char| char.is_whitespace() || "(\"'".contains(char)))
+        .next()
+        .unwrap_or_default();
+        let rest = &output[index + name.len()..];
+        let digits = rest.strip_prefix(':')?;
+        let end = digits
+            .find(|char: char| !char.is_ascii_digit())
+            .unwrap_or(digits.len());
+        is_document(&format!("{prefix}{name}"), document, root)
+            .then(|| digits[..end].parse().ok())
+            .flatten()
+    })
+}
+
+fn number(node: Node, attribute: &str) -> Option<u32> {
+    node.attribute(attribute)?.trim().parse().ok()
+}
+
+/// From a 1-based `line` and `column`, the whole line when there is no column.
+fn range(line: Option<u32>, column: Option<u32>) -> lsp::Range {
+    let line = line.unwrap_or(1).saturating_sub(1);
+    match column {
+        Some(column) => {
+            let position = lsp::Position::new(line, column.saturating_sub(1));
+            lsp::Range::new(position, position)
+        }
+        None => lsp::Range::new(lsp::Position::new(line, 0), lsp::Position::new(line + 1, 0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = "/mirror";
+    const DOCUMENT: &str = "/mirror/src/app.js";
+
+    /// `(line, character, message)` of each diagnostic
+    fn lines(diagnostics: Result<Value, String>) -> Vec<(u64, u64, String)> {
+        let Value::Array(diagnostics) = diagnostics.unwrap() else {
+            unreachable!()
+        };
+        (diagnostics.iter())
+            .map(|diagnostic| {
+                let start = &diagnostic["range"]["start"];
+                let message = diagnostic["message"].as_str().unwrap_or_default();
+                (
+                    start["line"].as_u64().unwrap(),
+                    start["character"].as_u64().unwrap(),
+                    message.to_owned(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn checkstyle_report() {
+        let report = r#"<?xml version="1.0" encoding="utf-8"?>
+<checkstyle version="4.3">
+  <file name="/mirror/src/app.js">
+    <error line="3" column="7" severity="error" message="'x' is not defined." source="eslint.rules.no-undef" />
+    <error line="5" column="1" severity="warning" message="Unexpected console statement." source="eslint.rules.no-console" />
+  </file>
+  <file name="/mirror/src/other.js">
+    <error line="1" column="1" severity="error" message="elsewhere" source="eslint.rules.semi" />
+  </file>
+  <file name="src/app.js">
+    <error line="8" severity="info" message="relative" />
+  </file>
+  <file name="lib/src/app.js">
+    <error line="9" severity="info" message="another app.js" />
+  </file>
+</checkstyle>"#;
+        let diagnostics = checkstyle(report, Path::new(DOCUMENT), Path::new(ROOT));
+        assert_eq!(
+            lines(diagnostics.clone()),
+            [
+                (2, 6, "'x' is not defined.".into()),
+                (4, 0, "Unexpected console statement.".into()),
+                (7, 0, "relative".into()),
+            ]
+        );
+        let diagnostics = diagnostics.unwrap();
+        assert_eq!(diagnostics[0]["severity"], 1);
+        assert_eq!(diagnostics[0]["code"], "eslint.rules.no-undef");
+        assert_eq!(diagnostics[1]["severity"], 2);
+        assert_eq!(
+            diagnostics[2]["range"]["end"],
+            json!({"line": 8, "character": 0})
+        );
+
+        assert!(checkstyle("<checkstyle>", Path::new(DOCUMENT), Path::new(ROOT)).is_err());
+    }
+
+    #[test]
+    fn junit_report() {
+        let report = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="app" file="src/app.js" tests="5" failures="3">
+    <testcase name="adds" line="10"><failure message="expected 3 got 4" type="AssertionError"/></testcase>
+    <testcase name="passes" line="20"/>
+    <testcase name="throws"><error type="TypeError">TypeError: x is undefined
+    at throws (/mirror/src/app.js:31:5)
+    at /mirror/node_modules/runner.js:1:1</error></testcase>
+  </testsuite>
+  <testsuite name="other" file="src/other.js">
+    <testcase name="elsewhere" line="3"><failure message="not this document"/></testcase>
+    <testcase name="calls"><failure>at call (src/app.js:42)</failure></testcase>
+  </testsuite>
+</testsuites>"#;
+        let diagnostics = junit(report, Path::new(DOCUMENT), Path::new(ROOT));
+        assert_eq!(
+            lines(diagnostics.clone()),
+            [
+                (9, 0, "adds: expected 3 got 4".into()),
+                (30, 0, "throws: TypeError: x is undefined".into()),
+                (41, 0, "calls: at call (src/app.js:42)".into()),
+            ]
+        );
+        assert_eq!(diagnostics.unwrap()[0]["code"], "AssertionError");
+    }
+}
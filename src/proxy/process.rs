@@ -0,0 +1,285 @@
+use smol::{
+    channel, io,
+    process::{Command, Stdio},
+    Timer,
+};
+use std::{process::Output, time::Duration};
+
+const CHUNK: usize = 64 * 1024;
+
+/// Build a `Command` which lead a new process group, so its children can be killed together.
+pub fn command(
+    program: &str,
+    args: impl IntoIterator<Item = impl AsRef<std::ffi::OsStr>>,
+) -> Command {
+    std_command(program, args).into()
+}
+
+/// Like [`command`], but `file` is inherited by the command although it was opened with `CLOEXEC`,
+/// so it doesn't leak into the other commands spawned at the same time.
+#[cfg(unix)]
+pub fn command_inheriting(
+    program: &str,
+    args: impl IntoIterator<Item = impl AsRef<std::ffi::OsStr>>,
+    file: &std::fs::File,
+) -> Command {
+    use std::os::{fd::AsRawFd as _, unix::process::CommandExt as _};
+
+    let fd = file.as_raw_fd();
+    let mut cmd = std_command(program, args);
+    // SAFETY: `fcntl` is async-signal-safe and `fd` stays open until the command is spawned
+    unsafe {
+        cmd.pre_exec(move || match libc::fcntl(fd, libc::F_SETFD, 0) {
+            -1 => Err(std::io::Error::last_os_error()),
+            _ => Ok(()),
+        });
+    }
+    cmd.into()
+}
+
+#[cfg(not(unix))]
+pub fn command_inheriting(
+    program: &str,
+    args: impl IntoIterator<Item = impl AsRef<std::ffi::OsStr>>,
+    _: &std::fs::File,
+) -> Command {
+    command(program, args)
+}
+
+fn std_command(
+    program: &str,
+    args: impl IntoIterator<Item = impl AsRef<std::ffi::OsStr>>,
+) -> std::process::Command {
+    let mut cmd = std::process::Command::new(program);
+    cmd.args(args);
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+    cmd
+}
+
+/// Why a command didn't produce an output.
+#[derive(Clone, Debug)]
+pub enum Failure {
+    Spawn(String),
+    TimedOut,
+    Superseded,
+    Io(io::ErrorKind, String),
+    TooLarge(Output), // with what was read before the command was killed
+}
+
+/// What is kept of the output of a command, and where its lines go while it is still running.
+#[derive(Default)]
+pub struct Capture {
+    pub max_output: Option<usize>, // in bytes of stdout, a command printing more is killed
+    pub lines: Option<channel::Sender<String>>, // each line of stdout as soon as it is read, e.g. to parse NDJSON incrementally
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Spawn(err) | Failure::Io(_, err) => f.write_str(err),
+            Failure::TimedOut => f.write_str("timed out"),
+            Failure::Superseded => f.write_str("superseded by a newer request"),
+            Failure::TooLarge(output) => {
+                write!(f, "output larger than {} bytes", output.stdout.len())
+            }
+        }
+    }
+}
+
+impl From<io::Error> for Failure {
+    fn from(err: io::Error) -> Self {
+        Failure::Io(err.kind(), err.to_string())
+    }
+}
+
+/// Run `cmd` with `input` piped into its stdin (if any) then collect its output as it is printed.
+/// Its whole process group is killed when `timeout` elapse, when its stdout exceed the `capture` limit
+/// or when this future is dropped, which is how tower-lsp handle `$/cancelRequest`.
+pub async fn run(
+    mut cmd: Command,
+    input: Option<&str>,
+    timeout: Option<Duration>,
+    capture: Capture,
+) -> Result<Output, Failure> {
+    use smol::io::AsyncWriteExt as _;
+
+    let mut child = cmd
+        .stdin(match input {
+            Some(_) => Stdio::piped(),
+            None => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| Failure::Spawn(err.to_string()))?;
+    let mut group = Group(Some(child.id()));
+
+    let stdin = child.stdin.take();
+    let write = async move {
+        if let (Some(mut stdin), Some(input)) = (stdin, input) {
+            stdin.write_all(input.as_bytes()).await?;
+        } // INFO: stdin is closed here so the command know the content has ended
+        io::Result::Ok(())
+    };
+    let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+    let output = async {
+        let stdout = async {
+            let stdout = read(stdout, capture.max_output, capture.lines.as_ref(), false).await;
+            if let Ok((_, true)) = stdout {
+                // INFO: otherwise stderr wouldn't end while the command is blocked on a full stdout
+                group.kill();
+                let _ = child.kill();
+            }
+            stdout
+        };
+        let stderr = read(stderr, capture.max_output, None, true);
+        let (written, (stdout, stderr)) =
+            smol::future::zip(write, smol::future::zip(stdout, stderr)).await;
+        if let Err(err) = written {
+            if err.kind() != io::ErrorKind::BrokenPipe {
+                return Err(err.into()); // the command may not read all of it
+            }
+        }
+        let ((stdout, too_large), (stderr, _)) = (stdout?, stderr?);
+        let status = child.status().await?; // INFO: the signal which killed it when `too_large`
+        let output = Output {
+            status,
+            stdout,
+            stderr,
+        };
+        match too_large {
+            true => Err(Failure::TooLarge(output)),
+            false => Ok(output),
+        }
+    };
+    let output = match timeout {
+        Some(timeout) => {
+            let timer = async {
+                Timer::after(timeout).await;
+                Err(Failure::TimedOut)
+            };
+            smol::future::or(output, timer).await
+        }
+        None => output.await,
+    };
+
+    if output.is_ok() {
+        group.0 = None; // INFO: leave alone what the command deliberately left running (e.g. a daemon)
+    }
+    output
+}
+
+/// Read a pipe until its end, sending each line as soon as it is read.
+/// Beyond `max` bytes, return what was read with `true`, or keep reading without keeping it when `drain`.
+async fn read(
+    pipe: Option<impl io::AsyncRead + Unpin>,
+    max: Option<usize>,
+    lines: Option<&channel::Sender<String>>,
+    drain: bool,
+) -> io::Result<(Vec<u8>, bool)> {
+    use smol::io::AsyncReadExt as _;
+
+    let Some(mut pipe) = pipe else {
+        return Ok((vec![], false));
+    };
+    let (mut bytes, mut chunk) = (vec![], vec![0; CHUNK]);
+    let (mut line_start, mut truncated) = (0, false);
+    loop {
+        let read = pipe.read(&mut chunk).await?;
+        let scanned = bytes.len();
+        bytes.extend_from_slice(&chunk[..read]);
+        if let Some(lines) = lines {
+            let ends = (bytes[scanned..].iter().enumerate())
+                .filter(|(_, byte)| **byte == b'\n')
+                .map(|(index, _)| scanned + index);
+            for end in ends.collect::<Vec<_>>() {
+                let line = String::from_utf8_lossy(&bytes[line_start..end]);
+                let _ = lines.send(line.into_owned()).await; // INFO: the receiver may not want more
+                line_start = end + 1;
+            }
+            if read == 0 && line_start < bytes.len() {
+                let line = String::from_utf8_lossy(&bytes[line_start..]);
+                let _ = lines.send(line.into_owned()).await;
+            }
+        }
+        if let Some(max) = max.filter(|max| bytes.len() > *max) {
+            if !drain {
+                return Ok((bytes, true));
+            }
+            bytes.truncate(max);
+            line_start = line_start.min(max);
+            truncated = true;
+        }
+        if read == 0 {
+            return Ok((bytes, truncated));
+        }
+    }
+}
+
+/// Process group led by a spawned command, killed on drop.
+pub struct Group(pub Option<u32>);
+
+impl Group {
+    pub fn kill(&mut self) {
+        if let Some(pid) = self.0.take() {
+            kill_group(pid);
+        }
+    }
+}
+
+impl Drop for Group {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+#[cfg(unix)]
+fn kill_group(pid: u32) {
+    // SAFETY: only send a signal, a negative pid target the process group
+    unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGKILL) };
+}
+
+#[cfg(not(unix))]
+fn kill_group(_: u32) {} // INFO: only the command itself is killed by `kill_on_drop`
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn too_large_is_not_a_success() {
+        let capture = Capture {
+            max_output: Some(16),
+            lines: None,
+        };
+        let output = smol::block_on(run(command("yes", [] as [&str; 0]), None, None, capture));
+        let Err(Failure::TooLarge(output)) = output else {
+            panic!("expected the output to be too large");
+        };
+        assert!(output.stdout.len() > 16);
+        assert!(!output.status.success());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn memfd_is_only_inherited_by_its_command() {
+        let (file, path) = super::super::memfd("hello").unwrap();
+        let cat = |command: Command| {
+            let capture = Capture {
+                max_output: None,
+                lines: None,
+            };
+            smol::block_on(run(command, None, None, capture))
+                .ok()
+                .unwrap()
+        };
+        let inheriting = cat(command_inheriting("cat", [&path], &file));
+        assert_eq!(
+            (inheriting.status.success(), &inheriting.stdout[..]),
+            (true, &b"hello"[..])
+        );
+        assert!(!cat(command("cat", [&path])).status.success());
+    }
+}
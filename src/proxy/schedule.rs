@@ -0,0 +1,170 @@
+use super::process::Failure;
+use dashmap::{mapref::entry::Entry, DashMap};
+use smol::{
+    channel,
+    future::{self, Future},
+    lock::{OnceCell, Semaphore},
+};
+use std::{
+    path::{Path, PathBuf},
+    process::Output,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Concurrency caps of the commands of one language, and of every command.
+#[derive(Clone)]
+pub struct Limits {
+    pub language: Arc<Semaphore>,
+    pub global: Arc<Semaphore>,
+}
+
+/// Commands of one `Exec` which are queued or running.
+#[derive(Default)]
+pub struct InFlight {
+    runs: DashMap<u64, Arc<OnceCell<Result<Output, Failure>>>>, // Map<hash of the request, output>
+    latest: DashMap<PathBuf, (u64, channel::Sender<()>)>, // Map<document, (generation, dropped when superseded)>
+    generation: AtomicU64,
+    supersede: bool,
+}
+
+impl InFlight {
+    /// When `supersede`, only the latest request per document matter (e.g. completion),
+    /// otherwise they may legitimately run side by side (e.g. expanding several hierarchy items).
+    pub fn new(supersede: bool) -> Self {
+        Self {
+            supersede,
+            ..Self::default()
+        }
+    }
+
+    /// Run `spawn` within `limits`, unless an identical request (same `key`) is already in flight,
+    /// in which case its output is shared.
+    /// A newer request on the same `document` supersede this one, which then fail as [`Failure::Superseded`].
+    pub async fn run<Fut>(
+        &self,
+        limits: &Limits,
+        document: &Path,
+        key: u64,
+        spawn: impl FnOnce() -> Fut,
+    ) -> Result<Output, Failure>
+    where
+        Fut: Future<Output = Result<Output, Failure>>,
+    {
+        let (joined, cell) = match self.runs.entry(key) {
+            Entry::Occupied(run) => (true, run.get().clone()),
+            Entry::Vacant(run) => (false, run.insert(Arc::default()).clone()),
+        };
+        let init = || async {
+            let _language = limits.language.acquire().await;
+            let _global = limits.global.acquire().await;
+            spawn().await
+        };
+        let shared = if joined || !self.supersede {
+            Ok(cell.get_or_init(init).await)
+        } else {
+            let generation = self.generation.fetch_add(1, Ordering::Relaxed);
+            let (sender, superseded) = channel::bounded(1);
+            // INFO: dropping the previous sender wake up the request it belongs to
+            self.latest
+                .insert(document.to_path_buf(), (generation, sender));
+            let output = future::or(async { Ok(cell.get_or_init(init).await) }, async {
+                let _ = superseded.recv().await;
+                Err(Failure::Superseded)
+            })
+            .await;
+            self.latest
+                .remove_if(document, |_, (latest, _)| *latest == generation);
+            output
+        };
+        self.runs.remove_if(&key, |_, run| Arc::ptr_eq(run, &cell));
+        shared?.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::atomic::AtomicUsize, time::Duration};
+
+    fn limits() -> Limits {
+        Limits {
+            language: Arc::new(Semaphore::new(2)),
+            global: Arc::new(Semaphore::new(2)),
+        }
+    }
+
+    /// A command printing `stdout` after `delay`, counting its runs.
+    fn command(
+        runs: &AtomicUsize,
+        delay: u64,
+        stdout: &str,
+    ) -> impl Future<Output = Result<Output, Failure>> {
+        runs.fetch_add(1, Ordering::Relaxed);
+        let stdout = stdout.as_bytes().to_vec();
+        async move {
+            smol::Timer::after(Duration::from_millis(delay)).await;
+            Ok(Output {
+                status: Default::default(),
+                stdout,
+                stderr: vec![],
+            })
+        }
+    }
+
+    fn stdout(output: Result<Output, Failure>) -> Result<String, String> {
+        output
+            .map(|output| String::from_utf8(output.stdout).unwrap())
+            .map_err(|failure| format!("{failure:?}"))
+    }
+
+    #[test]
+    fn coalesce_identical_requests() {
+        let (in_flight, limits, runs) = (InFlight::new(true), limits(), &AtomicUsize::new(0));
+        let document = Path::new("/a.py");
+        let run = |stdout| in_flight.run(&limits, document, 1, move || command(runs, 20, stdout));
+        let (first, second) = smol::block_on(future::zip(run("first"), run("second")));
+        assert_eq!(
+            (stdout(first), stdout(second)),
+            (Ok("first".into()), Ok("first".into()))
+        );
+        assert_eq!(runs.load(Ordering::Relaxed), 1);
+
+        // INFO: once done, the same request run again
+        let third = smol::block_on(run("third"));
+        assert_eq!(stdout(third), Ok("third".into()));
+        assert_eq!(runs.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn supersede_older_requests() {
+        let (limits, runs) = (limits(), &AtomicUsize::new(0));
+        let document = Path::new("/a.py");
+        for supersede in [true, false] {
+            let in_flight = InFlight::new(supersede);
+            let run = |key, delay, stdout| {
+                in_flight.run(&limits, document, key, move || command(runs, delay, stdout))
+            };
+            let (older, newer) =
+                smol::block_on(future::zip(run(1, 50, "older"), run(2, 10, "newer")));
+            let older = stdout(older);
+            match supersede {
+                true => assert_eq!(older, Err("Superseded".into())),
+                false => assert_eq!(older, Ok("older".into())),
+            }
+            assert_eq!(stdout(newer), Ok("newer".into()));
+
+            // INFO: other documents are independent
+            let other = in_flight.run(&limits, Path::new("/b.py"), 3, || {
+                command(runs, 10, "other")
+            });
+            let (newest, other) = smol::block_on(future::zip(run(4, 10, "newest"), other));
+            assert_eq!(
+                (stdout(newest), stdout(other)),
+                (Ok("newest".into()), Ok("other".into()))
+            );
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use super::{markdown, PassThrough, Proxy};
+use crate::Content;
+use serde::Deserialize;
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types as lsp;
+
+/// Signatures of the call under the cursor, with their documentation normalized into Markdown.
+pub struct SignatureHelp {
+    pub proxy: PassThrough,
+    pub trigger_characters: Option<Vec<String>>, // e.g. "("
+    pub retrigger_characters: Option<Vec<String>>, // e.g. ","
+    pub max_documentation: Option<usize>,        // in bytes, of each signature or parameter
+}
+
+/// Either an `lsp::SignatureHelp` or the plain text of `Parser::Text`, the signature then its documentation.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Printed {
+    SignatureHelp(lsp::SignatureHelp),
+    Text(String),
+}
+
+impl Proxy for SignatureHelp {
+    type Params = lsp::SignatureHelpParams;
+    type Response = lsp::SignatureHelp;
+
+    async fn proxy_response(
+        &self,
+        params: Self::Params,
+        content: &Content,
+        client: Option<&lsp::ClientCapabilities>,
+    ) -> Result<Option<Self::Response>> {
+        let position = params.text_document_position_params.position;
+        let (line, character) = (position.line.to_string(), position.character.to_string());
+        let printed: Option<Printed> = self
+            .proxy
+            .exec(content, &[("line", &line), ("character", &character)])
+            .await?;
+        let render = markdown::Render {
+            language_id: &content.language_id,
+            max_length: self.max_documentation,
+            markdown: markdown::supported(
+                client.and_then(|client| client.text_document.as_ref()?.signature_help.as_ref()),
+                |signature_help| {
+                    (signature_help.signature_information.as_ref())?
+                        .documentation_format
+                        .as_ref()
+                },
+            ),
+        };
+        Ok(match printed {
+            Some(Printed::SignatureHelp(help)) => Some(lsp::SignatureHelp {
+                signatures: (help.signatures.into_iter())
+                    .map(|signature| document(signature, &render))
+                    .collect(),
+                ..help
+            }),
+            Some(Printed::Text(text)) => {
+                from_text(&text, &render).map(|signature| lsp::SignatureHelp {
+                    signatures: vec![signature],
+                    active_signature: Some(0),
+                    active_parameter: None,
+                })
+            }
+            None => None,
+        })
+    }
+}
+
+impl SignatureHelp {
+    /// The trigger characters of every `proxies`, e.g. of each language when they are registered together.
+    pub fn options<'a>(
+        proxies: impl IntoIterator<Item = &'a Self>,
+    ) -> Option<lsp::SignatureHelpOptions> {
+        let proxies: Vec<_> = proxies.into_iter().collect();
+        if proxies.is_empty() {
+            return None;
+        }
+        let characters = |characters: fn(&Self) -> Option<&Vec<String>>| {
+            let result: Vec<_> = (proxies.iter())
+                .filter_map(|proxy| characters(proxy))
+                .flatten()
+                .cloned()
+                .collect();
+            (!result.is_empty()).then_some(result)
+        };
+        Some(lsp::SignatureHelpOptions {
+            trigger_characters: characters(|proxy| proxy.trigger_characters.as_ref()),
+            retrigger_characters: characters(|proxy| proxy.retrigger_characters.as_ref()),
+            ..Default::default()
+        })
+    }
+}
+
+fn document(
+    signature: lsp::SignatureInformation,
+    render: &markdown::Render,
+) -> lsp::SignatureInformation {
+    lsp::SignatureInformation {
+        documentation: (signature.documentation).map(|it| render.documentation(it)),
+        parameters: (signature.parameters).map(|parameters| {
+            (parameters.into_iter())
+                .map(|parameter| lsp::ParameterInformation {
+                    documentation: (parameter.documentation).map(|it| render.documentation(it)),
+                    ..parameter
+                })
+                .collect()
+        }),
+        ..signature
+    }
+}
+
+/// A signature printed as plain text, its first line being the label and the following ones its documentation.
+fn from_text(text: &str, render: &markdown::Render) -> Option<lsp::SignatureInformation> {
+    let mut lines = text.lines();
+    let label = (lines.by_ref().map(markdown::strip)).find(|line| !line.trim().is_empty())?;
+    let documentation = lines.collect::<Vec<_>>().join("\n");
+    Some(lsp::SignatureInformation {
+        label: label.trim().into(),
+        documentation: (!markdown::strip(&documentation).trim().is_empty()).then(|| {
+            lsp::Documentation::MarkupContent(
+                render.markup(&documentation, lsp::MarkupKind::PlainText),
+            )
+        }),
+        parameters: None,
+        active_parameter: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::{Exec, Parser};
+    use serde_json::json;
+
+    fn signature_help(program: &str, args: &[&str], parser: Parser) -> SignatureHelp {
+        SignatureHelp {
+            proxy: PassThrough::ExecCommand(Exec::test(program, args, parser)),
+            trigger_characters: Some(vec!["(".into()]),
+            retrigger_characters: Some(vec![",".into()]),
+            max_documentation: None,
+        }
+    }
+
+    fn respond(signature_help: &SignatureHelp) -> Option<lsp::SignatureHelp> {
+        let content = Content::test("python", "/mirror/a.py", "");
+        let params: lsp::SignatureHelpParams = serde_json::from_value(json!({
+            "textDocument": {"uri": "file:///mirror/a.py"},
+            "position": {"line": 2, "character": 5},
+        }))
+        .unwrap();
+        smol::block_on(signature_help.proxy_response(params, &content, None)).unwrap()
+    }
+
+    #[test]
+    fn signature_of_plain_text() {
+        let printf = signature_help(
+            "printf",
+            &["\n\x1b[1mopen\x1b[0m(file, mode='r')\nOpen *file* at {line}:{character}.\n"],
+            Parser::Text,
+        );
+        let help = respond(&printf).unwrap();
+        assert_eq!(help.active_signature, Some(0));
+        let [signature] = &help.signatures[..] else {
+            panic!("{help:?}")
+        };
+        assert_eq!(signature.label, "open(file, mode='r')");
+        assert_eq!(
+            signature.documentation,
+            Some(lsp::Documentation::MarkupContent(lsp::MarkupContent {
+                kind: lsp::MarkupKind::Markdown,
+                value: r"Open \*file\* at 2:5.".into(),
+            }))
+        );
+
+        // INFO: nothing but blank lines is no signature
+        assert_eq!(
+            respond(&signature_help("printf", &["\n \n"], Parser::Text)),
+            None
+        );
+    }
+
+    #[test]
+    fn document_each_parameter() {
+        let printed = json!({
+            "signatures": [{
+                "label": "f(a, b)",
+                "documentation": "\x1b[1mf\x1b[0m adds",
+                "parameters": [
+                    {"label": "a", "documentation": {"kind": "markdown", "value": "*first*"}},
+                    {"label": "b"},
+                ],
+            }],
+            "activeParameter": 1,
+        });
+        let echo = signature_help("echo", &[&printed.to_string()], Parser::Json);
+        let help = respond(&echo).unwrap();
+        assert_eq!(help.active_parameter, Some(1));
+        let signature = &help.signatures[0];
+        let markdown = |value: &str| {
+            Some(lsp::Documentation::MarkupContent(lsp::MarkupContent {
+                kind: lsp::MarkupKind::Markdown,
+                value: value.into(),
+            }))
+        };
+        assert_eq!(signature.documentation, markdown("**f** adds"));
+        let parameters = signature.parameters.as_ref().unwrap();
+        assert_eq!(parameters[0].documentation, markdown("*first*"));
+        assert_eq!(parameters[1].documentation, None);
+    }
+
+    #[test]
+    fn options_of_every_proxy() {
+        let (a, b) = (
+            signature_help("true", &[], Parser::Text),
+            signature_help("true", &[], Parser::Text),
+        );
+        let options = SignatureHelp::options([&a, &b]).unwrap();
+        assert_eq!(
+            options.trigger_characters,
+            Some(vec!["(".into(), "(".into()])
+        );
+        assert_eq!(
+            options.retrigger_characters,
+            Some(vec![",".into(), ",".into()])
+        );
+        assert!(SignatureHelp::options([]).is_none());
+    }
+}
@@ -0,0 +1,60 @@
+use super::hierarchy::{exec, origin, restore};
+use super::{PassThrough, Proxy};
+use crate::Content;
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types as lsp;
+
+pub struct TypeHierarchy {
+    pub prepare: PassThrough,
+    pub supertypes: PassThrough,
+    pub subtypes: PassThrough,
+}
+
+impl Proxy for TypeHierarchy {
+    type Params = lsp::TypeHierarchyPrepareParams;
+    type Response = Vec<lsp::TypeHierarchyItem>;
+
+    async fn proxy_response(
+        &self,
+        params: Self::Params,
+        content: &Content,
+        _: Option<&lsp::ClientCapabilities>,
+    ) -> Result<Option<Self::Response>> {
+        let lsp::TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+        let (line, character) = (position.line.to_string(), position.character.to_string());
+        let items: Option<Self::Response> = self
+            .prepare
+            .exec(content, &[("line", &line), ("character", &character)])
+            .await?;
+        Ok(items.map(|items| {
+            (items.into_iter())
+                .map(|item| origin(item, &text_document.uri))
+                .collect()
+        }))
+    }
+}
+
+impl TypeHierarchy {
+    pub async fn supertypes(
+        &self,
+        item: lsp::TypeHierarchyItem,
+        content: &Content,
+    ) -> Result<Option<Vec<lsp::TypeHierarchyItem>>> {
+        let (uri, item) = restore(item);
+        let items: Option<Vec<_>> = exec(&self.supertypes, &item, content).await?;
+        Ok(items.map(|items| items.into_iter().map(|item| origin(item, &uri)).collect()))
+    }
+
+    pub async fn subtypes(
+        &self,
+        item: lsp::TypeHierarchyItem,
+        content: &Content,
+    ) -> Result<Option<Vec<lsp::TypeHierarchyItem>>> {
+        let (uri, item) = restore(item);
+        let items: Option<Vec<_>> = exec(&self.subtypes, &item, content).await?;
+        Ok(items.map(|items| items.into_iter().map(|item| origin(item, &uri)).collect()))
+    }
+}
@@ -0,0 +1,260 @@
+use super::{
+    process::{self, Failure},
+    substitute,
+};
+use crate::Content;
+use serde_json::Value;
+use smol::{
+    future,
+    io::{self, AsyncBufReadExt as _, AsyncReadExt as _, AsyncWriteExt as _, BufReader},
+    lock::{Mutex, Semaphore},
+    process::{Child, ChildStdin, ChildStdout, Stdio},
+    Timer,
+};
+use std::{process::Output, time::Duration};
+
+/// A long-running tool which answer each line written to its stdin with one line on its stdout,
+/// so the spawn cost is only paid once per worker.
+pub struct Worker {
+    pub program: String,
+    pub args: Vec<String>,
+    pub request: Option<String>, // template of a request line, default to a JSON object of every placeholder
+    pub timeout: Option<Duration>,
+    pub max_memory: Option<u64>, // in bytes, a worker using more is restarted after its response (Linux only)
+    pub max_output: Option<usize>, // in bytes of a response line, a worker printing more is restarted
+    pub pool: Pool,
+    pub cache: super::Cache,
+    pub parser: super::Parser,
+}
+
+/// Warm processes of a [`Worker`], at most `size` of them handle a request at the same time.
+pub struct Pool {
+    idle: Mutex<Vec<Process>>,
+    size: Semaphore,
+}
+
+impl Pool {
+    pub fn new(size: usize) -> Self {
+        Self {
+            idle: Mutex::new(vec![]),
+            size: Semaphore::new(size),
+        }
+    }
+}
+
+/// Killed when dropped, e.g. when a request is cancelled or timed out in the middle of a response.
+struct Process {
+    _group: process::Group,
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Worker {
+    /// Send one request framed from `vars` then return the response line.
+    /// The document is read from the mirror, so `{file}` and `{path}` are the same.
+    pub async fn request(
+        &self,
+        content: &Content,
+        vars: &[(&str, &str)],
+    ) -> Result<Vec<u8>, Failure> {
+        let path = content.path.to_string_lossy();
+        let request = self.frame(&[&[("file", path.as_ref()), ("path", &path)], vars].concat());
+
+        let _permit = self.pool.size.acquire().await;
+        let idle = self.pool.idle.lock().await.pop();
+        let reused = idle.is_some();
+        let mut process = match idle {
+            Some(process) => process,
+            None => self.spawn()?,
+        };
+        let response = match self.send(&mut process, &request).await {
+            // INFO: an idle worker may have crashed since its last request, so retry once on a fresh one
+            Err(Failure::Io(kind, _)) if reused && is_crash(kind) => {
+                process = self.spawn()?;
+                self.send(&mut process, &request).await
+            }
+            response => response,
+        }?;
+
+        if self.is_healthy(&mut process) {
+            self.pool.idle.lock().await.push(process);
+        }
+        Ok(response)
+    }
+
+    pub fn argv(&self) -> Vec<String> {
+        [&self.program]
+            .into_iter()
+            .chain(&self.args)
+            .cloned()
+            .collect()
+    }
+
+    fn frame(&self, vars: &[(&str, &str)]) -> String {
+        let request = match &self.request {
+            Some(template) => substitute(template, vars),
+            None => Value::Object(
+                (vars.iter())
+                    .map(|(key, value)| (key.to_string(), value.to_string().into()))
+                    .collect(),
+            )
+            .to_string(),
+        };
+        request.replace('\n', " ") // INFO: a newline would end the request early
+    }
+
+    fn spawn(&self) -> Result<Process, Failure> {
+        let mut child = process::command(&self.program, &self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null()) // INFO: a full stderr pipe would block the worker
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| Failure::Spawn(err.to_string()))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(io::Error::from(io::ErrorKind::BrokenPipe).into());
+        };
+        Ok(Process {
+            _group: process::Group(Some(child.id())),
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        })
+    }
+
+    async fn send(&self, process: &mut Process, request: &str) -> Result<Vec<u8>, Failure> {
+        let exchange = async {
+            process.stdin.write_all(request.as_bytes()).await?;
+            process.stdin.write_all(b"\n").await?;
+            process.stdin.flush().await?;
+            let mut response = vec![];
+            let limit = self.max_output.map_or(u64::MAX, |max| max as u64 + 1);
+            let mut stdout = (&mut process.stdout).take(limit);
+            match stdout.read_until(b'\n', &mut response).await? {
+                0 => Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                // INFO: the rest of the line is still unread, so the worker is dropped with it
+                read if read as u64 == limit && !response.ends_with(b"\n") => {
+                    let _ = process.child.kill();
+                    Err(Failure::TooLarge(Output {
+                        status: process.child.status().await?,
+                        stdout: response,
+                        stderr: vec![],
+                    }))
+                }
+                _ => Ok(response),
+            }
+        };
+        match self.timeout {
+            Some(timeout) => {
+                let timer = async {
+                    Timer::after(timeout).await;
+                    Err(Failure::TimedOut)
+                };
+                future::or(exchange, timer).await
+            }
+            None => exchange.await,
+        }
+    }
+
+    fn is_healthy(&self, process: &mut Process) -> bool {
+        let alive = matches!(process.child.try_status(), Ok(None));
+        let within_memory = (self.max_memory)
+            .zip(rss(process.child.id()))
+            .is_none_or(|(max, rss)| rss <= max);
+        alive && within_memory
+    }
+}
+
+fn is_crash(kind: io::ErrorKind) -> bool {
+    matches!(
+        kind,
+        io::ErrorKind::UnexpectedEof | io::ErrorKind::BrokenPipe
+    )
+}
+
+/// Resident memory of a process in bytes.
+#[cfg(target_os = "linux")]
+fn rss(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    let kb = (status.lines())
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim();
+    Some(kb.parse::<u64>().ok()? * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn rss(_: u32) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `cat` echoes each request line, so the response is the framed request itself.
+    fn cat(request: Option<&str>, max_output: Option<usize>) -> Worker {
+        Worker {
+            program: "cat".into(),
+            args: vec![],
+            request: request.map(Into::into),
+            timeout: Some(Duration::from_secs(5)),
+            max_memory: None,
+            max_output,
+            pool: Pool::new(1),
+            cache: super::super::Cache::new(1),
+            parser: super::super::Parser::Text,
+        }
+    }
+
+    #[test]
+    fn frame_a_template() {
+        let worker = cat(Some("{file} {line}:{character} {word}"), None);
+        let content = Content::test("python", "/mirror/main.py", "");
+        let vars = [("line", "3"), ("character", "4"), ("word", "a\nb")];
+        let response = smol::block_on(worker.request(&content, &vars));
+        assert_eq!(response.unwrap(), b"/mirror/main.py 3:4 a b\n");
+    }
+
+    #[test]
+    fn frame_a_json_object() {
+        let worker = cat(None, None);
+        let content = Content::test("python", "/mirror/main.py", "");
+        let response = smol::block_on(worker.request(&content, &[("line", "3")])).unwrap();
+        let request: Value = serde_json::from_slice(&response).unwrap();
+        assert_eq!(
+            request,
+            serde_json::json!({"file": "/mirror/main.py", "path": "/mirror/main.py", "line": "3"})
+        );
+    }
+
+    #[test]
+    fn reuse_the_worker() {
+        let worker = cat(Some("{line}"), None);
+        let content = Content::test("python", "/mirror/main.py", "");
+        smol::block_on(async {
+            let first = worker.request(&content, &[("line", "1")]).await.unwrap();
+            let pid = worker.pool.idle.lock().await[0].child.id();
+            let second = worker.request(&content, &[("line", "2")]).await.unwrap();
+            assert_eq!((first, second), (b"1\n".to_vec(), b"2\n".to_vec()));
+            assert_eq!(worker.pool.idle.lock().await[0].child.id(), pid);
+        });
+    }
+
+    #[test]
+    fn drop_a_worker_printing_too_much() {
+        let worker = cat(Some("{line}"), Some(4));
+        let content = Content::test("python", "/mirror/main.py", "");
+        smol::block_on(async {
+            let response = worker.request(&content, &[("line", "123456789")]).await;
+            assert!(
+                matches!(response, Err(Failure::TooLarge(output)) if output.stdout == b"12345")
+            );
+            assert!(worker.pool.idle.lock().await.is_empty());
+            let response = worker.request(&content, &[("line", "1")]).await;
+            assert_eq!(response.unwrap(), b"1\n");
+        });
+    }
+}
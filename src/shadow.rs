@@ -0,0 +1,127 @@
+use smol::{fs, io, lock::Mutex, stream::StreamExt as _};
+use std::path::{Component, Path};
+
+/// Held while changing the mirrors, since documents opened together may share a directory to materialize.
+static MIRRORS: Mutex<()> = Mutex::new(());
+
+/// Symlink every entry of `workspace` into `mirror`, except those which already exist.
+/// This let tools resolve imports or project config (e.g. `bsconfig.json`) as if they run in the workspace,
+/// while only open documents are replaced with real files (see [`materialize`]).
+pub async fn link(workspace: &Path, mirror: &Path) -> io::Result<()> {
+    let _mirrors = MIRRORS.lock().await;
+    link_entries(workspace, mirror).await
+}
+
+async fn link_entries(workspace: &Path, mirror: &Path) -> io::Result<()> {
+    fs::create_dir_all(mirror).await?;
+    let mut entries = fs::read_dir(workspace).await?;
+    while let Some(entry) = entries.try_next().await? {
+        let target = mirror.join(entry.file_name());
+        if fs::symlink_metadata(&target).await.is_err() {
+            symlink(&entry.path(), &target).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Turn every symlinked directory along `relative` path into a real directory (whose entries are symlinked),
+/// then remove the symlink of the file itself, so it can be written without touching the workspace.
+pub async fn materialize(workspace: &Path, mirror: &Path, relative: &Path) -> io::Result<()> {
+    let _mirrors = MIRRORS.lock().await;
+    let (mut source, mut target) = (workspace.to_path_buf(), mirror.to_path_buf());
+    let dirs = relative
+        .parent()
+        .map(Path::components)
+        .into_iter()
+        .flatten();
+    for dir in dirs.filter(|component| matches!(component, Component::Normal(_))) {
+        source.push(dir);
+        target.push(dir);
+        if is_symlink(&target).await {
+            fs::remove_file(&target).await?;
+            fs::create_dir(&target).await?;
+            link_entries(&source, &target).await?;
+        }
+    }
+
+    let file = mirror.join(relative);
+    if is_symlink(&file).await {
+        fs::remove_file(&file).await?;
+    }
+    Ok(())
+}
+
+/// Put back the symlink of a document which no longer has unsaved edits.
+pub async fn restore(workspace: &Path, mirror: &Path, relative: &Path) -> io::Result<()> {
+    let _mirrors = MIRRORS.lock().await;
+    let (source, target) = (workspace.join(relative), mirror.join(relative));
+    if fs::symlink_metadata(&target).await.is_ok() {
+        fs::remove_file(&target).await?;
+    }
+    if fs::metadata(&source).await.is_ok() {
+        symlink(&source, &target).await?;
+    }
+    Ok(())
+}
+
+async fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .await
+        .is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+#[cfg(unix)]
+async fn symlink(source: &Path, target: &Path) -> io::Result<()> {
+    fs::unix::symlink(source, target).await
+}
+
+#[cfg(windows)]
+async fn symlink(source: &Path, target: &Path) -> io::Result<()> {
+    match fs::metadata(source).await?.is_dir() {
+        true => fs::windows::symlink_dir(source, target).await,
+        false => fs::windows::symlink_file(source, target).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn materialize_concurrently() {
+        smol::block_on(async {
+            let dir = std::env::temp_dir().join(format!("lspcat-shadow-{}", std::process::id()));
+            let (workspace, mirror) = (dir.join("workspace"), dir.join("mirror"));
+            std::fs::create_dir_all(workspace.join("src/nested")).unwrap();
+            let files = ["src/nested/a.c", "src/nested/b.c", "src/c.c", "d.c"];
+            for file in files {
+                std::fs::write(workspace.join(file), file).unwrap();
+            }
+            std::fs::write(workspace.join("src/nested/other.c"), "").unwrap();
+            link(&workspace, &mirror).await.unwrap();
+
+            let opened = files.map(|file| {
+                let (workspace, mirror) = (workspace.clone(), mirror.clone());
+                smol::spawn(async move { materialize(&workspace, &mirror, Path::new(file)).await })
+            });
+            for task in opened {
+                task.await.unwrap();
+            }
+            for dir in ["src", "src/nested"] {
+                let metadata = std::fs::symlink_metadata(mirror.join(dir)).unwrap();
+                assert!(metadata.is_dir(), "{dir} is still a symlink");
+            }
+            for file in files {
+                assert!(!mirror.join(file).exists(), "{file} wasn't removed");
+                assert_eq!(std::fs::read_to_string(workspace.join(file)).unwrap(), file);
+            }
+            // INFO: the other entries of a materialized directory are still linked
+            assert!(is_symlink(&mirror.join("src/nested/other.c")).await);
+            restore(&workspace, &mirror, Path::new("src/c.c"))
+                .await
+                .unwrap();
+            assert!(is_symlink(&mirror.join("src/c.c")).await);
+            std::fs::remove_dir_all(&dir).unwrap();
+        })
+    }
+}
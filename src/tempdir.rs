@@ -0,0 +1,198 @@
+use smol::{fs, io, stream::StreamExt as _};
+use std::{
+    ops::Deref,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const PREFIX: &str = "lspcat-";
+const LOCK: &str = ".lock";
+
+/// Directory holding the mirrors, named by a stable hash of the workspace roots
+/// so tools which cache by absolute path (e.g. a build cache) can reuse it across sessions.
+/// It is held by an advisory lock until dropped, which the OS release even if lspcat crash.
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf,
+    stable: bool, // `false` when the stable dir is held by another lspcat, so this one is per process
+    _lock: std::fs::File,
+}
+
+impl TempDir {
+    /// Take the stable dir of the `roots`, whose mirrors left by a crashed session are removed,
+    /// or a per process one while another lspcat hold it.
+    pub async fn acquire(base: &Path, roots: &[&str]) -> io::Result<Self> {
+        let mut roots = roots.to_vec();
+        roots.sort_unstable();
+        let name = format!("{PREFIX}{:016x}", hash(&roots.join(" ")));
+
+        let path = base.join(&name);
+        fs::create_dir_all(&path).await?;
+        if let Some(lock) = try_lock(&path.join(LOCK))? {
+            let dir = Self {
+                path,
+                stable: true,
+                _lock: lock,
+            };
+            dir.clean().await?;
+            return Ok(dir);
+        }
+
+        let path = base.join(format!("{name}-{}", std::process::id()));
+        fs::create_dir_all(&path).await?;
+        match try_lock(&path.join(LOCK))? {
+            Some(lock) => Ok(Self {
+                path,
+                stable: false,
+                _lock: lock,
+            }),
+            None => Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!("{} is locked", path.display()),
+            )),
+        }
+    }
+
+    /// Remove the mirrors, but keep a stable dir for the next session.
+    pub async fn clean(&self) -> io::Result<()> {
+        if !self.stable {
+            return fs::remove_dir_all(&self.path).await;
+        }
+        for dir in ["workspace", "root"] {
+            match fs::remove_dir_all(self.path.join(dir)).await {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Remove every `lspcat-*` dir in `base` which no process hold and which wasn't touched within `ttl`.
+pub async fn gc(base: &Path, ttl: Duration) -> io::Result<()> {
+    let mut entries = fs::read_dir(base).await?;
+    while let Some(entry) = entries.try_next().await? {
+        if !entry.file_name().to_string_lossy().starts_with(PREFIX) {
+            continue;
+        }
+        let path = entry.path();
+        // INFO: check the age first since taking the lock touch the dir
+        let stale = fs::symlink_metadata(&path)
+            .await
+            .and_then(|metadata| {
+                Ok(metadata.is_dir() && metadata.modified()?.elapsed().unwrap_or_default() > ttl)
+            })
+            .unwrap_or(false);
+        if !stale {
+            continue;
+        }
+        if let Ok(Some(lock)) = try_lock(&path.join(LOCK)) {
+            drop(lock);
+            let _ = fs::remove_dir_all(&path).await;
+        }
+    }
+    Ok(())
+}
+
+/// FNV-1a, unlike [`std::collections::hash_map::DefaultHasher`] it is stable across Rust releases.
+fn hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Take the lock without waiting, or return `None` when another process hold it.
+#[cfg(unix)]
+fn try_lock(path: &Path) -> io::Result<Option<std::fs::File>> {
+    use std::{io::Write as _, os::fd::AsRawFd as _};
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?;
+    // SAFETY: `file` own a valid file descriptor
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        let err = io::Error::last_os_error();
+        return match err.kind() {
+            io::ErrorKind::WouldBlock => Ok(None),
+            _ => Err(err),
+        };
+    }
+    file.set_len(0)?;
+    write!(file, "{}", std::process::id())?; // INFO: only for humans
+    Ok(Some(file))
+}
+
+/// Take the lock without waiting, or return `None` when another process hold it.
+#[cfg(windows)]
+fn try_lock(path: &Path) -> io::Result<Option<std::fs::File>> {
+    use std::os::windows::fs::OpenOptionsExt as _;
+
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    // INFO: opening without sharing is exclusive until the handle is closed
+    match std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .share_mode(0)
+        .open(path)
+    {
+        Ok(file) => Ok(Some(file)),
+        Err(err) if err.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acquire_fallback_and_gc() {
+        smol::block_on(async {
+            let base = std::env::temp_dir().join(format!("lspcat-test-{}", std::process::id()));
+            let base = base.join("tmp"); // INFO: so that gc doesn't see the test dir itself
+            std::fs::create_dir_all(&base).unwrap();
+
+            let stable = TempDir::acquire(&base, &["/b", "/a"]).await.unwrap();
+            assert!(stable.stable);
+            std::fs::create_dir_all(stable.join("workspace/0")).unwrap();
+            std::fs::write(stable.join("workspace/0/stale.c"), "").unwrap();
+            std::fs::write(stable.join("cache"), "").unwrap();
+
+            // INFO: the lock is per open file, so this process hold it against itself
+            let other = TempDir::acquire(&base, &["/a", "/b"]).await.unwrap();
+            assert!(!other.stable);
+            let fallback = format!("{}-{}", stable.display(), std::process::id());
+            assert_eq!(other.path, PathBuf::from(fallback));
+
+            // INFO: as if lspcat crashed, the mirrors are left behind
+            let path = stable.path.clone();
+            drop(stable);
+            let stable = TempDir::acquire(&base, &["/a", "/b"]).await.unwrap();
+            assert!(stable.stable && stable.path == path);
+            assert!(!stable.join("workspace").exists());
+            assert!(stable.join("cache").exists());
+
+            smol::Timer::after(Duration::from_millis(10)).await;
+            let other_path = other.path.clone();
+            drop(other);
+            std::fs::create_dir(base.join("unrelated")).unwrap();
+            gc(&base, Duration::ZERO).await.unwrap();
+            assert!(stable.exists()); // INFO: still held
+            assert!(!other_path.exists());
+            assert!(base.join("unrelated").exists());
+
+            std::fs::remove_dir_all(base.parent().unwrap()).unwrap();
+        })
+    }
+}